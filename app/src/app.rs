@@ -4,13 +4,18 @@ use crate::search_tab::SearchTab;
 use crate::settings_tab::SettingsTab;
 use crate::vgtk_ext::*;
 use database::DatabaseAction;
+use futures::channel::oneshot;
 use libhandy::{
     CenteringPolicy, HeaderBar, HeaderBarExt, Squeezer, SqueezerExt, ViewSwitcher, ViewSwitcherBar,
     ViewSwitcherBarExt, ViewSwitcherExt, ViewSwitcherPolicy,
 };
-use loader::{Loader, Query};
-use player::PlayerAction;
-use state::{ChannelRef, CurrentState, EpisodeRef, Image as ImageObj, State, StateAction};
+use loader::{is_feed_url, Loader, Query};
+use player::{PlayerAction, VideoSink};
+use state::{
+    ChannelRef, CurrentState, DownloadPolicy, EnclosurePreference, EpisodeRef, Image as ImageObj,
+    Playback, QueueItem, State, StateAction,
+};
+use std::path::PathBuf;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use vgtk::lib::gio::ApplicationFlags;
@@ -35,11 +40,14 @@ pub enum Message {
     SetMobile(bool),
     SetTab(Tab),
     SetSearchDetail(Option<ChannelRef>),
+    SetSearchEpisode(Option<EpisodeRef>),
     SetSearchQuery(String),
     SetHomeDetail(Option<ChannelRef>),
+    SetHomeEpisode(Option<EpisodeRef>),
 
     // Playback
     HandlePlay(EpisodeRef),
+    HandlePlayAt(EpisodeRef, u64),
     HandleSkipBack,
     HandleSkipForward,
     HandlePause,
@@ -47,13 +55,44 @@ pub enum Message {
     HandleSeek(u64),
     HandleRateChange(f64),
 
+    // Queue
+    HandleEnqueue(EpisodeRef),
+    HandlePlayNext(EpisodeRef),
+    HandleSkipToNext,
+    HandleNextChapter,
+    HandlePreviousChapter,
+    HandleQueueMove(usize, usize),
+    HandleQueueRemove(usize),
+    HandleQueueClear,
+
     // Subscription
     HandleSubscribe(ChannelRef),
     HandleUnsubscribe(ChannelRef),
 
+    // Played
+    HandleMarkPlayed(EpisodeRef),
+    HandleMarkUnplayed(EpisodeRef),
+    HandleDownload(EpisodeRef),
+    HandleCancelDownload(EpisodeRef),
+    HandleDeleteDownload(EpisodeRef),
+    HandleDownloadPolicy(ChannelRef, DownloadPolicy),
+    HandleEnclosurePreference(EnclosurePreference),
+    HandleVideoAsAudio(bool),
+    HandlePlayedThreshold(u64),
+
+    // Import and export
+    HandleImportOpml(PathBuf),
+    HandleExportOpml(PathBuf),
+    SetOpmlStatus(String),
+
     // External
     InitDispatch(Arc<CurrentState>),
-    Init(Loader, Sender<PlayerAction>, Sender<DatabaseAction>),
+    Init(
+        Loader,
+        Sender<PlayerAction>,
+        Sender<DatabaseAction>,
+        Option<VideoSink>,
+    ),
     StateChanged(Arc<State>),
 }
 
@@ -72,9 +111,22 @@ pub struct App {
     loader: Option<Loader>,
     player: Option<Sender<PlayerAction>>,
     database: Option<Sender<DatabaseAction>>,
+    video_sink: Option<VideoSink>,
+    opml_status: Option<String>,
 }
 
-impl App {}
+impl App {
+    /// Once the user leaves a channel, they've seen its new episodes.
+    fn clear_new_episodes(&self, prev: Option<&ChannelRef>, next: Option<&ChannelRef>) {
+        if let (Some(database), Some(prev)) = (&self.database, prev) {
+            if Some(prev) != next && prev.new_episode_count() > 0 {
+                database
+                    .send(DatabaseAction::ClearNewEpisodes(prev.clone()))
+                    .unwrap();
+            }
+        }
+    }
+}
 
 impl Component for App {
     type Message = Message;
@@ -85,6 +137,8 @@ impl Component for App {
             Message::None => UpdateAction::None,
             Message::Quit => {
                 eprintln!("exit");
+                // The database thread stops once nothing can send to it.
+                self.database = None;
                 vgtk::quit();
                 UpdateAction::None
             }
@@ -101,19 +155,40 @@ impl Component for App {
                 }
             }
             Message::SetSearchDetail(search) => {
+                self.clear_new_episodes(self.state.search_focus(), search.as_ref());
                 if let Some(current) = &self.current {
                     current.update(vec![StateAction::SetSearchFocus(search.clone())]);
                 }
                 if let Some(loader) = &self.loader {
                     if let Some(search) = search {
-                        loader.queue(Query::ItunesLookup {
-                            pk: search.pk().to_owned(),
-                        });
+                        loader.queue(Query::channel(search.pk().to_owned()));
                     }
                 }
                 UpdateAction::None
             }
+            Message::SetSearchEpisode(episode) => {
+                if let Some(current) = &self.current {
+                    current.update(vec![StateAction::SetSearchEpisodeFocus(episode)]);
+                }
+                UpdateAction::None
+            }
             Message::SetSearchQuery(search) => {
+                let feed_url = search.trim();
+                if is_feed_url(feed_url) {
+                    // Feeds that aren't in iTunes can be added by URL. Show it as the only result.
+                    if let (Some(current), Some(loader)) = (&self.current, &self.loader) {
+                        current.update(vec![
+                            StateAction::SetSearchQuery(search.clone()),
+                            StateAction::SetSearchFeed {
+                                query: search.clone(),
+                                results: Ok(vec![feed_url.to_owned()]),
+                            },
+                        ]);
+                        loader.queue(Query::channel(feed_url.to_owned()));
+                    }
+                    return UpdateAction::None;
+                }
+
                 if let Some(current) = &self.current {
                     current.update(vec![StateAction::SetSearchQuery(search.clone())]);
                 }
@@ -127,18 +202,23 @@ impl Component for App {
                 UpdateAction::None
             }
             Message::SetHomeDetail(channel) => {
+                self.clear_new_episodes(self.state.home_focus(), channel.as_ref());
                 if let Some(current) = &self.current {
                     current.update(vec![StateAction::SetHomeFocus(channel.clone())]);
                 }
                 if let Some(loader) = &self.loader {
                     if let Some(channel) = channel {
-                        loader.queue(Query::ItunesLookup {
-                            pk: channel.pk().to_owned(),
-                        });
+                        loader.queue(Query::channel(channel.pk().to_owned()));
                     }
                 }
                 UpdateAction::None
             }
+            Message::SetHomeEpisode(episode) => {
+                if let Some(current) = &self.current {
+                    current.update(vec![StateAction::SetHomeEpisodeFocus(episode)]);
+                }
+                UpdateAction::None
+            }
 
             // Playback
             Message::HandlePlay(episode) => {
@@ -148,7 +228,7 @@ impl Component for App {
                         .send(PlayerAction::PlayRemote {
                             episode_pk: episode.pk().to_owned(),
                             channel_pk: episode.channel().pk().to_owned(),
-                            uri: episode.audio().to_owned(),
+                            uri: episode.media_url().to_owned(),
                         })
                         .unwrap();
 
@@ -166,6 +246,22 @@ impl Component for App {
 
                 UpdateAction::None
             }
+            Message::HandlePlayAt(episode, time) => {
+                let player_state = self.state.player_state();
+                let player_state = player_state.as_ref().as_ref();
+                let playing = player_state.map_or(false, |state| state.episode_pk == episode.pk());
+
+                if !playing {
+                    self.update(Message::HandlePlay(episode));
+                }
+                if let Some(player) = &self.player {
+                    player.send(PlayerAction::SetTime(time)).unwrap();
+                    if player_state.map_or(false, |state| state.playback == Playback::Paused) {
+                        player.send(PlayerAction::Unpause).unwrap();
+                    }
+                }
+                UpdateAction::None
+            }
             Message::HandleSkipBack => {
                 let time = self
                     .state
@@ -221,6 +317,70 @@ impl Component for App {
                 UpdateAction::None
             }
 
+            // Queue
+            Message::HandleEnqueue(episode) => {
+                if let (Some(player), Some(Ok(episode))) = (&self.player, episode.get().as_deref())
+                {
+                    player
+                        .send(PlayerAction::Enqueue(QueueItem {
+                            episode_pk: episode.pk().to_owned(),
+                            channel_pk: episode.channel().pk().to_owned(),
+                            uri: episode.media_url().to_owned(),
+                        }))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandlePlayNext(episode) => {
+                if let (Some(player), Some(Ok(episode))) = (&self.player, episode.get().as_deref())
+                {
+                    player
+                        .send(PlayerAction::PlayNext(QueueItem {
+                            episode_pk: episode.pk().to_owned(),
+                            channel_pk: episode.channel().pk().to_owned(),
+                            uri: episode.media_url().to_owned(),
+                        }))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleSkipToNext => {
+                if let Some(player) = &self.player {
+                    player.send(PlayerAction::SkipToNext).unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleNextChapter => {
+                if let Some(player) = &self.player {
+                    player.send(PlayerAction::NextChapter).unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandlePreviousChapter => {
+                if let Some(player) = &self.player {
+                    player.send(PlayerAction::PreviousChapter).unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleQueueMove(from, to) => {
+                if let Some(player) = &self.player {
+                    player.send(PlayerAction::MoveInQueue { from, to }).unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleQueueRemove(idx) => {
+                if let Some(player) = &self.player {
+                    player.send(PlayerAction::RemoveFromQueue(idx)).unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleQueueClear => {
+                if let Some(player) = &self.player {
+                    player.send(PlayerAction::ClearQueue).unwrap();
+                }
+                UpdateAction::None
+            }
+
             // Subscribe
             Message::HandleSubscribe(channel) => {
                 if let Some(database) = &self.database {
@@ -235,15 +395,130 @@ impl Component for App {
                 UpdateAction::None
             }
 
+            // Played
+            Message::HandleMarkPlayed(episode) => {
+                if let Some(database) = &self.database {
+                    database.send(DatabaseAction::MarkPlayed(episode)).unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleMarkUnplayed(episode) => {
+                if let Some(database) = &self.database {
+                    database
+                        .send(DatabaseAction::MarkUnplayed(episode))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+
+            // Downloads
+            Message::HandleDownload(episode) => {
+                if let Some(database) = &self.database {
+                    database.send(DatabaseAction::Download(episode)).unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleCancelDownload(episode) => {
+                if let Some(database) = &self.database {
+                    database
+                        .send(DatabaseAction::CancelDownload(episode))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleDeleteDownload(episode) => {
+                if let Some(database) = &self.database {
+                    database
+                        .send(DatabaseAction::DeleteDownload(episode))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleDownloadPolicy(channel, policy) => {
+                if let Some(database) = &self.database {
+                    database
+                        .send(DatabaseAction::SetDownloadPolicy(channel, policy))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleEnclosurePreference(preference) => {
+                if let Some(database) = &self.database {
+                    database
+                        .send(DatabaseAction::SetEnclosurePreference(preference))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandleVideoAsAudio(video_as_audio) => {
+                if let Some(database) = &self.database {
+                    database
+                        .send(DatabaseAction::SetVideoAsAudio(video_as_audio))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+            Message::HandlePlayedThreshold(played_threshold) => {
+                if let Some(database) = &self.database {
+                    database
+                        .send(DatabaseAction::SetPlayedThreshold(played_threshold))
+                        .unwrap();
+                }
+                UpdateAction::None
+            }
+
+            // Import and export
+            Message::HandleImportOpml(path) => {
+                if let Some(database) = &self.database {
+                    let (done, result) = oneshot::channel();
+                    database
+                        .send(DatabaseAction::ImportOpml(path, done))
+                        .unwrap();
+
+                    UpdateAction::defer(async move {
+                        Message::SetOpmlStatus(match result.await {
+                            Ok(Ok(count)) => format!("Subscribed to {} new podcasts.", count),
+                            Ok(Err(err)) => format!("Could not import subscriptions: {}", err),
+                            Err(_) => "Could not import subscriptions.".to_owned(),
+                        })
+                    })
+                } else {
+                    UpdateAction::None
+                }
+            }
+            Message::HandleExportOpml(path) => {
+                if let Some(database) = &self.database {
+                    let (done, result) = oneshot::channel();
+                    database
+                        .send(DatabaseAction::ExportOpml(path, done))
+                        .unwrap();
+
+                    UpdateAction::defer(async move {
+                        Message::SetOpmlStatus(match result.await {
+                            Ok(Ok(count)) => format!("Exported {} podcasts.", count),
+                            Ok(Err(err)) => format!("Could not export subscriptions: {}", err),
+                            Err(_) => "Could not export subscriptions.".to_owned(),
+                        })
+                    })
+                } else {
+                    UpdateAction::None
+                }
+            }
+            Message::SetOpmlStatus(status) => {
+                self.opml_status = Some(status);
+                UpdateAction::Render
+            }
+
             // External
             Message::InitDispatch(current) => {
                 self.current = Some(current);
                 UpdateAction::None
             }
-            Message::Init(loader, player, database) => {
+            Message::Init(loader, player, database, video_sink) => {
                 self.loader = Some(loader);
                 self.player = Some(player);
                 self.database = Some(database);
+                self.video_sink = video_sink;
                 UpdateAction::None
             }
             Message::StateChanged(state) => {
@@ -255,6 +530,14 @@ impl Component for App {
 
     fn view(&self) -> VNode<App> {
         let tab = self.tab;
+        let home_episode = self.state.home_episode_focus().is_some();
+        let search_episode = self.state.search_episode_focus().is_some();
+        let queue: Vec<EpisodeRef> = self
+            .state
+            .queue()
+            .iter()
+            .map(|item| self.state.episode_ref(item.episode_pk.clone()))
+            .collect();
 
         gtk! {
             <Application::new_unwrap(Some("ca.nettek.pyrocast"), ApplicationFlags::empty())>
@@ -295,7 +578,14 @@ impl Component for App {
                         <Button
                             visible=self.state.home_focus().is_some() && tab == Tab::Home
                             valign=Align::Center
-                            on clicked=|_| Message::SetHomeDetail(None)
+                            on clicked=|_| {
+                                // Go back to the podcast first if an episode is open.
+                                if home_episode {
+                                    Message::SetHomeEpisode(None)
+                                } else {
+                                    Message::SetHomeDetail(None)
+                                }
+                            }
                         >
                             <Image
                                 property_icon_name="go-previous-symbolic"
@@ -305,7 +595,13 @@ impl Component for App {
                         <Button
                             visible=self.state.search_focus().is_some() && tab == Tab::Search
                             valign=Align::Center
-                            on clicked=|_| Message::SetSearchDetail(None)
+                            on clicked=|_| {
+                                if search_episode {
+                                    Message::SetSearchEpisode(None)
+                                } else {
+                                    Message::SetSearchDetail(None)
+                                }
+                            }
                         >
                             <Image
                                 property_icon_name="go-previous-symbolic"
@@ -352,12 +648,20 @@ impl Component for App {
                                 <@NowPlaying
                                     player_state=self.state.player_state()
                                     episode_info=self.state.playing_episode()
+                                    video=self.video_sink.as_ref().and_then(|sink| sink.widget())
                                     on skip_back=|_| Message::HandleSkipBack
                                     on skip_forward=|_| Message::HandleSkipForward
                                     on pause=|_| Message::HandlePause
                                     on unpause=|_| Message::HandleUnpause
                                     on seek=|t| Message::HandleSeek(t)
                                     on rate_change=|rate| Message::HandleRateChange(rate)
+                                    on next_chapter=|_| Message::HandleNextChapter
+                                    on previous_chapter=|_| Message::HandlePreviousChapter
+                                    queue=queue
+                                    on skip_to_next=|_| Message::HandleSkipToNext
+                                    on queue_move=|(from, to)| Message::HandleQueueMove(from, to)
+                                    on queue_remove=|idx| Message::HandleQueueRemove(idx)
+                                    on queue_clear=|_| Message::HandleQueueClear
                                 />
                             </GtkBox>
                             <GtkBox
@@ -368,12 +672,24 @@ impl Component for App {
                             >
                                 <@HomeTab
                                     selected_podcast=self.state.home_focus().cloned()
+                                    selected_episode=self.state.home_episode_focus().cloned()
                                     mobile=self.mobile
                                     subscriptions=Some(self.state.subscriptions())
+                                    new_episodes=self.state.new_episodes()
                                     on select_podcast=|podcast| Message::SetHomeDetail(podcast)
+                                    on select_episode=|episode| Message::SetHomeEpisode(Some(episode))
                                     on play=|episode| Message::HandlePlay(episode)
+                                    on enqueue=|episode| Message::HandleEnqueue(episode)
+                                    on play_next=|episode| Message::HandlePlayNext(episode)
+                                    on play_at=|play_at| Message::HandlePlayAt(play_at.0, play_at.1)
                                     on subscribe=|channel| Message::HandleSubscribe(channel)
                                     on unsubscribe=|channel| Message::HandleUnsubscribe(channel)
+                                    on mark_played=|episode| Message::HandleMarkPlayed(episode)
+                                    on mark_unplayed=|episode| Message::HandleMarkUnplayed(episode)
+                                    on download=|episode| Message::HandleDownload(episode)
+                                    on cancel_download=|episode| Message::HandleCancelDownload(episode)
+                                    on delete_download=|episode| Message::HandleDeleteDownload(episode)
+                                    on download_policy=|policy| Message::HandleDownloadPolicy(policy.0, policy.1)
                                 />
                             </GtkBox>
                             <GtkBox
@@ -385,13 +701,24 @@ impl Component for App {
                                 <@SearchTab
                                     chart_results=Some(self.state.search_results())
                                     selected_podcast=self.state.search_focus().cloned()
+                                    selected_episode=self.state.search_episode_focus().cloned()
                                     mobile=self.mobile
                                     subscriptions=Some(self.state.subscriptions())
                                     on select_podcast=|podcast| Message::SetSearchDetail(podcast)
+                                    on select_episode=|episode| Message::SetSearchEpisode(Some(episode))
                                     on play=|episode| Message::HandlePlay(episode)
+                                    on enqueue=|episode| Message::HandleEnqueue(episode)
+                                    on play_next=|episode| Message::HandlePlayNext(episode)
+                                    on play_at=|play_at| Message::HandlePlayAt(play_at.0, play_at.1)
                                     on search=|search| Message::SetSearchQuery(search)
                                     on subscribe=|channel| Message::HandleSubscribe(channel)
                                     on unsubscribe=|channel| Message::HandleUnsubscribe(channel)
+                                    on mark_played=|episode| Message::HandleMarkPlayed(episode)
+                                    on mark_unplayed=|episode| Message::HandleMarkUnplayed(episode)
+                                    on download=|episode| Message::HandleDownload(episode)
+                                    on cancel_download=|episode| Message::HandleCancelDownload(episode)
+                                    on delete_download=|episode| Message::HandleDeleteDownload(episode)
+                                    on download_policy=|policy| Message::HandleDownloadPolicy(policy.0, policy.1)
                                 />
                             </GtkBox>
                             <GtkBox
@@ -400,7 +727,17 @@ impl Component for App {
                                 Stack::selected=tab == Tab::Settings
                                 Stack::name="pyrocast_tab_settings"
                             >
-                                <@SettingsTab />
+                                <@SettingsTab
+                                    opml_status=self.opml_status.clone()
+                                    enclosure_preference=self.state.enclosure_preference()
+                                    video_as_audio=self.state.video_as_audio()
+                                    played_threshold=self.state.played_threshold()
+                                    on import_opml=|path| Message::HandleImportOpml(path)
+                                    on export_opml=|path| Message::HandleExportOpml(path)
+                                    on enclosure_preference=|preference| Message::HandleEnclosurePreference(preference)
+                                    on video_as_audio=|video_as_audio| Message::HandleVideoAsAudio(video_as_audio)
+                                    on played_threshold=|played_threshold| Message::HandlePlayedThreshold(played_threshold)
+                                />
                             </GtkBox>
                         </Stack>
                         <ViewSwitcherBar
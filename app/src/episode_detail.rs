@@ -0,0 +1,246 @@
+use crate::fixed_image::FixedImage;
+use crate::people::People;
+use crate::search_detail::format_duration;
+use crate::vgtk_ext::*;
+use libhandy::{Column, ColumnExt};
+use pango::{AttrList, Attribute, Variant, Weight};
+use state::{Episode, EpisodeRef, Image, Person, StateError};
+use std::sync::Arc;
+use vgtk::lib::gtk::{
+    prelude::*, Align, Box as GtkBox, Button, Label, Orientation, ScrolledWindow, Viewport,
+};
+use vgtk::{ext::*, gtk, Callback, Component, UpdateAction, VNode};
+use xml::escape::escape_str_pcdata;
+
+#[derive(Debug, Default, Clone)]
+pub struct Props {
+    pub episode: Option<EpisodeRef>,
+    pub on_play: Callback<EpisodeRef>,
+    pub on_enqueue: Callback<EpisodeRef>,
+    pub on_play_next: Callback<EpisodeRef>,
+    /// Timestamps in the notes play the episode from that time, in ms.
+    pub on_play_at: Callback<(EpisodeRef, u64)>,
+}
+
+/// An episode's show notes, with its title and what else we know about it.
+#[derive(Debug, Default, Clone)]
+pub struct EpisodeDetail {
+    props: Props,
+    prev_episode: Option<Arc<Result<Episode, StateError>>>,
+    prev_persons: Vec<(Person, Option<Arc<Result<Image, StateError>>>)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    HandlePlay,
+    HandleEnqueue,
+    HandlePlayNext,
+}
+
+/// Who is on the episode, with their images if they have loaded.
+fn persons(
+    episode: Option<&Arc<Result<Episode, StateError>>>,
+) -> Vec<(Person, Option<Arc<Result<Image, StateError>>>)> {
+    match episode.map(|episode| episode.as_ref()) {
+        Some(Ok(episode)) => episode
+            .persons()
+            .iter()
+            .map(|person| (person.clone(), episode.person_image(person)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+impl Component for EpisodeDetail {
+    type Message = Message;
+    type Properties = Props;
+
+    fn update(&mut self, message: Message) -> UpdateAction<Self> {
+        match message {
+            Message::HandlePlay => {
+                if let Some(episode) = &self.props.episode {
+                    self.props.on_play.send(episode.clone());
+                }
+                UpdateAction::None
+            }
+            Message::HandleEnqueue => {
+                if let Some(episode) = &self.props.episode {
+                    self.props.on_enqueue.send(episode.clone());
+                }
+                UpdateAction::None
+            }
+            Message::HandlePlayNext => {
+                if let Some(episode) = &self.props.episode {
+                    self.props.on_play_next.send(episode.clone());
+                }
+                UpdateAction::None
+            }
+        }
+    }
+
+    fn create(props: Self::Properties) -> Self {
+        let prev_episode = props.episode.as_ref().and_then(|episode| episode.get());
+        EpisodeDetail {
+            prev_persons: persons(prev_episode.as_ref()),
+            prev_episode,
+            props,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        let next_episode = props.episode.as_ref().and_then(|episode| episode.get());
+        let next_persons = persons(next_episode.as_ref());
+        let rerender = props.episode != self.props.episode
+            || match (&self.prev_episode, &next_episode) {
+                (Some(prev), Some(next)) => !Arc::ptr_eq(prev, next),
+                (prev, next) => prev.is_some() != next.is_some(),
+            }
+            || next_persons != self.prev_persons;
+
+        self.props = props;
+        self.prev_episode = next_episode;
+        self.prev_persons = next_persons;
+
+        if rerender {
+            UpdateAction::Render
+        } else {
+            UpdateAction::None
+        }
+    }
+
+    fn view(&self) -> VNode<EpisodeDetail> {
+        let title_style = AttrList::new();
+        title_style.insert(Attribute::new_scale(1.2).unwrap());
+        title_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
+
+        let meta_style = AttrList::new();
+        meta_style.insert(Attribute::new_scale(0.8).unwrap());
+        meta_style.insert(Attribute::new_variant(Variant::SmallCaps).unwrap());
+
+        let episode = self.prev_episode.as_deref().and_then(|ep| ep.as_ref().ok());
+        let title = episode.map(|ep| ep.title()).unwrap_or_default();
+        let image = episode.and_then(|ep| ep.image());
+        let channel = episode.and_then(|ep| ep.channel().core());
+        let channel = channel
+            .as_deref()
+            .and_then(|channel| channel.as_ref().ok())
+            .map(|channel| channel.title().to_owned())
+            .unwrap_or_default();
+
+        let mut meta = vec![
+            episode
+                .and_then(|ep| ep.date())
+                .map(|date| date.format("%F").to_string())
+                .unwrap_or_default(),
+            episode
+                .and_then(|ep| ep.duration())
+                .map(format_duration)
+                .unwrap_or_default(),
+            episode
+                .and_then(|ep| ep.location())
+                .map(|location| location.name.clone())
+                .unwrap_or_default(),
+        ];
+        meta.retain(|item| !item.is_empty());
+        let meta = meta.join(" \u{00B7} ");
+
+        // Old saved episodes don't have notes yet, so fall back to the plain description.
+        let notes = episode
+            .map(|ep| {
+                if ep.notes().is_empty() {
+                    escape_str_pcdata(ep.description()).into_owned()
+                } else {
+                    ep.notes().to_owned()
+                }
+            })
+            .unwrap_or_default();
+
+        let on_timestamp = match &self.props.episode {
+            Some(episode) => {
+                let episode = episode.clone();
+                let on_play_at = self.props.on_play_at.clone();
+                Callback::from(move |time| on_play_at.send((episode.clone(), time)))
+            }
+            None => Callback::default(),
+        };
+
+        gtk! {
+            <ScrolledWindow property_width_request=300 hexpand=true halign=Align::Fill valign=Align::Fill>
+                <Viewport hexpand=true halign=Align::Fill>
+                    <Column halign=Align::Fill maximum_width=800 linear_growth_width=800 hexpand=true>
+                        <GtkBox orientation=Orientation::Vertical border_width=10 hexpand=true>
+                            <GtkBox orientation=Orientation::Horizontal margin_bottom=10>
+                                <GtkBox
+                                    halign=Align::Start
+                                    valign=Align::Start
+                                    property_width_request=100
+                                    margin_end=10
+                                >
+                                    <@FixedImage image=image width=100 />
+                                </GtkBox>
+                                <GtkBox orientation=Orientation::Vertical hexpand=true>
+                                    <Label
+                                        label=title.to_owned()
+                                        line_wrap=true
+                                        max_width_chars=1
+                                        xalign=0.0
+                                        hexpand=true
+                                        halign=Align::Fill
+                                        valign=Align::Start
+                                        attributes={Some(title_style)}
+                                    />
+                                    <Label
+                                        label=channel.clone()
+                                        visible=!channel.is_empty()
+                                        line_wrap=true
+                                        max_width_chars=1
+                                        xalign=0.0
+                                        hexpand=true
+                                        halign=Align::Fill
+                                    />
+                                    <Label
+                                        label=meta
+                                        halign=Align::Start
+                                        attributes={Some(meta_style)}
+                                    />
+                                </GtkBox>
+                            </GtkBox>
+                            <GtkBox orientation=Orientation::Horizontal spacing=10 margin_bottom=10>
+                                <Button
+                                    label="Play"
+                                    image="media-playback-start-symbolic"
+                                    always_show_image=true
+                                    on clicked=|_| Message::HandlePlay
+                                />
+                                <Button
+                                    label="Play next"
+                                    image="media-skip-forward-symbolic"
+                                    always_show_image=true
+                                    on clicked=|_| Message::HandlePlayNext
+                                />
+                                <Button
+                                    label="Add to queue"
+                                    image="list-add-symbolic"
+                                    always_show_image=true
+                                    on clicked=|_| Message::HandleEnqueue
+                                />
+                            </GtkBox>
+                            <@People persons=self.prev_persons.clone() />
+                            <Label
+                                markup=notes
+                                timestamp_handler=on_timestamp
+                                selectable=true
+                                line_wrap=true
+                                max_width_chars=1
+                                xalign=0.0
+                                hexpand=true
+                                halign=Align::Fill
+                                valign=Align::Start
+                            />
+                        </GtkBox>
+                    </Column>
+                </Viewport>
+            </ScrolledWindow>
+        }
+    }
+}
@@ -21,12 +21,19 @@ pub enum Message {
     MaybeInit,
 }
 
-fn to_pixbuf(image: &ImageObj) -> Option<Pixbuf> {
-    let loader = PixbufLoader::with_mime_type(image.mimetype.as_ref()?).ok()?;
-    loader.write(image.data.as_ref()?).ok()?;
+/// Decodes the smallest copy of the image that covers `size`, scaled to fit.
+fn to_pixbuf(image: &ImageObj, size: i32) -> Option<Pixbuf> {
+    let (mimetype, data) = image.closest(size.max(0) as u32)?;
+    let loader = PixbufLoader::with_mime_type(mimetype).ok()?;
+    loader.write(data).ok()?;
     loader.close().ok()?;
 
-    Some(loader.get_pixbuf()?)
+    let pixbuf = loader.get_pixbuf()?;
+    if pixbuf.get_width() == size && pixbuf.get_height() == size {
+        Some(pixbuf)
+    } else {
+        pixbuf.scale_simple(size, size, InterpType::Bilinear)
+    }
 }
 
 impl Component for FixedImage {
@@ -56,10 +63,7 @@ impl Component for FixedImage {
 
         let image = self.props.image.as_ref();
         let image = image.and_then(|img| img.as_ref().as_ref().ok());
-        let pixbuf = image
-            .and_then(|img| to_pixbuf(&img))
-            .as_ref()
-            .and_then(|p| p.scale_simple(size, size, InterpType::Bilinear));
+        let pixbuf = image.and_then(|img| to_pixbuf(&img, size));
 
         gtk! {
             <Image
@@ -1,10 +1,12 @@
+use crate::episode_detail::EpisodeDetail;
 use crate::fixed_image::FixedImage;
 use crate::preferred_size::{PreferredSize, PreferredSizeExt};
 use crate::search_detail::SearchDetail;
 use crate::vgtk_ext::*;
 use libhandy::{Leaflet, LeafletExt, LeafletTransitionType};
 use pango::{AttrList, Attribute, EllipsizeMode, Weight};
-use state::{ChannelRef, EpisodeRef, StateError};
+use state::{ChannelRef, DownloadPolicy, EpisodeRef, StateError};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use vgtk::lib::gtk::{
     prelude::*, Align, Box as GtkBox, FlowBox, FlowBoxChild, Label, Orientation, ScrolledWindow,
@@ -15,11 +17,23 @@ use vgtk::{gtk, Callback, Component, UpdateAction, VNode};
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Props {
     pub on_select_podcast: Callback<Option<ChannelRef>>,
+    pub on_select_episode: Callback<EpisodeRef>,
     pub on_play: Callback<EpisodeRef>,
+    pub on_enqueue: Callback<EpisodeRef>,
+    pub on_play_next: Callback<EpisodeRef>,
+    pub on_play_at: Callback<(EpisodeRef, u64)>,
     pub on_subscribe: Callback<ChannelRef>,
     pub on_unsubscribe: Callback<ChannelRef>,
+    pub on_mark_played: Callback<EpisodeRef>,
+    pub on_mark_unplayed: Callback<EpisodeRef>,
+    pub on_download: Callback<EpisodeRef>,
+    pub on_cancel_download: Callback<EpisodeRef>,
+    pub on_delete_download: Callback<EpisodeRef>,
+    pub on_download_policy: Callback<(ChannelRef, DownloadPolicy)>,
     pub subscriptions: Option<Arc<Result<Vec<ChannelRef>, StateError>>>,
+    pub new_episodes: Arc<HashMap<String, HashSet<String>>>,
     pub selected_podcast: Option<ChannelRef>,
+    pub selected_episode: Option<EpisodeRef>,
     pub mobile: bool,
 }
 
@@ -32,9 +46,19 @@ pub struct HomeTab {
 pub enum Message {
     None,
     HandleSelectPodcast(Option<usize>),
+    HandleSelectEpisode(Box<EpisodeRef>),
     HandlePlay(Box<EpisodeRef>),
+    HandleEnqueue(Box<EpisodeRef>),
+    HandlePlayNext(Box<EpisodeRef>),
+    HandlePlayAt(Box<(EpisodeRef, u64)>),
     HandleSubscribe(ChannelRef),
     HandleUnsubscribe(ChannelRef),
+    HandleMarkPlayed(Box<EpisodeRef>),
+    HandleMarkUnplayed(Box<EpisodeRef>),
+    HandleDownload(Box<EpisodeRef>),
+    HandleCancelDownload(Box<EpisodeRef>),
+    HandleDeleteDownload(Box<EpisodeRef>),
+    HandleDownloadPolicy(Box<(ChannelRef, DownloadPolicy)>),
 }
 
 impl HomeTab {
@@ -49,11 +73,20 @@ impl HomeTab {
                 let title = core.map(|channel| channel.title()).unwrap_or_default();
                 let author = core.map(|channel| channel.author()).unwrap_or_default();
                 let small_image = core.and_then(|channel| channel.small_image());
+                let new_episodes = self
+                    .props
+                    .new_episodes
+                    .get(channel.pk())
+                    .map(|episodes| episodes.len())
+                    .unwrap_or(0);
 
                 let med_title_style = AttrList::new();
                 med_title_style.insert(Attribute::new_scale(1.2).unwrap());
                 med_title_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
 
+                let new_style = AttrList::new();
+                new_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
+
                 gtk! {
                     <FlowBoxChild
                         FlowBox::selected=selected
@@ -93,6 +126,13 @@ impl HomeTab {
                                     label=author
                                 />
                             </GtkBox>
+                            <Label
+                                label=format!("{} new", new_episodes)
+                                visible=new_episodes > 0
+                                valign=Align::Center
+                                margin_start=10
+                                attributes={Some(new_style.clone())}
+                            />
                         </GtkBox>
                     </FlowBoxChild>
                 }
@@ -179,10 +219,26 @@ impl Component for HomeTab {
                 self.props.on_select_podcast.send(msg);
                 UpdateAction::None
             }
+            Message::HandleSelectEpisode(episode) => {
+                self.props.on_select_episode.send(*episode);
+                UpdateAction::None
+            }
             Message::HandlePlay(episode) => {
                 self.props.on_play.send(*episode);
                 UpdateAction::None
             }
+            Message::HandleEnqueue(episode) => {
+                self.props.on_enqueue.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandlePlayNext(episode) => {
+                self.props.on_play_next.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandlePlayAt(play_at) => {
+                self.props.on_play_at.send(*play_at);
+                UpdateAction::None
+            }
             Message::HandleSubscribe(channel) => {
                 self.props.on_subscribe.send(channel);
                 UpdateAction::None
@@ -191,6 +247,30 @@ impl Component for HomeTab {
                 self.props.on_unsubscribe.send(channel);
                 UpdateAction::None
             }
+            Message::HandleMarkPlayed(episode) => {
+                self.props.on_mark_played.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleMarkUnplayed(episode) => {
+                self.props.on_mark_unplayed.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleDownload(episode) => {
+                self.props.on_download.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleCancelDownload(episode) => {
+                self.props.on_cancel_download.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleDeleteDownload(episode) => {
+                self.props.on_delete_download.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleDownloadPolicy(policy) => {
+                self.props.on_download_policy.send(*policy);
+                UpdateAction::None
+            }
         }
     }
 
@@ -226,14 +306,24 @@ impl Component for HomeTab {
                     Leaflet::is_visible_child=self.props.selected_podcast.is_some()
                 >
                     {
-                        if let Some(podcast) = self.props.selected_podcast.clone() {
+                        if let Some(episode) = self.props.selected_episode.clone() {
+                            gtk! {
+                                <@EpisodeDetail
+                                    episode={Some(episode)}
+                                    on play=|episode| Message::HandlePlay(Box::new(episode))
+                                    on enqueue=|episode| Message::HandleEnqueue(Box::new(episode))
+                                    on play_next=|episode| Message::HandlePlayNext(Box::new(episode))
+                                    on play_at=|play_at| Message::HandlePlayAt(Box::new(play_at))
+                                />
+                            }
+                        } else if let Some(podcast) = self.props.selected_podcast.clone() {
                             let subscribed = self.props.subscriptions
                                 .as_ref()
                                 .and_then(|subs|
                                     subs
                                         .as_ref()
                                         .as_ref()
-                                        .map(|subs| subs.contains(&podcast))
+                                        .map(|subs| subs.iter().any(|sub| sub.same_channel(&podcast)))
                                         .ok())
                                 .unwrap_or(false);
 
@@ -242,9 +332,18 @@ impl Component for HomeTab {
                                     podcast={Some(podcast)}
                                     mobile=self.props.mobile
                                     subscribed=subscribed
+                                    on select_episode=|episode| Message::HandleSelectEpisode(Box::new(episode))
                                     on play=|episode| Message::HandlePlay(Box::new(episode))
+                                    on enqueue=|episode| Message::HandleEnqueue(Box::new(episode))
+                                    on play_next=|episode| Message::HandlePlayNext(Box::new(episode))
                                     on subscribe=|channel| Message::HandleSubscribe(channel)
                                     on unsubscribe=|channel| Message::HandleUnsubscribe(channel)
+                                    on mark_played=|episode| Message::HandleMarkPlayed(Box::new(episode))
+                                    on mark_unplayed=|episode| Message::HandleMarkUnplayed(Box::new(episode))
+                                    on download=|episode| Message::HandleDownload(Box::new(episode))
+                                    on cancel_download=|episode| Message::HandleCancelDownload(Box::new(episode))
+                                    on delete_download=|episode| Message::HandleDeleteDownload(Box::new(episode))
+                                    on download_policy=|policy| Message::HandleDownloadPolicy(Box::new(policy))
                                 />
                             }
                         } else {
@@ -2,24 +2,43 @@
 #![allow(clippy::toplevel_ref_arg)]
 
 mod app;
+mod episode_detail;
 mod fixed_image;
 mod home_tab;
 mod now_playing;
+mod people;
 mod preferred_size;
 mod search_detail;
 mod search_results;
 mod search_tab;
 mod settings_tab;
 mod vgtk_ext;
+mod video;
 
 use app::{App, Message};
 use async_std::stream::StreamExt;
 use async_std::task;
 use loader::Loader;
-use state::CurrentState;
+use state::{CurrentState, StateAction};
 use std::env::args;
 use std::sync::Arc;
-use vgtk::lib::gio::prelude::*;
+use vgtk::lib::gio::{prelude::*, NetworkMonitor};
+
+/// Keeps track of whether the network is metered, for automatic downloads.
+fn watch_network(current: &Arc<CurrentState>) {
+    if let Some(monitor) = NetworkMonitor::get_default() {
+        current.update(vec![StateAction::SetNetworkMetered(
+            monitor.get_network_metered(),
+        )]);
+
+        let current = Arc::clone(current);
+        monitor.connect_property_network_metered_notify(move |monitor| {
+            current.update(vec![StateAction::SetNetworkMetered(
+                monitor.get_network_metered(),
+            )]);
+        });
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     pretty_env_logger::init();
@@ -29,14 +48,20 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     vgtk::lib::glib::set_prgname(Some("ca.nettek.pyrocast"));
 
     let (current, mut waiter) = CurrentState::new();
+    watch_network(&current);
     scope.send_message(Message::InitDispatch(Arc::clone(&current)));
 
+    let video_sink = player::VideoSink::new();
+    if video_sink.is_none() {
+        eprintln!("Cannot show video without the GStreamer GTK sink, so it will only be heard.");
+    }
+
     task::spawn(async move {
         let loader = Loader::new(current.clone(), 10);
-        let player = player::new_player(current.clone());
-        let database = database::new_database(current.clone(), loader.clone());
+        let player = player::new_player(current.clone(), video_sink.clone());
+        let database = database::new_database(current.clone(), loader.clone(), player.clone());
 
-        scope.send_message(Message::Init(loader, player, database));
+        scope.send_message(Message::Init(loader, player, database, video_sink));
 
         while waiter.next().await.is_some() {
             let sent = scope.try_send(Message::StateChanged(current.get()));
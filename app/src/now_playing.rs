@@ -1,13 +1,14 @@
 use crate::fixed_image::FixedImage;
 use crate::vgtk_ext::*;
+use crate::video;
 use chrono::NaiveTime;
 use libhandy::{Column, ColumnExt};
 use pango::{AttrList, Attribute, EllipsizeMode, Weight};
-use state::{Episode, Playback, PlayerState, StateError};
+use state::{chapter_at, segment_at, Episode, EpisodeRef, Playback, PlayerState, StateError};
 use std::sync::Arc;
 use vgtk::lib::gtk::{
-    prelude::*, Align, Box, Button, ComboBoxText, Label, Orientation, Scale, ScrolledWindow,
-    Spinner,
+    prelude::*, Align, Box, Button, ComboBoxText, Label, ListBox, ListBoxRow, Orientation,
+    PolicyType, Scale, ScrolledWindow, SelectionMode, Spinner, Widget,
 };
 use vgtk::{gtk, Callback, Component, UpdateAction, VNode};
 
@@ -15,12 +16,22 @@ use vgtk::{gtk, Callback, Component, UpdateAction, VNode};
 pub struct Props {
     pub player_state: Arc<Option<PlayerState>>,
     pub episode_info: Option<Arc<Result<Episode, StateError>>>,
+    /// Where the player draws video, if it can.
+    pub video: Option<Widget>,
     pub on_skip_back: Callback<()>,
     pub on_skip_forward: Callback<()>,
     pub on_pause: Callback<()>,
     pub on_unpause: Callback<()>,
     pub on_seek: Callback<u64>,
     pub on_rate_change: Callback<f64>,
+    pub on_next_chapter: Callback<()>,
+    pub on_previous_chapter: Callback<()>,
+
+    pub queue: Vec<EpisodeRef>,
+    pub on_skip_to_next: Callback<()>,
+    pub on_queue_move: Callback<(usize, usize)>,
+    pub on_queue_remove: Callback<usize>,
+    pub on_queue_clear: Callback<()>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -36,9 +47,180 @@ pub enum Message {
     HandleUnpause,
     HandleMaybeSeek(f64),
     HandleSetRate(f64),
+    HandleNextChapter,
+    HandlePreviousChapter,
+    HandleJumpToChapter(usize),
+    HandleJumpToSegment(usize),
+    HandleSkipToNext,
+    HandleQueueMove(usize, usize),
+    HandleQueueRemove(usize),
+    HandleQueueClear,
+    HandleFullscreen,
     None,
 }
 
+/// Formats milliseconds like the seek bar does.
+fn format_time(ms: u64) -> String {
+    let seconds = (ms / 1000) as u32;
+    let time = NaiveTime::from_num_seconds_from_midnight(seconds, 0);
+    if seconds >= 3600 {
+        time.format("%T").to_string()
+    } else {
+        time.format("%M∶%S").to_string()
+    }
+}
+
+impl NowPlaying {
+    fn episode(&self) -> Option<&Episode> {
+        self.props
+            .episode_info
+            .as_deref()
+            .and_then(|ep| ep.as_ref().ok())
+    }
+
+    fn view_chapters(&self, current: Option<usize>) -> Vec<VNode<NowPlaying>> {
+        let chapters = self.episode().map(|ep| ep.chapters()).unwrap_or_default();
+
+        chapters
+            .iter()
+            .enumerate()
+            .map(|(i, chapter)| {
+                let weight = if Some(i) == current {
+                    Weight::Bold
+                } else {
+                    Weight::Normal
+                };
+                let style = AttrList::new();
+                style.insert(Attribute::new_weight(weight).unwrap());
+
+                gtk! {
+                    <ListBoxRow>
+                        <Box orientation=Orientation::Horizontal spacing=10 border_width=5>
+                            <Label label=format_time(chapter.start) valign=Align::Center />
+                            <Label
+                                label=chapter.title.clone()
+                                attributes={Some(style)}
+                                max_width_chars=1
+                                xalign=0.0
+                                hexpand=true
+                                halign=Align::Fill
+                                ellipsize=EllipsizeMode::End
+                            />
+                        </Box>
+                    </ListBoxRow>
+                }
+            })
+            .collect()
+    }
+
+    fn view_transcript(&self, current: Option<usize>) -> Vec<VNode<NowPlaying>> {
+        let transcript = self.episode().map(|ep| ep.transcript()).unwrap_or_default();
+
+        transcript
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| {
+                let weight = if Some(i) == current {
+                    Weight::Bold
+                } else {
+                    Weight::Normal
+                };
+                let style = AttrList::new();
+                style.insert(Attribute::new_weight(weight).unwrap());
+
+                let speaker_style = AttrList::new();
+                speaker_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
+
+                gtk! {
+                    <ListBoxRow>
+                        <Box orientation=Orientation::Vertical border_width=5>
+                            <Label
+                                label=segment.speaker.clone().unwrap_or_default()
+                                visible=segment.speaker.is_some()
+                                attributes={Some(speaker_style)}
+                                xalign=0.0
+                                halign=Align::Fill
+                            />
+                            <Label
+                                label=segment.text.clone()
+                                attributes={Some(style)}
+                                line_wrap=true
+                                max_width_chars=1
+                                xalign=0.0
+                                hexpand=true
+                                halign=Align::Fill
+                            />
+                        </Box>
+                    </ListBoxRow>
+                }
+            })
+            .collect()
+    }
+
+    fn view_queue(&self) -> Vec<VNode<NowPlaying>> {
+        let queue_len = self.props.queue.len();
+
+        self.props
+            .queue
+            .iter()
+            .enumerate()
+            .map(|(i, episode_ref)| {
+                let episode = episode_ref.get();
+                let episode = episode.as_deref().and_then(|ep| ep.as_ref().ok());
+                let title = episode.map(|ep| ep.title().to_owned()).unwrap_or_default();
+                let channel = episode.and_then(|ep| ep.channel().core());
+                let channel = channel.as_deref().and_then(|core| core.as_ref().ok());
+                let author = channel.map(|core| core.title().to_owned()).unwrap_or_default();
+
+                gtk! {
+                    <ListBoxRow activatable=false>
+                        <Box orientation=Orientation::Horizontal>
+                            <Box orientation=Orientation::Vertical hexpand=true valign=Align::Center>
+                                <Label
+                                    label=title
+                                    max_width_chars=1
+                                    xalign=0.0
+                                    hexpand=true
+                                    halign=Align::Fill
+                                    ellipsize=EllipsizeMode::End
+                                />
+                                <Label
+                                    label=author
+                                    max_width_chars=1
+                                    xalign=0.0
+                                    hexpand=true
+                                    halign=Align::Fill
+                                    ellipsize=EllipsizeMode::End
+                                />
+                            </Box>
+                            <Button
+                                image="go-up-symbolic"
+                                tooltip_text="Move up"
+                                valign=Align::Center
+                                sensitive={i > 0}
+                                on clicked=|_| Message::HandleQueueMove(i, i.saturating_sub(1))
+                            />
+                            <Button
+                                image="go-down-symbolic"
+                                tooltip_text="Move down"
+                                valign=Align::Center
+                                sensitive={i + 1 < queue_len}
+                                on clicked=|_| Message::HandleQueueMove(i, i + 1)
+                            />
+                            <Button
+                                image="list-remove-symbolic"
+                                tooltip_text="Remove from queue"
+                                valign=Align::Center
+                                on clicked=|_| Message::HandleQueueRemove(i)
+                            />
+                        </Box>
+                    </ListBoxRow>
+                }
+            })
+            .collect()
+    }
+}
+
 impl Component for NowPlaying {
     type Message = Message;
     type Properties = Props;
@@ -81,6 +263,48 @@ impl Component for NowPlaying {
 
                 UpdateAction::None
             }
+            Message::HandleNextChapter => {
+                self.props.on_next_chapter.send(());
+                UpdateAction::None
+            }
+            Message::HandlePreviousChapter => {
+                self.props.on_previous_chapter.send(());
+                UpdateAction::None
+            }
+            Message::HandleJumpToChapter(idx) => {
+                if let Some(chapter) = self.episode().and_then(|ep| ep.chapters().get(idx)) {
+                    self.props.on_seek.send(chapter.start);
+                }
+                UpdateAction::None
+            }
+            Message::HandleJumpToSegment(idx) => {
+                if let Some(segment) = self.episode().and_then(|ep| ep.transcript().get(idx)) {
+                    self.props.on_seek.send(segment.start);
+                }
+                UpdateAction::None
+            }
+            Message::HandleSkipToNext => {
+                self.props.on_skip_to_next.send(());
+                UpdateAction::None
+            }
+            Message::HandleQueueMove(from, to) => {
+                self.props.on_queue_move.send((from, to));
+                UpdateAction::None
+            }
+            Message::HandleQueueRemove(idx) => {
+                self.props.on_queue_remove.send(idx);
+                UpdateAction::None
+            }
+            Message::HandleQueueClear => {
+                self.props.on_queue_clear.send(());
+                UpdateAction::None
+            }
+            Message::HandleFullscreen => {
+                if let Some(video) = &self.props.video {
+                    video::show_fullscreen(video);
+                }
+                UpdateAction::None
+            }
             Message::None => UpdateAction::None,
         }
     }
@@ -112,6 +336,18 @@ impl Component for NowPlaying {
         let playback = player_state
             .map(|state| state.playback)
             .unwrap_or(Playback::Stopped);
+        let show_video =
+            self.props.video.is_some() && player_state.map_or(false, |state| state.video);
+        let video = self.props.video.clone();
+
+        let chapters = episode.map(|ep| ep.chapters()).unwrap_or_default();
+        let current_chapter = chapter_at(chapters, time as u64);
+        let chapter_title = current_chapter
+            .map(|idx| chapters[idx].title.clone())
+            .unwrap_or_default();
+
+        let transcript = episode.map(|ep| ep.transcript()).unwrap_or_default();
+        let current_segment = segment_at(transcript, time as u64);
 
         let med_title_style = AttrList::new();
         med_title_style.insert(Attribute::new_scale(1.2).unwrap());
@@ -137,10 +373,32 @@ impl Component for NowPlaying {
                             valign=Align::Center
                             border_width=10
                         >
-                            <@FixedImage
-                                image=episode.and_then(|ep| ep.image())
-                                width=300
+                            <Box
+                                orientation=Orientation::Vertical
+                                visible=show_video
+                                hexpand=true
+                                property_height_request=300
+                                on map=|container| {
+                                    if let Some(video) = &video {
+                                        video::attach(container, video);
+                                    }
+                                    Message::None
+                                }
+                            />
+                            <Button
+                                image="view-fullscreen-symbolic"
+                                tooltip_text="Fullscreen"
+                                visible=show_video
+                                halign=Align::End
+                                margin_top=5
+                                on clicked=|_| Message::HandleFullscreen
                             />
+                            <Box orientation=Orientation::Vertical visible=!show_video>
+                                <@FixedImage
+                                    image=episode.and_then(|ep| ep.image_at(time as u64))
+                                    width=300
+                                />
+                            </Box>
                             <Label
                                 margin_top=10
                                 label=episode.map(|ep| ep.title().to_owned()).unwrap_or_default()
@@ -154,6 +412,34 @@ impl Component for NowPlaying {
                                 ellipsize=EllipsizeMode::End
                                 attributes={Some(med_title_style.clone())}
                             />
+                            <Box
+                                orientation=Orientation::Horizontal
+                                visible=!chapters.is_empty()
+                                spacing=5
+                                margin_top=5
+                            >
+                                <Button
+                                    image="media-skip-backward-symbolic"
+                                    tooltip_text="Previous chapter"
+                                    valign=Align::Center
+                                    sensitive=playback.active()
+                                    on clicked=|_| Message::HandlePreviousChapter
+                                />
+                                <Label
+                                    label=chapter_title
+                                    max_width_chars=1
+                                    hexpand=true
+                                    halign=Align::Fill
+                                    ellipsize=EllipsizeMode::End
+                                />
+                                <Button
+                                    image="media-skip-forward-symbolic"
+                                    tooltip_text="Next chapter"
+                                    valign=Align::Center
+                                    sensitive={playback.active() && current_chapter.map_or(false, |idx| idx + 1 < chapters.len())}
+                                    on clicked=|_| Message::HandleNextChapter
+                                />
+                            </Box>
                             <Label
                                 label=episode.map(|ep| ep.description().to_owned()).unwrap_or_default()
                                 hexpand=true
@@ -179,6 +465,66 @@ impl Component for NowPlaying {
                                     )
                                 }
                             />
+                            <Label
+                                label="Chapters"
+                                visible=!chapters.is_empty()
+                                margin_top=20
+                                xalign=0.0
+                                halign=Align::Fill
+                                attributes={Some(med_title_style.clone())}
+                            />
+                            <ListBox
+                                visible=!chapters.is_empty()
+                                margin_top=10
+                                selection_mode=SelectionMode::None
+                                on row_activated=|_, row| Message::HandleJumpToChapter(row.get_index() as usize)
+                            >
+                                {self.view_chapters(current_chapter)}
+                            </ListBox>
+                            <Label
+                                label="Transcript"
+                                visible=!transcript.is_empty()
+                                margin_top=20
+                                xalign=0.0
+                                halign=Align::Fill
+                                attributes={Some(med_title_style.clone())}
+                            />
+                            <ScrolledWindow
+                                visible=!transcript.is_empty()
+                                margin_top=10
+                                min_content_height=200
+                                hscrollbar_policy=PolicyType::Never
+                            >
+                                <ListBox
+                                    selection_mode=SelectionMode::None
+                                    scrolled_to_row=current_segment.map(|idx| idx as i32)
+                                    on row_activated=|_, row| Message::HandleJumpToSegment(row.get_index() as usize)
+                                >
+                                    {self.view_transcript(current_segment)}
+                                </ListBox>
+                            </ScrolledWindow>
+                            <Label
+                                label="Up Next"
+                                visible=!self.props.queue.is_empty()
+                                margin_top=20
+                                xalign=0.0
+                                halign=Align::Fill
+                                attributes={Some(med_title_style.clone())}
+                            />
+                            <ListBox
+                                visible=!self.props.queue.is_empty()
+                                margin_top=10
+                                selection_mode=SelectionMode::None
+                            >
+                                {self.view_queue()}
+                            </ListBox>
+                            <Button
+                                label="Clear queue"
+                                visible=!self.props.queue.is_empty()
+                                halign=Align::Start
+                                margin_top=10
+                                on clicked=|_| Message::HandleQueueClear
+                            />
                         </Box>
                     </ScrolledWindow>
                     <Box orientation=Orientation::Vertical hexpand=true vexpand=true halign=Align::Fill valign=Align::Center>
@@ -229,6 +575,17 @@ impl Component for NowPlaying {
                                             />
                                         }
                                     }
+                                    Playback::Ended => {
+                                        gtk! {
+                                            <Button
+                                                image="media-playback-start-symbolic"
+                                                on clicked=|_| Message::HandleUnpause
+                                                border_width=10
+                                                sensitive=true
+                                                property_width_request=80
+                                            />
+                                        }
+                                    }
                                     Playback::Buffering => {
                                         gtk! {
                                             <Spinner
@@ -270,6 +627,13 @@ impl Component for NowPlaying {
                                 border_width=10
                                 sensitive=playback.active()
                             />
+                            <Button
+                                image="media-skip-forward-symbolic"
+                                tooltip_text="Play next in queue"
+                                on clicked=|_| Message::HandleSkipToNext
+                                border_width=10
+                                sensitive=!self.props.queue.is_empty()
+                            />
                         </Box>
                     </Box>
                 </Box>
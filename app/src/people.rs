@@ -0,0 +1,109 @@
+use crate::fixed_image::FixedImage;
+use pango::{AttrList, Attribute, Variant, Weight};
+use state::{Image, Person, StateError};
+use std::sync::Arc;
+use vgtk::lib::gtk::{prelude::*, Align, Box as GtkBox, Label, Orientation};
+use vgtk::{ext::*, gtk, Component, UpdateAction, VNode};
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Props {
+    /// Each person, with their image if it has loaded.
+    pub persons: Vec<(Person, Option<Arc<Result<Image, StateError>>>)>,
+}
+
+/// The hosts, guests and crew of a show or an episode.
+#[derive(Debug, Default, Clone)]
+pub struct People {
+    props: Props,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {}
+
+/// Hosts come first, then guests, then everyone else.
+fn rank(person: &Person) -> u8 {
+    if person.is_host() {
+        0
+    } else if person.is_guest() {
+        1
+    } else {
+        2
+    }
+}
+
+/// "producer" becomes "Producer".
+fn role_title(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl Component for People {
+    type Message = Message;
+    type Properties = Props;
+
+    fn create(props: Self::Properties) -> Self {
+        People { props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        if props == self.props {
+            UpdateAction::None
+        } else {
+            self.props = props;
+            UpdateAction::Render
+        }
+    }
+
+    fn view(&self) -> VNode<People> {
+        let name_style = AttrList::new();
+        name_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
+
+        let role_style = AttrList::new();
+        role_style.insert(Attribute::new_scale(0.8).unwrap());
+        role_style.insert(Attribute::new_variant(Variant::SmallCaps).unwrap());
+
+        let mut persons: Vec<_> = self.props.persons.iter().collect();
+        persons.sort_by_key(|(person, _)| rank(person));
+
+        gtk! {
+            <GtkBox orientation=Orientation::Vertical>
+                {
+                    persons.into_iter().map(|(person, image)| {
+                        let name = match &person.href {
+                            Some(href) => format!(
+                                "<a href=\"{}\">{}</a>",
+                                escape_str_attribute(href),
+                                escape_str_pcdata(&person.name),
+                            ),
+                            None => escape_str_pcdata(&person.name).into_owned(),
+                        };
+
+                        gtk! {
+                            <GtkBox orientation=Orientation::Horizontal margin_bottom=5>
+                                <GtkBox valign=Align::Center property_width_request=48 margin_end=10>
+                                    <@FixedImage image=image.clone() width=48 />
+                                </GtkBox>
+                                <GtkBox orientation=Orientation::Vertical valign=Align::Center>
+                                    <Label
+                                        markup=name
+                                        halign=Align::Start
+                                        attributes={Some(name_style.clone())}
+                                    />
+                                    <Label
+                                        label=role_title(&person.role)
+                                        halign=Align::Start
+                                        attributes={Some(role_style.clone())}
+                                    />
+                                </GtkBox>
+                            </GtkBox>
+                        }
+                    })
+                }
+            </GtkBox>
+        }
+    }
+}
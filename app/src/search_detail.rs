@@ -1,13 +1,19 @@
 use crate::fixed_image::FixedImage;
-use chrono::{naive::NaiveTime, DateTime};
+use crate::people::People;
+use crate::vgtk_ext::*;
 use libhandy::{Column, ColumnExt, Squeezer, SqueezerExt};
 use pango::EllipsizeMode;
-use pango::{AttrList, Attribute, Variant, Weight};
-use state::{ChannelCore, ChannelDetail, ChannelRef, EpisodeRef, StateError};
+use pango::{AttrList, Attribute, Style, Variant, Weight};
+use state::{
+    ChannelCore, ChannelDetail, ChannelRef, Download, DownloadPolicy, Episode, EpisodeRef,
+    EpisodeType, Image, Person, ShowType, StateError,
+};
+use std::cmp::Reverse;
 use std::sync::Arc;
+use std::time::Duration;
 use vgtk::lib::gtk::{
-    prelude::*, Align, Box as GtkBox, Button, Label, ListBox, ListBoxRow, Orientation,
-    ScrolledWindow, SelectionMode, Viewport,
+    prelude::*, Align, Box as GtkBox, Button, CheckButton, ComboBoxText, Label, ListBox,
+    ListBoxRow, Orientation, ScrolledWindow, SelectionMode, Viewport,
 };
 use vgtk::{ext::*, gtk, Callback, Component, UpdateAction, VNode};
 use xml::escape::{escape_str_attribute, escape_str_pcdata};
@@ -15,9 +21,18 @@ use xml::escape::{escape_str_attribute, escape_str_pcdata};
 #[derive(Debug, Default, Clone)]
 pub struct Props {
     pub podcast: Option<ChannelRef>,
+    pub on_select_episode: Callback<EpisodeRef>,
     pub on_play: Callback<EpisodeRef>,
+    pub on_enqueue: Callback<EpisodeRef>,
+    pub on_play_next: Callback<EpisodeRef>,
     pub on_subscribe: Callback<ChannelRef>,
     pub on_unsubscribe: Callback<ChannelRef>,
+    pub on_mark_played: Callback<EpisodeRef>,
+    pub on_mark_unplayed: Callback<EpisodeRef>,
+    pub on_download: Callback<EpisodeRef>,
+    pub on_cancel_download: Callback<EpisodeRef>,
+    pub on_delete_download: Callback<EpisodeRef>,
+    pub on_download_policy: Callback<(ChannelRef, DownloadPolicy)>,
     pub mobile: bool,
     pub subscribed: bool,
 }
@@ -25,15 +40,287 @@ pub struct Props {
 #[derive(Debug, Default, Clone)]
 pub struct SearchDetail {
     props: Props,
+    /// The channel's episodes, in the order they are shown.
+    episodes: Vec<EpisodeRef>,
     episode_limit: usize,
 
     prev_core: Option<Arc<Result<ChannelCore, StateError>>>,
     prev_detail: Option<Arc<Result<ChannelDetail, StateError>>>,
+    prev_status: Vec<EpisodeStatus>,
+    prev_policy: DownloadPolicy,
+    prev_persons: Vec<(Person, Option<Arc<Result<Image, StateError>>>)>,
+}
+
+/// Per-episode state that is not part of the feed, and can change at any time.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct EpisodeStatus {
+    new: bool,
+    played: bool,
+    time_left: Option<String>,
+    download: Option<Download>,
+}
+
+impl EpisodeStatus {
+    fn new(episode: &Episode) -> EpisodeStatus {
+        EpisodeStatus {
+            new: episode.is_new(),
+            played: episode.played(),
+            time_left: time_left(episode),
+            download: episode.download(),
+        }
+    }
+
+    /// Labels to show after the date and duration.
+    fn labels(&self) -> Vec<String> {
+        let mut labels = vec![];
+
+        if self.new {
+            labels.push("New".to_owned());
+        }
+
+        if self.played {
+            labels.push("Played".to_owned());
+        } else if let Some(time_left) = &self.time_left {
+            labels.push(time_left.clone());
+        }
+
+        match &self.download {
+            Some(Download::Queued) => labels.push("Waiting to download".to_owned()),
+            Some(download @ Download::InProgress { .. }) => match download.fraction() {
+                Some(fraction) => labels.push(format!("Downloading {:.0}%", fraction * 100.0)),
+                None => labels.push("Downloading".to_owned()),
+            },
+            Some(Download::Done(_)) => labels.push("Downloaded".to_owned()),
+            Some(Download::Failed(_)) => labels.push("Download failed".to_owned()),
+            Some(Download::Cancelled) | None => {}
+        }
+
+        labels
+    }
+}
+
+/// The selected id of a combo box whose ids are numbers.
+fn combo_id(widget: &ComboBoxText) -> u64 {
+    widget
+        .get_active_id()
+        .and_then(|id| id.to_string().parse::<u64>().ok())
+        .unwrap_or(0)
+}
+
+/// An episode's length, with hours only when it has any.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}∶{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// A label like "32 min left" for partially played episodes.
+fn time_left(episode: &Episode) -> Option<String> {
+    let progress = episode.progress().filter(|progress| progress.time > 0)?;
+    let minutes = (progress.remaining()? + 59999) / 60000;
+
+    Some(format!("{} min left", minutes))
+}
+
+/// The heading of a season in the episode list.
+fn season_title(episode: Option<&Episode>) -> String {
+    match episode.and_then(|episode| episode.season()) {
+        Some(season) => match episode.and_then(|episode| episode.season_name()) {
+            Some(name) => format!("Season {}: {}", season, name),
+            None => format!("Season {}", season),
+        },
+        None => "Other episodes".to_owned(),
+    }
+}
+
+impl SearchDetail {
+    fn episode(&self, idx: usize) -> Option<EpisodeRef> {
+        self.episodes.get(idx).cloned()
+    }
+
+    /// The channel's hosts, guests and crew, with their images if they have loaded.
+    fn persons(&self) -> Vec<(Person, Option<Arc<Result<Image, StateError>>>)> {
+        let detail = self.props.podcast.as_ref().and_then(|pod| pod.details());
+        let detail = match detail.as_deref().and_then(|channel| channel.as_ref().ok()) {
+            Some(detail) => detail,
+            None => return vec![],
+        };
+
+        detail
+            .persons()
+            .iter()
+            .map(|person| (person.clone(), detail.person_image(person)))
+            .collect()
+    }
+
+    /// The episodes in the order to show them: grouped by season if the feed has seasons, and
+    /// oldest first for serial shows.
+    fn ordered_episodes(&self) -> Vec<EpisodeRef> {
+        let detail = self.props.podcast.as_ref().and_then(|pod| pod.details());
+        let detail = match detail.as_deref().and_then(|channel| channel.as_ref().ok()) {
+            Some(detail) => detail,
+            None => return vec![],
+        };
+
+        let mut episodes: Vec<_> = detail
+            .episodes()
+            .iter()
+            .map(|episode_ref| {
+                let episode = episode_ref.get();
+                let episode = episode.as_deref().and_then(|episode| episode.as_ref().ok());
+                let season = episode.and_then(|episode| episode.season());
+                let number = episode.and_then(|episode| episode.number());
+                let date = episode.and_then(|episode| episode.date());
+                (episode_ref.clone(), season, number, date)
+            })
+            .collect();
+
+        if detail.show_type() == ShowType::Serial {
+            // Feeds list the newest episode first, so this keeps the rest in order.
+            episodes.reverse();
+            episodes.sort_by_key(|(_, season, number, date)| (season.unwrap_or(0), *number, *date));
+        } else if episodes.iter().any(|(_, season, _, _)| season.is_some()) {
+            episodes.sort_by_key(|(_, season, _, _)| Reverse(*season));
+        }
+
+        episodes.into_iter().map(|(episode, ..)| episode).collect()
+    }
+
+    fn download_policy(&self) -> DownloadPolicy {
+        self.props
+            .podcast
+            .as_ref()
+            .map(|channel| channel.download_policy())
+            .unwrap_or_default()
+    }
+
+    /// Controls for automatically downloading and cleaning up episodes of a subscription.
+    fn view_download_policy(&self) -> VNode<SearchDetail> {
+        let policy = self.download_policy();
+
+        let keep_latest: Vec<(String, String)> = vec![
+            ("0".to_owned(), "Don't download automatically".to_owned()),
+            ("1".to_owned(), "Download the latest episode".to_owned()),
+            ("3".to_owned(), "Download the latest 3 episodes".to_owned()),
+            ("5".to_owned(), "Download the latest 5 episodes".to_owned()),
+            (
+                "10".to_owned(),
+                "Download the latest 10 episodes".to_owned(),
+            ),
+        ];
+
+        let delete_played_after: Vec<(String, String)> = vec![
+            ("0".to_owned(), "Keep played episodes".to_owned()),
+            (
+                "1".to_owned(),
+                "Delete played episodes after a day".to_owned(),
+            ),
+            (
+                "7".to_owned(),
+                "Delete played episodes after a week".to_owned(),
+            ),
+            (
+                "30".to_owned(),
+                "Delete played episodes after a month".to_owned(),
+            ),
+        ];
+
+        // In MB.
+        let max_size: Vec<(String, String)> = vec![
+            ("0".to_owned(), "No storage limit".to_owned()),
+            ("500".to_owned(), "Use up to 500 MB".to_owned()),
+            ("1000".to_owned(), "Use up to 1 GB".to_owned()),
+            ("2000".to_owned(), "Use up to 2 GB".to_owned()),
+            ("5000".to_owned(), "Use up to 5 GB".to_owned()),
+        ];
+
+        gtk! {
+            <GtkBox orientation=Orientation::Vertical spacing=5 margin_bottom=10>
+                <ComboBoxText
+                    hexpand=false
+                    halign=Align::Start
+                    options=keep_latest
+                    active_id=policy.keep_latest.to_string()
+                    on property_active_id_notify=|widget| {
+                        Message::HandleDownloadPolicy(DownloadPolicy {
+                            keep_latest: combo_id(widget) as u32,
+                            ..policy
+                        })
+                    }
+                />
+                <CheckButton
+                    label="Only download on unmetered connections"
+                    active=policy.unmetered_only
+                    sensitive={policy.keep_latest > 0}
+                    on toggled=|widget| {
+                        Message::HandleDownloadPolicy(DownloadPolicy {
+                            unmetered_only: widget.get_active(),
+                            ..policy
+                        })
+                    }
+                />
+                <ComboBoxText
+                    hexpand=false
+                    halign=Align::Start
+                    options=delete_played_after
+                    active_id=policy.delete_played_after.unwrap_or(0).to_string()
+                    on property_active_id_notify=|widget| {
+                        Message::HandleDownloadPolicy(DownloadPolicy {
+                            delete_played_after: Some(combo_id(widget) as u32).filter(|days| *days > 0),
+                            ..policy
+                        })
+                    }
+                />
+                <ComboBoxText
+                    hexpand=false
+                    halign=Align::Start
+                    options=max_size
+                    active_id=(policy.max_bytes.unwrap_or(0) / 1_000_000).to_string()
+                    on property_active_id_notify=|widget| {
+                        Message::HandleDownloadPolicy(DownloadPolicy {
+                            max_bytes: Some(combo_id(widget) * 1_000_000).filter(|bytes| *bytes > 0),
+                            ..policy
+                        })
+                    }
+                />
+            </GtkBox>
+        }
+    }
+
+    /// The status of each visible episode.
+    fn status(&self) -> Vec<EpisodeStatus> {
+        self.episodes
+            .iter()
+            .take(self.episode_limit)
+            .map(|episode_ref| {
+                let episode = episode_ref.get();
+                let episode = episode.as_deref().and_then(|episode| episode.as_ref().ok());
+                episode.map(EpisodeStatus::new).unwrap_or_default()
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum Message {
+    HandleSelectEpisode(usize),
     HandlePlay(usize),
+    HandleEnqueue(usize),
+    HandlePlayNext(usize),
+    HandleMarkPlayed(usize),
+    HandleMarkUnplayed(usize),
+    HandleDownload(usize),
+    HandleCancelDownload(usize),
+    HandleDeleteDownload(usize),
+    HandleDownloadPolicy(DownloadPolicy),
     HandleShowMore,
     HandleSubscribe,
     HandleUnsubscribe,
@@ -45,15 +332,65 @@ impl Component for SearchDetail {
 
     fn update(&mut self, message: Message) -> UpdateAction<Self> {
         match message {
+            Message::HandleSelectEpisode(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_select_episode.send(episode);
+                }
+                UpdateAction::None
+            }
             Message::HandlePlay(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_play.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandleEnqueue(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_enqueue.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandlePlayNext(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_play_next.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandleMarkPlayed(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_mark_played.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandleMarkUnplayed(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_mark_unplayed.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandleDownload(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_download.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandleCancelDownload(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_cancel_download.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandleDeleteDownload(idx) => {
+                if let Some(episode) = self.episode(idx) {
+                    self.props.on_delete_download.send(episode);
+                }
+                UpdateAction::None
+            }
+            Message::HandleDownloadPolicy(policy) => {
                 if let Some(channel) = &self.props.podcast {
-                    let detail = channel.details();
-                    let detail = detail.as_deref().and_then(|channel| channel.as_ref().ok());
-                    let episodes = detail.map(|channel| channel.episodes()).unwrap_or_default();
-
-                    if let Some(episode) = episodes.get(idx) {
-                        self.props.on_play.send(episode.clone());
-                    }
+                    self.props
+                        .on_download_policy
+                        .send((channel.clone(), policy));
                 }
                 UpdateAction::None
             }
@@ -77,12 +414,18 @@ impl Component for SearchDetail {
     }
 
     fn create(props: Self::Properties) -> Self {
-        SearchDetail {
+        let mut detail = SearchDetail {
             props,
+            episodes: vec![],
             episode_limit: 20,
             prev_core: None,
             prev_detail: None,
-        }
+            prev_status: vec![],
+            prev_persons: vec![],
+            prev_policy: DownloadPolicy::default(),
+        };
+        detail.episodes = detail.ordered_episodes();
+        detail
     }
 
     fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
@@ -93,11 +436,16 @@ impl Component for SearchDetail {
             self.prev_core = props.podcast.as_ref().and_then(|pod| pod.core());
             self.prev_detail = props.podcast.as_ref().and_then(|pod| pod.details());
             self.props = props;
+            self.episodes = self.ordered_episodes();
+            self.prev_status = self.status();
+            self.prev_policy = self.download_policy();
+            self.prev_persons = self.persons();
             UpdateAction::Render
         } else {
             let next_core = props.podcast.as_ref().and_then(|pod| pod.core());
             let next_detail = props.podcast.as_ref().and_then(|pod| pod.details());
             let mut rerender = false;
+            let mut detail_changed = false;
 
             if let (Some(prev_core), Some(next_core)) = (&self.prev_core, &next_core) {
                 if !Arc::ptr_eq(prev_core, next_core) {
@@ -109,11 +457,12 @@ impl Component for SearchDetail {
 
             if let (Some(prev_detail), Some(next_detail)) = (&self.prev_detail, &next_detail) {
                 if !Arc::ptr_eq(prev_detail, next_detail) {
-                    rerender = true;
+                    detail_changed = true;
                 }
             } else if self.prev_detail.is_some() != next_detail.is_some() {
-                rerender = true;
+                detail_changed = true;
             }
+            rerender |= detail_changed;
 
             if self.props.subscribed != props.subscribed || self.props.mobile != props.mobile {
                 rerender = true;
@@ -123,6 +472,28 @@ impl Component for SearchDetail {
             self.prev_detail = props.podcast.as_ref().and_then(|pod| pod.details());
             self.props = props;
 
+            if detail_changed {
+                self.episodes = self.ordered_episodes();
+            }
+
+            let next_status = self.status();
+            if self.prev_status != next_status {
+                rerender = true;
+            }
+            self.prev_status = next_status;
+
+            let next_policy = self.download_policy();
+            if self.prev_policy != next_policy {
+                rerender = true;
+            }
+            self.prev_policy = next_policy;
+
+            let next_persons = self.persons();
+            if self.prev_persons != next_persons {
+                rerender = true;
+            }
+            self.prev_persons = next_persons;
+
             if rerender {
                 UpdateAction::Render
             } else {
@@ -144,6 +515,15 @@ impl Component for SearchDetail {
             let episode_title_style = AttrList::new();
             episode_title_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
 
+            // Trailers and bonus episodes.
+            let extra_title_style = AttrList::new();
+            extra_title_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
+            extra_title_style.insert(Attribute::new_style(Style::Italic).unwrap());
+
+            let season_style = AttrList::new();
+            season_style.insert(Attribute::new_scale(1.2).unwrap());
+            season_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
+
             let episode_meta_style = AttrList::new();
             episode_meta_style.insert(Attribute::new_scale(0.8).unwrap());
             episode_meta_style.insert(Attribute::new_variant(Variant::SmallCaps).unwrap());
@@ -153,6 +533,7 @@ impl Component for SearchDetail {
             let title = core.map(|channel| channel.title()).unwrap_or_default();
             let author = core.map(|channel| channel.author()).unwrap_or_default();
             let big_image = core.and_then(|channel| channel.big_image());
+            let ended = core.map_or(false, |channel| channel.ended());
 
             let details = channel.details();
             let details = details.as_deref().and_then(|channel| channel.as_ref().ok());
@@ -160,9 +541,41 @@ impl Component for SearchDetail {
             let description = details
                 .map(|channel| channel.description())
                 .unwrap_or_default();
-            let episodes = details
-                .map(|channel| channel.episodes())
+            let support = details
+                .and_then(|channel| channel.funding().first())
+                .map(|funding| {
+                    format!(
+                        "<a href=\"{}\">Support this show</a>",
+                        escape_str_attribute(&funding.url),
+                    )
+                })
                 .unwrap_or_default();
+            let episodes = &self.episodes;
+            let episode_seasons: Vec<_> = episodes
+                .iter()
+                .map(|episode_ref| {
+                    let episode = episode_ref.get();
+                    let episode = episode.as_deref().and_then(|episode| episode.as_ref().ok());
+                    (
+                        episode.and_then(|episode| episode.season()),
+                        season_title(episode),
+                    )
+                })
+                .collect();
+            // A heading above the first episode of each season, if the feed has seasons.
+            let grouped = episode_seasons.iter().any(|(season, _)| season.is_some());
+            let season_headers: Vec<String> = episode_seasons
+                .iter()
+                .enumerate()
+                .map(|(i, (season, title))| {
+                    let first = i == 0 || episode_seasons[i - 1].0 != *season;
+                    if grouped && first {
+                        title.clone()
+                    } else {
+                        String::new()
+                    }
+                })
+                .collect();
 
             let link = format!(
                 "<a href=\"{}\">{}</a>",
@@ -359,14 +772,28 @@ impl Component for SearchDetail {
                     </Squeezer>
 
                     <GtkBox border_width=10 valign=Align::Start hexpand=true orientation=Orientation::Vertical>
+                        <Label
+                            label="This show has ended, so there won't be new episodes."
+                            visible=ended
+                            line_wrap=true
+                            max_width_chars=1
+                            xalign=0.0
+                            halign=Align::Fill
+                            margin_bottom=10
+                        />
                         {
                             if self.props.subscribed {
                                 gtk! {
-                                    <Button
-                                        on clicked=|_| Message::HandleUnsubscribe
-                                        label="Unsubscribe"
-                                        margin_bottom=10
-                                    />
+                                    <GtkBox orientation=Orientation::Vertical>
+                                        <Button
+                                            on clicked=|_| Message::HandleUnsubscribe
+                                            label="Unsubscribe"
+                                            margin_bottom=10
+                                        />
+                                        {
+                                            self.view_download_policy()
+                                        }
+                                    </GtkBox>
                                 }
                             } else {
                                 gtk! {
@@ -378,68 +805,187 @@ impl Component for SearchDetail {
                                 }
                             }
                         }
-                        <ListBox border_width=10 valign=Align::Start hexpand=true selection_mode=SelectionMode::None>
+                        <Label
+                            markup=support.clone()
+                            visible=!support.is_empty()
+                            halign=Align::Start
+                            margin_bottom=10
+                        />
+                        <@People persons=self.prev_persons.clone() />
+                        <ListBox
+                            border_width=10
+                            valign=Align::Start
+                            hexpand=true
+                            selection_mode=SelectionMode::None
+                            on row_activated=|_, row| Message::HandleSelectEpisode(row.get_index() as usize)
+                        >
                             {
                                 episodes.iter().take(self.episode_limit).enumerate().map(|(i, episode_ref)| {
                                     let episode = episode_ref.get();
                                     let episode = episode.as_deref().and_then(|episode| episode.as_ref().ok());
                                     let title = episode.map(|episode| episode.title()).unwrap_or_default();
-                                    let date = episode.map(|episode| episode.date()).unwrap_or_default();
-                                    let date = DateTime::parse_from_rfc2822(date).map(|date| date.format("%F").to_string()).unwrap_or_default();
-                                    let duration = episode.map(|episode| episode.duration()).unwrap_or_default();
-                                    let duration = if duration.contains(":") {
-                                        duration.to_owned()
+                                    let date = episode
+                                        .and_then(|episode| episode.date())
+                                        .map(|date| date.format("%F").to_string())
+                                        .unwrap_or_default();
+                                    let duration = episode
+                                        .and_then(|episode| episode.duration())
+                                        .map(format_duration)
+                                        .unwrap_or_default();
+                                    let description = episode.map(|episode| episode.description()).unwrap_or_default();
+                                    let status = episode.map(EpisodeStatus::new).unwrap_or_default();
+                                    let played = status.played;
+                                    let episode_type = episode.map(|episode| episode.episode_type()).unwrap_or_default();
+                                    let kind = match episode_type {
+                                        EpisodeType::Full => String::new(),
+                                        EpisodeType::Trailer => "Trailer".to_owned(),
+                                        EpisodeType::Bonus => "Bonus".to_owned(),
+                                    };
+                                    let number = episode
+                                        .and_then(|episode| episode.number())
+                                        .map(|number| format!("Episode {}", number))
+                                        .unwrap_or_default();
+                                    let title_style = if episode_type == EpisodeType::Full {
+                                        episode_title_style.clone()
                                     } else {
-                                        let seconds = duration.parse::<u32>().unwrap_or(0);
-                                        let time = NaiveTime::from_num_seconds_from_midnight(seconds, 0);
-                                        if seconds >= 3600 {
-                                            time.format("%T").to_string()
-                                        } else {
-                                            time.format("%M∶%S").to_string()
-                                        }
+                                        extra_title_style.clone()
                                     };
-                                    let description = episode.map(|episode| episode.description()).unwrap_or_default();
+                                    let header = season_headers[i].clone();
+                                    let mut meta = vec![kind, number, date, duration];
+                                    meta.retain(|item| !item.is_empty());
+                                    meta.extend(status.labels());
+                                    let meta = meta.join(" \u{00B7} ");
 
                                     gtk! {
                                         <ListBoxRow activatable=true>
-                                            <GtkBox hexpand=false orientation=Orientation::Horizontal>
-                                                <GtkBox orientation=Orientation::Vertical hexpand=true>
-                                                    <Label
-                                                        label=title.to_string()
-                                                        line_wrap=true
-                                                        max_width_chars=1
-                                                        xalign=0.0
+                                            <GtkBox orientation=Orientation::Vertical>
+                                                <Label
+                                                    label=header.clone()
+                                                    visible=!header.is_empty()
+                                                    halign=Align::Start
+                                                    margin_top=10
+                                                    margin_bottom=5
+                                                    attributes={Some(season_style.clone())}
+                                                />
+                                                <GtkBox hexpand=false orientation=Orientation::Horizontal>
+                                                    <GtkBox
+                                                        orientation=Orientation::Vertical
                                                         hexpand=true
-                                                        halign=Align::Fill
-                                                        valign=Align::Start
-                                                        attributes={Some(episode_title_style.clone())}
+                                                        opacity={if played { 0.5 } else { 1.0 }}
+                                                    >
+                                                        <Label
+                                                            label=title.to_string()
+                                                            line_wrap=true
+                                                            max_width_chars=1
+                                                            xalign=0.0
+                                                            hexpand=true
+                                                            halign=Align::Fill
+                                                            valign=Align::Start
+                                                            attributes={Some(title_style)}
+                                                        />
+                                                        <Label
+                                                            label=meta
+                                                            halign=Align::Start
+                                                            valign=Align::Start
+                                                            attributes={Some(episode_meta_style.clone())}
+                                                        />
+                                                        <Label
+                                                            label=description.replace("\n", " ")
+                                                            hexpand=true
+                                                            max_width_chars=1
+                                                            xalign=0.0
+                                                            halign=Align::Fill
+                                                            valign=Align::Start
+                                                            ellipsize=EllipsizeMode::End
+                                                        />
+                                                    </GtkBox>
+                                                    {
+                                                        if played {
+                                                            gtk! {
+                                                                <Button
+                                                                    margin_start=10
+                                                                    border_width=10
+                                                                    image="edit-undo-symbolic"
+                                                                    tooltip_text="Mark as unplayed"
+                                                                    halign=Align::End
+                                                                    valign=Align::Center
+                                                                    on clicked=|_| Message::HandleMarkUnplayed(i)
+                                                                />
+                                                            }
+                                                        } else {
+                                                            gtk! {
+                                                                <Button
+                                                                    margin_start=10
+                                                                    border_width=10
+                                                                    image="object-select-symbolic"
+                                                                    tooltip_text="Mark as played"
+                                                                    halign=Align::End
+                                                                    valign=Align::Center
+                                                                    on clicked=|_| Message::HandleMarkPlayed(i)
+                                                                />
+                                                            }
+                                                        }
+                                                    }
+                                                    {
+                                                        match &status.download {
+                                                            Some(download) if download.active() => gtk! {
+                                                                <Button
+                                                                    border_width=10
+                                                                    image="process-stop-symbolic"
+                                                                    tooltip_text="Cancel download"
+                                                                    halign=Align::End
+                                                                    valign=Align::Center
+                                                                    on clicked=|_| Message::HandleCancelDownload(i)
+                                                                />
+                                                            },
+                                                            Some(Download::Done(_)) => gtk! {
+                                                                <Button
+                                                                    border_width=10
+                                                                    image="user-trash-symbolic"
+                                                                    tooltip_text="Delete download"
+                                                                    halign=Align::End
+                                                                    valign=Align::Center
+                                                                    on clicked=|_| Message::HandleDeleteDownload(i)
+                                                                />
+                                                            },
+                                                            _ => gtk! {
+                                                                <Button
+                                                                    border_width=10
+                                                                    image="folder-download-symbolic"
+                                                                    tooltip_text="Download"
+                                                                    halign=Align::End
+                                                                    valign=Align::Center
+                                                                    on clicked=|_| Message::HandleDownload(i)
+                                                                />
+                                                            },
+                                                        }
+                                                    }
+                                                    <Button
+                                                        border_width=10
+                                                        image="media-skip-forward-symbolic"
+                                                        tooltip_text="Play next"
+                                                        halign=Align::End
+                                                        valign=Align::Center
+                                                        on clicked=|_| Message::HandlePlayNext(i)
                                                     />
-                                                    <Label
-                                                        label=format!("{} \u{00B7} {}", date, duration)
-                                                        halign=Align::Start
-                                                        valign=Align::Start
-                                                        attributes={Some(episode_meta_style.clone())}
+                                                    <Button
+                                                        border_width=10
+                                                        image="list-add-symbolic"
+                                                        tooltip_text="Add to queue"
+                                                        halign=Align::End
+                                                        valign=Align::Center
+                                                        on clicked=|_| Message::HandleEnqueue(i)
                                                     />
-                                                    <Label
-                                                        label=description.replace("\n", " ")
-                                                        hexpand=true
-                                                        max_width_chars=1
-                                                        xalign=0.0
-                                                        halign=Align::Fill
-                                                        valign=Align::Start
-                                                        ellipsize=EllipsizeMode::End
+                                                    <Button
+                                                        border_width=10
+                                                        image="media-playback-start-symbolic"
+                                                        halign=Align::End
+                                                        valign=Align::Center
+                                                        on clicked=|_| {
+                                                            Message::HandlePlay(i)
+                                                        }
                                                     />
                                                 </GtkBox>
-                                                <Button
-                                                    margin_start=10
-                                                    border_width=10
-                                                    image="media-playback-start-symbolic"
-                                                    halign=Align::End
-                                                    valign=Align::Center
-                                                    on clicked=|_| {
-                                                        Message::HandlePlay(i)
-                                                    }
-                                                />
                                             </GtkBox>
                                         </ListBoxRow>
                                     }
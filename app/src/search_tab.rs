@@ -1,10 +1,11 @@
+use crate::episode_detail::EpisodeDetail;
 use crate::search_detail::SearchDetail;
 use crate::search_results::SearchResults;
 use crate::vgtk_ext::*;
 use libhandy::{
     Column, ColumnExt, Leaflet, LeafletExt, LeafletTransitionType, SearchBar, SearchBarExt,
 };
-use state::{ChannelRef, EpisodeRef, StateError};
+use state::{ChannelRef, DownloadPolicy, EpisodeRef, StateError};
 use std::sync::Arc;
 use vgtk::lib::gtk::{prelude::*, Box as GtkBox, Label, Orientation, SearchEntry};
 use vgtk::{gtk, Callback, Component, UpdateAction, VNode};
@@ -12,12 +13,23 @@ use vgtk::{gtk, Callback, Component, UpdateAction, VNode};
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Props {
     pub on_select_podcast: Callback<Option<ChannelRef>>,
+    pub on_select_episode: Callback<EpisodeRef>,
     pub on_play: Callback<EpisodeRef>,
+    pub on_enqueue: Callback<EpisodeRef>,
+    pub on_play_next: Callback<EpisodeRef>,
+    pub on_play_at: Callback<(EpisodeRef, u64)>,
     pub on_search: Callback<String>,
     pub on_subscribe: Callback<ChannelRef>,
     pub on_unsubscribe: Callback<ChannelRef>,
+    pub on_mark_played: Callback<EpisodeRef>,
+    pub on_mark_unplayed: Callback<EpisodeRef>,
+    pub on_download: Callback<EpisodeRef>,
+    pub on_cancel_download: Callback<EpisodeRef>,
+    pub on_delete_download: Callback<EpisodeRef>,
+    pub on_download_policy: Callback<(ChannelRef, DownloadPolicy)>,
     pub subscriptions: Option<Arc<Result<Vec<ChannelRef>, StateError>>>,
     pub selected_podcast: Option<ChannelRef>,
+    pub selected_episode: Option<EpisodeRef>,
     pub chart_results: Option<Arc<Result<Vec<ChannelRef>, StateError>>>,
     pub mobile: bool,
 }
@@ -30,10 +42,20 @@ pub struct SearchTab {
 #[derive(Clone, Debug)]
 pub enum Message {
     HandleSelectPodcast(Option<ChannelRef>),
+    HandleSelectEpisode(Box<EpisodeRef>),
     HandlePlay(Box<EpisodeRef>),
+    HandleEnqueue(Box<EpisodeRef>),
+    HandlePlayNext(Box<EpisodeRef>),
+    HandlePlayAt(Box<(EpisodeRef, u64)>),
     HandleSearch(String),
     HandleSubscribe(ChannelRef),
     HandleUnsubscribe(ChannelRef),
+    HandleMarkPlayed(Box<EpisodeRef>),
+    HandleMarkUnplayed(Box<EpisodeRef>),
+    HandleDownload(Box<EpisodeRef>),
+    HandleCancelDownload(Box<EpisodeRef>),
+    HandleDeleteDownload(Box<EpisodeRef>),
+    HandleDownloadPolicy(Box<(ChannelRef, DownloadPolicy)>),
 }
 
 impl Component for SearchTab {
@@ -46,10 +68,26 @@ impl Component for SearchTab {
                 self.props.on_select_podcast.send(podcast);
                 UpdateAction::None
             }
+            Message::HandleSelectEpisode(episode) => {
+                self.props.on_select_episode.send(*episode);
+                UpdateAction::None
+            }
             Message::HandlePlay(episode) => {
                 self.props.on_play.send(*episode);
                 UpdateAction::None
             }
+            Message::HandleEnqueue(episode) => {
+                self.props.on_enqueue.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandlePlayNext(episode) => {
+                self.props.on_play_next.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandlePlayAt(play_at) => {
+                self.props.on_play_at.send(*play_at);
+                UpdateAction::None
+            }
             Message::HandleSearch(search) => {
                 self.props.on_search.send(search);
                 UpdateAction::None
@@ -62,6 +100,30 @@ impl Component for SearchTab {
                 self.props.on_unsubscribe.send(channel);
                 UpdateAction::None
             }
+            Message::HandleMarkPlayed(episode) => {
+                self.props.on_mark_played.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleMarkUnplayed(episode) => {
+                self.props.on_mark_unplayed.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleDownload(episode) => {
+                self.props.on_download.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleCancelDownload(episode) => {
+                self.props.on_cancel_download.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleDeleteDownload(episode) => {
+                self.props.on_delete_download.send(*episode);
+                UpdateAction::None
+            }
+            Message::HandleDownloadPolicy(policy) => {
+                self.props.on_download_policy.send(*policy);
+                UpdateAction::None
+            }
         }
     }
 
@@ -92,6 +154,7 @@ impl Component for SearchTab {
                     <SearchBar search_mode=true show_close_button=false>
                         <Column hexpand=true maximum_width=600>
                             <SearchEntry
+                                placeholder_text="Search, or paste a feed URL"
                                 on property_text_notify=|entry| Message::HandleSearch(entry.get_text().to_string())
                             />
                         </Column>
@@ -107,14 +170,24 @@ impl Component for SearchTab {
                     Leaflet::is_visible_child=self.props.selected_podcast.is_some()
                 >
                     {
-                        if let Some(podcast) = self.props.selected_podcast.clone() {
+                        if let Some(episode) = self.props.selected_episode.clone() {
+                            gtk! {
+                                <@EpisodeDetail
+                                    episode={Some(episode)}
+                                    on play=|episode| Message::HandlePlay(Box::new(episode))
+                                    on enqueue=|episode| Message::HandleEnqueue(Box::new(episode))
+                                    on play_next=|episode| Message::HandlePlayNext(Box::new(episode))
+                                    on play_at=|play_at| Message::HandlePlayAt(Box::new(play_at))
+                                />
+                            }
+                        } else if let Some(podcast) = self.props.selected_podcast.clone() {
                             let subscribed = self.props.subscriptions
                                 .as_ref()
                                 .and_then(|subs|
                                     subs
                                         .as_ref()
                                         .as_ref()
-                                        .map(|subs| subs.contains(&podcast))
+                                        .map(|subs| subs.iter().any(|sub| sub.same_channel(&podcast)))
                                         .ok())
                                 .unwrap_or(false);
 
@@ -123,9 +196,18 @@ impl Component for SearchTab {
                                     podcast={Some(podcast)}
                                     mobile=self.props.mobile
                                     subscribed=subscribed
+                                    on select_episode=|episode| Message::HandleSelectEpisode(Box::new(episode))
                                     on play=|episode| Message::HandlePlay(Box::new(episode))
+                                    on enqueue=|episode| Message::HandleEnqueue(Box::new(episode))
+                                    on play_next=|episode| Message::HandlePlayNext(Box::new(episode))
                                     on subscribe=|channel| Message::HandleSubscribe(channel)
                                     on unsubscribe=|channel| Message::HandleUnsubscribe(channel)
+                                    on mark_played=|episode| Message::HandleMarkPlayed(Box::new(episode))
+                                    on mark_unplayed=|episode| Message::HandleMarkUnplayed(Box::new(episode))
+                                    on download=|episode| Message::HandleDownload(Box::new(episode))
+                                    on cancel_download=|episode| Message::HandleCancelDownload(Box::new(episode))
+                                    on delete_download=|episode| Message::HandleDeleteDownload(Box::new(episode))
+                                    on download_policy=|policy| Message::HandleDownloadPolicy(Box::new(policy))
                                 />
                             }
                         } else {
@@ -1,28 +1,220 @@
-use vgtk::lib::gtk::{prelude::*, Box, Label, Orientation};
-use vgtk::{gtk, Component, UpdateAction, VNode};
+use crate::vgtk_ext::*;
+use pango::{AttrList, Attribute, Weight};
+use state::EnclosurePreference;
+use std::path::PathBuf;
+use vgtk::lib::gtk::{
+    prelude::*, Align, Box, Button, CheckButton, ComboBoxText, FileChooserAction,
+    FileChooserNative, FileFilter, Label, Orientation, ResponseType, Window,
+};
+use vgtk::{gtk, Callback, Component, UpdateAction, VNode};
 
 #[derive(Debug, Default, Clone)]
-pub struct SettingsTab {}
+pub struct Props {
+    /// The result of the last import or export.
+    pub opml_status: Option<String>,
+    pub on_import_opml: Callback<PathBuf>,
+    pub on_export_opml: Callback<PathBuf>,
+    pub enclosure_preference: EnclosurePreference,
+    pub on_enclosure_preference: Callback<EnclosurePreference>,
+    pub video_as_audio: bool,
+    pub on_video_as_audio: Callback<bool>,
+    /// Episodes are marked as played when there is less than this much time left (ms).
+    pub played_threshold: u64,
+    pub on_played_threshold: Callback<u64>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SettingsTab {
+    props: Props,
+}
 
 #[derive(Clone, Debug)]
-pub enum Message {}
+pub enum Message {
+    HandleImportOpml,
+    HandleExportOpml,
+    HandleEnclosurePreference(EnclosurePreference),
+    HandleVideoAsAudio(bool),
+    HandlePlayedThreshold(Option<u64>),
+}
+
+/// Asks the user for an OPML file to open or save.
+fn choose_opml_file(action: FileChooserAction) -> Option<PathBuf> {
+    let (title, accept) = match action {
+        FileChooserAction::Save => ("Export Subscriptions", "_Export"),
+        _ => ("Import Subscriptions", "_Import"),
+    };
+
+    let dialog = FileChooserNative::new(
+        Some(title),
+        None::<&Window>,
+        action,
+        Some(accept),
+        Some("_Cancel"),
+    );
+
+    let filter = FileFilter::new();
+    filter.set_name(Some("OPML"));
+    filter.add_pattern("*.opml");
+    filter.add_pattern("*.xml");
+    dialog.add_filter(&filter);
+
+    if action == FileChooserAction::Save {
+        dialog.set_current_name("subscriptions.opml");
+        dialog.set_do_overwrite_confirmation(true);
+    }
+
+    if dialog.run() == ResponseType::Accept {
+        dialog.get_filename()
+    } else {
+        None
+    }
+}
 
 impl Component for SettingsTab {
     type Message = Message;
-    type Properties = ();
+    type Properties = Props;
 
-    fn update(&mut self, _message: Message) -> UpdateAction<Self> {
-        UpdateAction::None
+    fn create(props: Self::Properties) -> Self {
+        SettingsTab { props }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> UpdateAction<Self> {
+    fn update(&mut self, message: Message) -> UpdateAction<Self> {
+        match message {
+            Message::HandleImportOpml => {
+                if let Some(path) = choose_opml_file(FileChooserAction::Open) {
+                    self.props.on_import_opml.send(path);
+                }
+            }
+            Message::HandleExportOpml => {
+                if let Some(path) = choose_opml_file(FileChooserAction::Save) {
+                    self.props.on_export_opml.send(path);
+                }
+            }
+            Message::HandleEnclosurePreference(preference) => {
+                if preference != self.props.enclosure_preference {
+                    self.props.on_enclosure_preference.send(preference);
+                }
+            }
+            Message::HandleVideoAsAudio(video_as_audio) => {
+                if video_as_audio != self.props.video_as_audio {
+                    self.props.on_video_as_audio.send(video_as_audio);
+                }
+            }
+            Message::HandlePlayedThreshold(Some(played_threshold)) => {
+                if played_threshold != self.props.played_threshold {
+                    self.props.on_played_threshold.send(played_threshold);
+                }
+            }
+            Message::HandlePlayedThreshold(None) => {}
+        }
+
         UpdateAction::None
     }
 
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        let rerender = self.props.opml_status != props.opml_status
+            || self.props.enclosure_preference != props.enclosure_preference
+            || self.props.video_as_audio != props.video_as_audio
+            || self.props.played_threshold != props.played_threshold;
+        self.props = props;
+
+        if rerender {
+            UpdateAction::Render
+        } else {
+            UpdateAction::None
+        }
+    }
+
     fn view(&self) -> VNode<SettingsTab> {
+        let heading_style = AttrList::new();
+        heading_style.insert(Attribute::new_weight(Weight::Bold).unwrap());
+
+        let opml_status = self.props.opml_status.clone().unwrap_or_default();
+
+        let quality = vec![
+            (
+                EnclosurePreference::FeedDefault.as_str().to_owned(),
+                "Use the version the feed recommends".to_owned(),
+            ),
+            (
+                EnclosurePreference::SmallestWhenMetered.as_str().to_owned(),
+                "Use the lowest bitrate on metered connections".to_owned(),
+            ),
+            (
+                EnclosurePreference::Smallest.as_str().to_owned(),
+                "Always use the lowest bitrate".to_owned(),
+            ),
+            (
+                EnclosurePreference::Largest.as_str().to_owned(),
+                "Always use the highest bitrate".to_owned(),
+            ),
+        ];
+
+        let played_thresholds = vec![
+            ("0".to_owned(), "When it ends".to_owned()),
+            ("30000".to_owned(), "With 30 seconds left".to_owned()),
+            ("60000".to_owned(), "With 1 minute left".to_owned()),
+            ("120000".to_owned(), "With 2 minutes left".to_owned()),
+            ("300000".to_owned(), "With 5 minutes left".to_owned()),
+        ];
+
         gtk! {
-            <Box orientation=Orientation::Vertical hexpand=true vexpand=true>
-                <Label label="To implement" vexpand=true />
+            <Box orientation=Orientation::Vertical hexpand=true vexpand=true border_width=20 spacing=10>
+                <Label label="Subscriptions" xalign=0.0 attributes={Some(heading_style.clone())} />
+                <Label
+                    label="Move your subscriptions to or from another podcast app using an OPML file."
+                    xalign=0.0
+                    line_wrap=true
+                />
+                <Box orientation=Orientation::Horizontal spacing=10 halign=Align::Start>
+                    <Button label="Import…" on clicked=|_| Message::HandleImportOpml />
+                    <Button label="Export…" on clicked=|_| Message::HandleExportOpml />
+                </Box>
+                <Label
+                    label=opml_status.clone()
+                    visible=!opml_status.is_empty()
+                    xalign=0.0
+                    line_wrap=true
+                />
+                <Label label="Playback" xalign=0.0 margin_top=10 attributes={Some(heading_style.clone())} />
+                <Label
+                    label="Mark episodes as played:"
+                    xalign=0.0
+                    line_wrap=true
+                />
+                <ComboBoxText
+                    hexpand=false
+                    halign=Align::Start
+                    options=played_thresholds
+                    active_id=self.props.played_threshold.to_string()
+                    on property_active_id_notify=|widget| {
+                        Message::HandlePlayedThreshold(
+                            widget.get_active_id().and_then(|id| id.parse().ok()),
+                        )
+                    }
+                />
+                <Label label="Quality" xalign=0.0 margin_top=10 attributes={Some(heading_style)} />
+                <Label
+                    label="Some shows publish each episode in several versions. Choose which one to stream and download."
+                    xalign=0.0
+                    line_wrap=true
+                />
+                <ComboBoxText
+                    hexpand=false
+                    halign=Align::Start
+                    options=quality
+                    active_id=self.props.enclosure_preference.as_str().to_owned()
+                    on property_active_id_notify=|widget| {
+                        Message::HandleEnclosurePreference(EnclosurePreference::parse(
+                            &widget.get_active_id().map(|id| id.to_string()).unwrap_or_default(),
+                        ))
+                    }
+                />
+                <CheckButton
+                    label="Play video podcasts as audio only, to save data"
+                    active=self.props.video_as_audio
+                    on toggled=|widget| Message::HandleVideoAsAudio(widget.get_active())
+                />
             </Box>
         }
     }
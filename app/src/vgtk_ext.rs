@@ -1,10 +1,18 @@
 use libhandy::{Leaflet, LeafletExt};
+use state::timestamp_from_href;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
+use vgtk::lib::glib::object::ObjectExt;
 use vgtk::lib::glib::object::{CanDowncast, Cast, IsA, ObjectType};
+use vgtk::lib::glib::types::StaticType;
 use vgtk::lib::gtk::{
-    ComboBoxExt, ComboBoxText, ComboBoxTextExt, Container, ContainerExt, FlowBox, FlowBoxChild,
-    FlowBoxChildExt, FlowBoxExt, RangeExt, Scale, Stack, StackExt, TreeModelExt, Widget, WidgetExt,
+    AdjustmentExt, ComboBoxExt, ComboBoxText, ComboBoxTextExt, Container, ContainerExt, FlowBox,
+    FlowBoxChild, FlowBoxChildExt, FlowBoxExt, Inhibit, Label, LabelExt, ListBox, ListBoxExt,
+    RangeExt, Scale, ScrolledWindow, ScrolledWindowExt, Stack, StackExt, TreeModelExt, Widget,
+    WidgetExt,
 };
+use vgtk::Callback;
 
 pub trait GetNamedDescendants {
     fn get_named_descendants(&self) -> HashMap<String, Widget>;
@@ -120,6 +128,76 @@ impl RangeExtHelpers for Scale {
     }
 }
 
+const SCROLLED_TO_ROW_KEY: &str = "pyrocast_scrolled_to_row";
+
+pub trait ListBoxExtHelpers {
+    fn get_scrolled_to_row(&self) -> Option<i32>;
+    /// Scrolls the enclosing ScrolledWindow so the row is in the middle.
+    fn set_scrolled_to_row(&self, index: Option<i32>);
+}
+
+impl ListBoxExtHelpers for ListBox {
+    fn get_scrolled_to_row(&self) -> Option<i32> {
+        unsafe { self.get_data::<Option<i32>>(SCROLLED_TO_ROW_KEY) }
+            .copied()
+            .flatten()
+    }
+
+    fn set_scrolled_to_row(&self, index: Option<i32>) {
+        unsafe { self.set_data(SCROLLED_TO_ROW_KEY, index) };
+
+        let row = match index.and_then(|index| self.get_row_at_index(index)) {
+            Some(row) => row,
+            None => return,
+        };
+        let scrolled = self
+            .get_ancestor(ScrolledWindow::static_type())
+            .and_then(|widget| widget.downcast::<ScrolledWindow>().ok());
+
+        if let Some(adjustment) = scrolled.and_then(|scrolled| scrolled.get_vadjustment()) {
+            let allocation = row.get_allocation();
+            let center = allocation.y as f64 + allocation.height as f64 / 2.0;
+            // GTK clamps this to the scrollable range.
+            adjustment.set_value(center - adjustment.get_page_size() / 2.0);
+        }
+    }
+}
+
+const TIMESTAMP_HANDLER_KEY: &str = "pyrocast_timestamp_handler";
+
+pub trait LabelExtHelpers {
+    fn get_timestamp_handler(&self) -> Callback<u64>;
+    /// Sends the time of timestamp links in the label's markup here, instead of opening them.
+    fn set_timestamp_handler(&self, handler: Callback<u64>);
+}
+
+impl LabelExtHelpers for Label {
+    fn get_timestamp_handler(&self) -> Callback<u64> {
+        unsafe { self.get_data::<Rc<RefCell<Callback<u64>>>>(TIMESTAMP_HANDLER_KEY) }
+            .map(|current| current.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    fn set_timestamp_handler(&self, handler: Callback<u64>) {
+        // vgtk signal handlers can't return Inhibit, so connect once and swap the handler after.
+        let current = unsafe { self.get_data::<Rc<RefCell<Callback<u64>>>>(TIMESTAMP_HANDLER_KEY) };
+        if let Some(current) = current {
+            current.replace(handler);
+            return;
+        }
+
+        let current = Rc::new(RefCell::new(handler));
+        unsafe { self.set_data(TIMESTAMP_HANDLER_KEY, Rc::clone(&current)) };
+        self.connect_activate_link(move |_, uri| match timestamp_from_href(uri) {
+            Some(time) => {
+                current.borrow().send(time);
+                Inhibit(true)
+            }
+            None => Inhibit(false),
+        });
+    }
+}
+
 pub trait ComboBoxExtHelpers {
     fn get_options(&self) -> Vec<(String, String)>;
     fn set_options(&self, options: Vec<(String, String)>);
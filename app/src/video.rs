@@ -0,0 +1,72 @@
+use vgtk::lib::gdk::{keys::constants as keys, EventType};
+use vgtk::lib::gtk::{prelude::*, Box as GtkBox, Container, Inhibit, Widget, Window, WindowType};
+
+/// Moves the player's video into `container`, unless it is already there.
+pub fn attach(container: &GtkBox, video: &Widget) {
+    let parent = video.get_parent();
+    if parent.as_ref() == Some(container.upcast_ref::<Widget>()) {
+        return;
+    }
+    if let Some(parent) = parent.and_then(|parent| parent.downcast::<Container>().ok()) {
+        parent.remove(video);
+    }
+
+    video.set_hexpand(true);
+    video.set_vexpand(true);
+    container.add(video);
+    video.show();
+}
+
+/// Shows the video on its own, filling the screen, until the user presses Escape, double-clicks
+/// it or closes the window. It goes back where it was afterwards.
+pub fn show_fullscreen(video: &Widget) {
+    let container = match video
+        .get_parent()
+        .and_then(|parent| parent.downcast::<GtkBox>().ok())
+    {
+        Some(container) => container,
+        None => return,
+    };
+
+    let window = Window::new(WindowType::Toplevel);
+    window.set_title("Pyrocast");
+    container.remove(video);
+    window.add(video);
+
+    let restore = {
+        let video = video.clone();
+        move |window: &Window| {
+            window.remove(&video);
+            attach(&container, &video);
+            window.destroy();
+        }
+    };
+
+    let restore_clone = restore.clone();
+    window.connect_key_press_event(move |window, event| {
+        if event.get_keyval() == keys::Escape {
+            restore_clone(window);
+            Inhibit(true)
+        } else {
+            Inhibit(false)
+        }
+    });
+
+    let restore_clone = restore.clone();
+    window.connect_button_press_event(move |window, event| {
+        if event.get_event_type() == EventType::DoubleButtonPress {
+            restore_clone(window);
+            Inhibit(true)
+        } else {
+            Inhibit(false)
+        }
+    });
+
+    window.connect_delete_event(move |window, _| {
+        restore(window);
+        Inhibit(true)
+    });
+
+    window.fullscreen();
+    window.show_all();
+}
@@ -1,10 +1,66 @@
 use async_std::stream::StreamExt;
+use database::DatabaseAction;
+use futures::channel::oneshot;
 use loader::{Loader, Query};
 use player::PlayerAction;
 use state::{CurrentState, StateAction};
+use std::path::PathBuf;
 
-// For now, this is a way for me to sanity check things, not actually a useful CLI.
+/// Imports or exports subscriptions, e.g. `cli import-opml subscriptions.opml`.
+fn opml(command: &str, path: PathBuf) {
+    async_std::task::block_on(async {
+        let (current, mut waiter) = CurrentState::new();
+        let loader = Loader::new(current.clone(), 10);
+        let player = player::new_player(current.clone(), None);
+        let database = database::new_database(current.clone(), loader, player);
+
+        let (done, result) = oneshot::channel();
+        if command == "import-opml" {
+            database
+                .send(DatabaseAction::ImportOpml(path, done))
+                .unwrap();
+        } else {
+            // Wait for the subscriptions to load, so we know their feeds.
+            while waiter.next().await.is_some() {
+                let subscriptions = current.get().subscriptions();
+                if let Ok(subscriptions) = subscriptions.as_ref() {
+                    if subscriptions.iter().all(|channel| channel.core().is_some()) {
+                        break;
+                    }
+                }
+            }
+
+            database
+                .send(DatabaseAction::ExportOpml(path, done))
+                .unwrap();
+        }
+
+        match result.await {
+            Ok(Ok(count)) if command == "import-opml" => {
+                println!("Subscribed to {} channels", count)
+            }
+            Ok(Ok(count)) => println!("Exported {} channels", count),
+            Ok(Err(err)) => eprintln!("{}", err),
+            Err(_) => eprintln!("The database stopped unexpectedly"),
+        }
+    });
+}
+
+// For now, this is mostly a way for me to sanity check things, not actually a useful CLI.
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match (args.get(1).map(|arg| arg.as_str()), args.get(2)) {
+        (Some(command @ "import-opml"), Some(path))
+        | (Some(command @ "export-opml"), Some(path)) => {
+            return opml(command, PathBuf::from(path));
+        }
+        (Some(_), _) => {
+            eprintln!("Usage: cli [import-opml FILE | export-opml FILE]");
+            std::process::exit(1);
+        }
+        (None, _) => {}
+    }
+
     async_std::task::block_on(async {
         let (current, mut waiter) = CurrentState::new();
         let loader = Loader::new(current.clone(), 10);
@@ -69,7 +125,7 @@ fn main() {
 
         // let episode = episode.as_ref().as_ref().unwrap();
 
-        let pa = player::new_player(current.clone());
+        let pa = player::new_player(current.clone(), None);
         pa.send(PlayerAction::SetRate(2.0)).unwrap();
         pa.send(PlayerAction::PlayRemote {
             episode_pk: "1234".to_owned(), // episode.pk().to_owned(),
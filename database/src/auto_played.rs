@@ -0,0 +1,82 @@
+use state::{Playback, PlayerState};
+
+/// Marks episodes as played when they are nearly over.
+#[derive(Debug, Default)]
+pub(crate) struct AutoPlayed {
+    /// The last episode we marked as played.
+    last: Option<String>,
+}
+
+impl AutoPlayed {
+    /// The episode to mark as played, if it just ended or has less than `played_threshold` (ms)
+    /// left.
+    ///
+    /// This only happens once per episode, so it can still be marked as unplayed.
+    pub fn check(&mut self, player_state: &PlayerState, played_threshold: u64) -> Option<String> {
+        let near_end = player_state.duration > 0
+            && player_state.playback == Playback::Playing
+            && player_state.time + played_threshold >= player_state.duration;
+
+        if (near_end || player_state.playback == Playback::Ended)
+            && self.last.as_ref() != Some(&player_state.episode_pk)
+        {
+            self.last = Some(player_state.episode_pk.clone());
+            return self.last.clone();
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player_state(playback: Playback, time: u64) -> PlayerState {
+        PlayerState {
+            episode_pk: String::from("a"),
+            channel_pk: String::from("c"),
+            playback,
+            time,
+            duration: 100_000,
+            rate: 1.0,
+            video: false,
+        }
+    }
+
+    #[test]
+    fn marks_near_end_once() {
+        let mut auto_played = AutoPlayed::default();
+
+        assert_eq!(
+            auto_played.check(&player_state(Playback::Playing, 50_000), 30_000),
+            None
+        );
+        assert_eq!(
+            auto_played.check(&player_state(Playback::Playing, 70_000), 30_000),
+            Some(String::from("a"))
+        );
+        assert_eq!(
+            auto_played.check(&player_state(Playback::Playing, 80_000), 30_000),
+            None
+        );
+        assert_eq!(
+            auto_played.check(&player_state(Playback::Ended, 100_000), 30_000),
+            None
+        );
+    }
+
+    #[test]
+    fn marks_ended() {
+        let mut auto_played = AutoPlayed::default();
+
+        assert_eq!(
+            auto_played.check(&player_state(Playback::Paused, 90_000), 30_000),
+            None
+        );
+        assert_eq!(
+            auto_played.check(&player_state(Playback::Ended, 100_000), 0),
+            Some(String::from("a"))
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use state::{ChannelDetail, Download, DownloadPolicy, State};
+use std::collections::HashMap;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// What the download policy needs to know about an episode in a subscribed feed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Candidate {
+    pub episode_pk: String,
+    /// The size of the enclosure, if the feed says.
+    pub length: Option<u64>,
+    /// The size of the local copy, if the download is done.
+    pub downloaded: Option<u64>,
+    /// Whether the episode is waiting to be or being downloaded.
+    pub active: bool,
+    /// When the episode was marked as played (seconds since the epoch).
+    pub played_at: Option<i64>,
+    /// Whether the episode is loaded in the player. It is never deleted.
+    pub playing: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Plan {
+    pub download: Vec<String>,
+    pub delete: Vec<String>,
+}
+
+/// Decides which episodes of a subscription to download or delete.
+///
+/// `episodes` are in feed order, newest first. This applies to all downloads of the subscription,
+/// including ones that were started by hand.
+pub(crate) fn plan(
+    policy: &DownloadPolicy,
+    episodes: &[Candidate],
+    now: i64,
+    network_metered: bool,
+) -> Plan {
+    let mut plan = Plan::default();
+
+    // Downloads that survive, newest first.
+    let mut kept = vec![];
+    for episode in episodes {
+        if let Some(size) = episode.downloaded {
+            let expired = match (policy.delete_played_after, episode.played_at) {
+                (Some(days), Some(played_at)) => now - played_at >= days as i64 * SECONDS_PER_DAY,
+                _ => false,
+            };
+
+            if expired && !episode.playing {
+                plan.delete.push(episode.episode_pk.clone());
+            } else {
+                kept.push((episode, size));
+            }
+        }
+    }
+
+    let mut used: u64 = kept.iter().map(|(_, size)| size).sum();
+    if let Some(max_bytes) = policy.max_bytes {
+        // Make room by deleting the oldest downloads first.
+        for (episode, size) in kept.iter().rev() {
+            if used <= max_bytes {
+                break;
+            }
+            if !episode.playing {
+                plan.delete.push(episode.episode_pk.clone());
+                used -= size;
+            }
+        }
+    }
+
+    if policy.keep_latest == 0 || (policy.unmetered_only && network_metered) {
+        return plan;
+    }
+
+    kept.retain(|(episode, _)| !plan.delete.contains(&episode.episode_pk));
+
+    // If the feed doesn't say how big an episode is, guess from the others.
+    let average_size = if kept.is_empty() {
+        0
+    } else {
+        kept.iter().map(|(_, size)| size).sum::<u64>() / kept.len() as u64
+    };
+
+    for episode in episodes.iter().take(policy.keep_latest as usize) {
+        if episode.downloaded.is_some() || episode.active || episode.played_at.is_some() {
+            continue;
+        }
+
+        let expected_size = episode
+            .length
+            .filter(|&length| length > 0)
+            .unwrap_or(average_size);
+        if let Some(max_bytes) = policy.max_bytes {
+            if used + expected_size > max_bytes {
+                break;
+            }
+        }
+
+        plan.download.push(episode.episode_pk.clone());
+        used += expected_size;
+    }
+
+    plan
+}
+
+/// Decides which episodes of a subscribed feed to download or delete, given when each played
+/// episode was marked as played.
+pub(crate) fn apply(
+    state: &State,
+    channel_pk: &str,
+    details: &ChannelDetail,
+    played: &HashMap<String, i64>,
+    now: i64,
+) -> Plan {
+    let playing = state.player_state();
+    let candidates: Vec<_> = details
+        .episodes()
+        .iter()
+        .map(|episode| {
+            let download = state.download(episode.pk());
+            Candidate {
+                episode_pk: episode.pk().to_owned(),
+                length: episode
+                    .get()
+                    .as_deref()
+                    .and_then(|episode| episode.as_ref().ok())
+                    .and_then(|episode| episode.preferred_enclosure())
+                    .and_then(|enclosure| enclosure.length),
+                downloaded: match download {
+                    Some(Download::Done(path)) => Some(
+                        std::fs::metadata(path)
+                            .map(|metadata| metadata.len())
+                            .unwrap_or(0),
+                    ),
+                    _ => None,
+                },
+                active: download.map(|download| download.active()).unwrap_or(false),
+                played_at: played.get(episode.pk()).copied(),
+                playing: matches!(playing.as_ref(), Some(playing) if playing.episode_pk == episode.pk()),
+            }
+        })
+        .collect();
+
+    plan(
+        &state.download_policy(channel_pk),
+        &candidates,
+        now,
+        state.network_metered(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(episode_pk: &str) -> Candidate {
+        Candidate {
+            episode_pk: episode_pk.to_owned(),
+            ..Candidate::default()
+        }
+    }
+
+    #[test]
+    fn downloads_latest_unplayed() {
+        let policy = DownloadPolicy {
+            keep_latest: 2,
+            ..DownloadPolicy::default()
+        };
+        let episodes = vec![
+            Candidate {
+                played_at: Some(0),
+                ..episode("a")
+            },
+            episode("b"),
+            episode("c"),
+        ];
+
+        let plan = plan(&policy, &episodes, 0, false);
+        assert_eq!(plan.download, vec![String::from("b")]);
+        assert!(plan.delete.is_empty());
+    }
+
+    #[test]
+    fn waits_for_unmetered_network() {
+        let policy = DownloadPolicy {
+            keep_latest: 1,
+            ..DownloadPolicy::default()
+        };
+        let episodes = vec![episode("a")];
+
+        assert_eq!(plan(&policy, &episodes, 0, true), Plan::default());
+
+        let policy = DownloadPolicy {
+            unmetered_only: false,
+            ..policy
+        };
+        assert_eq!(
+            plan(&policy, &episodes, 0, true).download,
+            vec![String::from("a")]
+        );
+    }
+
+    #[test]
+    fn deletes_old_played_downloads() {
+        let policy = DownloadPolicy {
+            delete_played_after: Some(2),
+            ..DownloadPolicy::default()
+        };
+        let episodes = vec![
+            Candidate {
+                downloaded: Some(10),
+                played_at: Some(SECONDS_PER_DAY),
+                ..episode("a")
+            },
+            Candidate {
+                downloaded: Some(10),
+                played_at: Some(0),
+                ..episode("b")
+            },
+            Candidate {
+                downloaded: Some(10),
+                played_at: Some(0),
+                playing: true,
+                ..episode("c")
+            },
+        ];
+
+        let plan = plan(&policy, &episodes, 2 * SECONDS_PER_DAY, false);
+        assert_eq!(plan.delete, vec![String::from("b")]);
+    }
+
+    #[test]
+    fn stays_under_storage_limit() {
+        let policy = DownloadPolicy {
+            keep_latest: 3,
+            max_bytes: Some(25),
+            ..DownloadPolicy::default()
+        };
+        let episodes = vec![
+            episode("a"),
+            Candidate {
+                downloaded: Some(10),
+                ..episode("b")
+            },
+            Candidate {
+                downloaded: Some(10),
+                ..episode("c")
+            },
+            Candidate {
+                downloaded: Some(10),
+                ..episode("d")
+            },
+        ];
+
+        let plan = plan(&policy, &episodes, 0, false);
+        assert_eq!(plan.delete, vec![String::from("d")]);
+        assert!(plan.download.is_empty());
+    }
+
+    #[test]
+    fn guesses_size_from_kept_downloads() {
+        let policy = DownloadPolicy {
+            keep_latest: 2,
+            max_bytes: Some(30),
+            ..DownloadPolicy::default()
+        };
+        let episodes = vec![
+            episode("a"),
+            Candidate {
+                downloaded: Some(10),
+                ..episode("b")
+            },
+            Candidate {
+                downloaded: Some(50),
+                ..episode("c")
+            },
+        ];
+
+        // "c" is deleted to make room, so a new episode is expected to be about as big as "b".
+        let plan = plan(&policy, &episodes, 0, false);
+        assert_eq!(plan.delete, vec![String::from("c")]);
+        assert_eq!(plan.download, vec![String::from("a")]);
+    }
+
+    #[test]
+    fn uses_enclosure_length() {
+        let policy = DownloadPolicy {
+            keep_latest: 3,
+            max_bytes: Some(30),
+            ..DownloadPolicy::default()
+        };
+        let episodes = vec![
+            Candidate {
+                length: Some(20),
+                ..episode("a")
+            },
+            Candidate {
+                length: Some(20),
+                ..episode("b")
+            },
+        ];
+
+        // There are no other downloads to guess from, but the feed says how big they are.
+        let plan = plan(&policy, &episodes, 0, false);
+        assert_eq!(plan.download, vec![String::from("a")]);
+    }
+}
@@ -0,0 +1,222 @@
+use loader::{Loader, Query as LoaderQuery};
+use sqlite::{Connection, Statement};
+use state::{CurrentState, Download, EpisodeRef, State, StateAction};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A 64-bit FNV-1a hash. Unlike `DefaultHasher`, it doesn't change between Rust releases.
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Where to save an episode, based on a hash of its pk and the enclosure's extension.
+fn download_path(download_dir: &Path, episode_pk: &str, url: &str) -> PathBuf {
+    let url_path = url
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or_default();
+    let extension = Path::new(url_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| extension.len() <= 5 && extension.chars().all(char::is_alphanumeric))
+        .unwrap_or("audio");
+
+    download_dir.join(format!("{:016x}.{}", stable_hash(episode_pk), extension))
+}
+
+/// A download, as saved when it starts.
+///
+/// It keeps the URL and path it started with, since the preferred enclosure can change.
+struct SavedDownload {
+    url: String,
+    path: PathBuf,
+    done: bool,
+}
+
+/// Downloaded episodes, and ones that were started.
+pub(crate) struct Downloads<'l> {
+    download_dir: PathBuf,
+    saved: HashMap<String, SavedDownload>,
+    /// The last downloads we checked, so we only look for new downloads when they changed.
+    saved_state: Arc<HashMap<String, Download>>,
+    add_download: Statement<'l>,
+    remove_download: Statement<'l>,
+}
+
+impl<'l> Downloads<'l> {
+    /// Loads saved downloads, and shows the ones that are done.
+    pub fn open(
+        connection: &'l Connection,
+        current: &CurrentState,
+        download_dir: PathBuf,
+    ) -> Downloads<'l> {
+        let mut get_downloads = connection
+            .prepare(r#"select episode_pk, path, url, done from download"#)
+            .unwrap();
+
+        let mut remove_download = connection
+            .prepare(r#"delete from download where episode_pk = ?"#)
+            .unwrap();
+
+        let mut saved = HashMap::new();
+        let mut missing_downloads = vec![];
+        while get_downloads.next().unwrap() == sqlite::State::Row {
+            let episode_pk = get_downloads.read::<String>(0).unwrap();
+            let download = SavedDownload {
+                path: PathBuf::from(get_downloads.read::<String>(1).unwrap()),
+                url: get_downloads.read::<String>(2).unwrap(),
+                done: get_downloads.read::<i64>(3).unwrap() != 0,
+            };
+            if !download.done || download.path.exists() {
+                saved.insert(episode_pk, download);
+            } else {
+                missing_downloads.push(episode_pk);
+            }
+        }
+
+        // Forget about files that were deleted behind our back.
+        for episode_pk in missing_downloads {
+            remove_download.bind(1, &episode_pk as &str).unwrap();
+            remove_download.next().unwrap();
+            remove_download.reset().unwrap();
+        }
+
+        current.update(
+            saved
+                .iter()
+                .filter(|(_, download)| download.done)
+                .map(|(episode_pk, download)| {
+                    StateAction::SetDownload(
+                        episode_pk.to_owned(),
+                        Some(Download::Done(download.path.clone())),
+                    )
+                })
+                .collect(),
+        );
+
+        Downloads {
+            download_dir,
+            saved,
+            saved_state: current.get().downloads(),
+            add_download: connection
+                .prepare(r#"insert or replace into download values(?, ?, ?, ?)"#)
+                .unwrap(),
+            remove_download,
+        }
+    }
+
+    /// Saves downloads that finished since we last checked.
+    pub fn save(&mut self, state: &State) {
+        let state_downloads = state.downloads();
+        if Arc::ptr_eq(&state_downloads, &self.saved_state) {
+            return;
+        }
+
+        for (episode_pk, download) in state_downloads.iter() {
+            if let Download::Done(path) = download {
+                let saved = self.saved.get_mut(episode_pk);
+                if let Some(saved) = saved.filter(|saved| !saved.done && &saved.path == path) {
+                    self.add_download.bind(1, episode_pk as &str).unwrap();
+                    self.add_download
+                        .bind(2, path.to_str().expect("Data directory must be utf-8"))
+                        .unwrap();
+                    self.add_download.bind(3, &saved.url as &str).unwrap();
+                    self.add_download.bind(4, 1_i64).unwrap();
+                    self.add_download.next().unwrap();
+                    self.add_download.reset().unwrap();
+
+                    saved.done = true;
+                }
+            }
+        }
+
+        self.saved_state = state_downloads;
+    }
+
+    /// Asks the loader for episodes that aren't downloaded or being downloaded.
+    pub fn start(
+        &mut self,
+        current: &CurrentState,
+        loader: &Loader,
+        state: &State,
+        episodes: Vec<EpisodeRef>,
+    ) {
+        for episode in episodes {
+            let active = state
+                .download(episode.pk())
+                .map(|download| download.active() || download.uri().is_some())
+                .unwrap_or(false);
+
+            if let (false, Some(Ok(episode))) = (active, episode.get().as_deref()) {
+                let download_dir = &self.download_dir;
+                let add_download = &mut self.add_download;
+
+                // Resume with the enclosure we started with, even if another one is preferred now.
+                let download = self
+                    .saved
+                    .entry(episode.pk().to_owned())
+                    .or_insert_with(|| {
+                        let url = episode.media_url().to_owned();
+                        let path = download_path(download_dir, episode.pk(), &url);
+
+                        add_download.bind(1, episode.pk()).unwrap();
+                        add_download
+                            .bind(2, path.to_str().expect("Data directory must be utf-8"))
+                            .unwrap();
+                        add_download.bind(3, &url as &str).unwrap();
+                        add_download.bind(4, 0_i64).unwrap();
+                        add_download.next().unwrap();
+                        add_download.reset().unwrap();
+
+                        SavedDownload {
+                            url,
+                            path,
+                            done: false,
+                        }
+                    });
+
+                current.update(vec![StateAction::SetDownload(
+                    episode.pk().to_owned(),
+                    Some(Download::Queued),
+                )]);
+                loader.queue(LoaderQuery::Download {
+                    episode_pk: episode.pk().to_owned(),
+                    url: download.url.clone(),
+                    path: download.path.clone(),
+                });
+            }
+        }
+    }
+
+    /// Deletes downloads, whether they are done or not.
+    pub fn delete(&mut self, current: &CurrentState, episode_pks: Vec<String>) {
+        for episode_pk in episode_pks {
+            if let Some(download) = self.saved.remove(&episode_pk) {
+                let path = &download.path;
+                for partial in &[loader::partial_path(path), loader::validator_path(path)] {
+                    match std::fs::remove_file(partial) {
+                        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                            eprintln!("Could not delete {}: {}", partial.display(), err);
+                        }
+                        _ => {}
+                    }
+                }
+
+                if download.done {
+                    if let Err(err) = std::fs::remove_file(path) {
+                        eprintln!("Could not delete {}: {}", path.display(), err);
+                    }
+                }
+
+                self.remove_download.bind(1, &episode_pk as &str).unwrap();
+                self.remove_download.next().unwrap();
+                self.remove_download.reset().unwrap();
+            }
+
+            current.update(vec![StateAction::SetDownload(episode_pk, None)]);
+        }
+    }
+}
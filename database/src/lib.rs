@@ -1,20 +1,95 @@
+mod auto_played;
+mod download_policy;
+mod downloads;
+mod new_episodes;
+pub mod opml;
+mod refresh;
+mod subscription;
+
+use auto_played::AutoPlayed;
 use directories::ProjectDirs;
+use downloads::Downloads;
+use futures::channel::oneshot;
 use loader::{Loader, Query as LoaderQuery};
+use new_episodes::NewEpisodes;
+use player::PlayerAction;
+use refresh::Refresh;
 use sqlite::{Connection, OpenFlags};
-use state::{ChannelRef, CurrentState, StateAction};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use state::{
+    ChannelCore, ChannelDetail, ChannelRef, Chapter, CurrentState, Download, DownloadPolicy,
+    Enclosure, EnclosurePreference, Episode, EpisodeRef, EpisodeType, FeedCache, Funding, Image,
+    Location, Person, Playback, Progress, QueueItem, ShowType, StateAction, StateError,
+    TranscriptLink,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub enum DatabaseAction {
     Subscribe(ChannelRef),
     Unsubscribe(ChannelRef),
+    MarkPlayed(EpisodeRef),
+    MarkUnplayed(EpisodeRef),
+    Download(EpisodeRef),
+    CancelDownload(EpisodeRef),
+    DeleteDownload(EpisodeRef),
+    SetDownloadPolicy(ChannelRef, DownloadPolicy),
+    SetEnclosurePreference(EnclosurePreference),
+    SetVideoAsAudio(bool),
+    /// Episodes are marked as played when there is less than this much time left (ms).
+    SetPlayedThreshold(u64),
+    /// Subscribes to the feeds in an OPML file, and replies with how many were new.
+    ImportOpml(PathBuf, OpmlReply),
+    /// Writes all subscriptions to an OPML file, and replies with how many were written.
+    ExportOpml(PathBuf, OpmlReply),
+    /// Forgets which of a channel's episodes are new, once the user has seen them.
+    ClearNewEpisodes(ChannelRef),
+}
+
+pub type OpmlReply = oneshot::Sender<Result<usize, StateError>>;
+
+/// A location as saved: its name, geo URI and OpenStreetMap id, which are empty if missing.
+fn saved_location(name: String, geo: String, osm: String) -> Option<Location> {
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(Location {
+        name,
+        geo: Some(geo).filter(|geo| !geo.is_empty()),
+        osm: Some(osm).filter(|osm| !osm.is_empty()),
+    })
+}
+
+/// Seconds since the epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Runs a migration, so that it applies completely or not at all.
+fn migrate(connection: &Connection, sql: &str) {
+    connection.execute("begin").unwrap();
+    connection.execute(sql).unwrap();
+    connection.execute("commit").unwrap();
 }
 
-fn database_thread(recv: Receiver<DatabaseAction>, current: Arc<CurrentState>, loader: Loader) {
+fn database_thread(
+    recv: Receiver<DatabaseAction>,
+    current: Arc<CurrentState>,
+    loader: Loader,
+    player: Sender<PlayerAction>,
+) {
     let dirs =
         ProjectDirs::from("ca", "nettek", "Pyrocast").expect("Cannot find project directories");
     let data_dir = dirs.data_dir();
     std::fs::create_dir_all(&data_dir).expect("Cannot create project data directory");
+    let download_dir = data_dir.join("downloads");
+    std::fs::create_dir_all(&download_dir).expect("Cannot create download directory");
     let db_name = data_dir
         .join("pyrocast.sqlite")
         .to_str()
@@ -39,17 +114,92 @@ fn database_thread(recv: Receiver<DatabaseAction>, current: Arc<CurrentState>, l
         )
         .unwrap();
 
-    let mut get_db_version = connection
-        .prepare(r#"select value from meta where id = "version""#)
-        .unwrap();
-    let db_version = if get_db_version.next().unwrap() == sqlite::State::Row {
-        get_db_version.read::<i64>(0).unwrap_or(0)
-    } else {
-        0
+    // Finish with the statement before migrating, so that it can't hold up a commit.
+    let db_version = {
+        let mut get_db_version = connection
+            .prepare(r#"select value from meta where id = "version""#)
+            .unwrap();
+        if get_db_version.next().unwrap() == sqlite::State::Row {
+            get_db_version.read::<i64>(0).unwrap_or(0)
+        } else {
+            0
+        }
     };
 
-    if db_version == 0 {
-        connection.execute(include_str!("./schema.sql")).unwrap();
+    if db_version < 1 {
+        migrate(&connection, include_str!("./schema.sql"));
+    }
+    if db_version < 2 {
+        migrate(&connection, include_str!("./schema_2.sql"));
+    }
+    if db_version < 3 {
+        migrate(&connection, include_str!("./schema_3.sql"));
+    }
+    if db_version < 4 {
+        migrate(&connection, include_str!("./schema_4.sql"));
+    }
+    if db_version < 5 {
+        migrate(&connection, include_str!("./schema_5.sql"));
+    }
+    if db_version < 6 {
+        migrate(&connection, include_str!("./schema_6.sql"));
+    }
+    if db_version < 7 {
+        migrate(&connection, include_str!("./schema_7.sql"));
+    }
+    if db_version < 8 {
+        migrate(&connection, include_str!("./schema_8.sql"));
+    }
+    if db_version < 9 {
+        migrate(&connection, include_str!("./schema_9.sql"));
+    }
+    if db_version < 10 {
+        migrate(&connection, include_str!("./schema_10.sql"));
+    }
+    if db_version < 11 {
+        migrate(&connection, include_str!("./schema_11.sql"));
+    }
+    if db_version < 12 {
+        migrate(&connection, include_str!("./schema_12.sql"));
+    }
+    if db_version < 13 {
+        migrate(&connection, include_str!("./schema_13.sql"));
+    }
+    if db_version < 14 {
+        migrate(&connection, include_str!("./schema_14.sql"));
+    }
+    if db_version < 15 {
+        migrate(&connection, include_str!("./schema_15.sql"));
+    }
+    if db_version < 16 {
+        migrate(&connection, include_str!("./schema_16.sql"));
+    }
+    if db_version < 17 {
+        migrate(&connection, include_str!("./schema_17.sql"));
+    }
+
+    let mut get_settings = connection
+        .prepare(r#"select id, value from setting"#)
+        .unwrap();
+
+    while get_settings.next().unwrap() == sqlite::State::Row {
+        let value = get_settings.read::<String>(1).unwrap();
+        match get_settings.read::<String>(0).unwrap().as_str() {
+            "enclosure_preference" => {
+                current.update(vec![StateAction::SetEnclosurePreference(
+                    EnclosurePreference::parse(&value),
+                )]);
+            }
+            "video_as_audio" => {
+                current.update(vec![StateAction::SetVideoAsAudio(value == "1")]);
+            }
+            "played_threshold" => {
+                if let Ok(played_threshold) = value.parse() {
+                    current.update(vec![StateAction::SetPlayedThreshold(played_threshold)]);
+                }
+            }
+            _ => {}
+        }
     }
 
     let mut subscriptions: Vec<String> = vec![];
@@ -71,9 +221,339 @@ fn database_thread(recv: Receiver<DatabaseAction>, current: Arc<CurrentState>, l
 
     send_subscriptions(&subscriptions);
 
+    // Show subscriptions as we last saw them, so they can be browsed offline. The loader refreshes
+    // them in the background.
+    let blank = current.get();
+
+    let mut get_channel_cores = connection
+        .prepare(
+            r#"select pk, title, author, image_200, image_600, image_rss, rss, rss_moved, ended
+            from channel_core"#,
+        )
+        .unwrap();
+
+    let mut saved = vec![];
+    let mut saved_images = vec![];
+    // Feeds that moved, by channel, and feeds that are gone.
+    let mut moved_feeds = HashMap::new();
+    let mut ended_feeds = HashSet::new();
+    while get_channel_cores.next().unwrap() == sqlite::State::Row {
+        let column = |idx| get_channel_cores.read::<String>(idx).unwrap();
+
+        let mut core = blank
+            .new_channel_core()
+            .with_pk(column(0))
+            .with_title(column(1))
+            .with_author(column(2));
+        if !column(3).is_empty() {
+            core = core.with_image_200(column(3));
+        }
+        if !column(4).is_empty() {
+            core = core.with_image_600(column(4));
+        }
+        if !column(5).is_empty() {
+            core = core.with_image_rss(column(5));
+        }
+        if !column(6).is_empty() {
+            core = core.with_rss(column(6));
+        }
+        if get_channel_cores.read::<i64>(7).unwrap() != 0 {
+            core = core.with_rss_moved(true);
+            moved_feeds.insert(column(0), column(6));
+        }
+        if get_channel_cores.read::<i64>(8).unwrap() != 0 {
+            core = core.with_ended(true);
+            ended_feeds.insert(column(0));
+        }
+
+        if let Some(image) = core.image_rss().or_else(|| core.image_600()) {
+            saved_images.push(image.to_owned());
+        }
+        saved.push(core.build());
+    }
+
+    let mut get_chapters = connection
+        .prepare(r#"select episode_pk, start, title, image, url from chapter order by episode_pk, position"#)
+        .unwrap();
+
+    // The chapters of each episode, as saved.
+    let mut chapters: HashMap<String, Vec<Chapter>> = HashMap::new();
+    while get_chapters.next().unwrap() == sqlite::State::Row {
+        let column = |idx| get_chapters.read::<String>(idx).unwrap();
+
+        chapters.entry(column(0)).or_default().push(Chapter {
+            start: get_chapters.read::<i64>(1).unwrap() as u64,
+            title: column(2),
+            image: Some(column(3)).filter(|image| !image.is_empty()),
+            url: Some(column(4)).filter(|url| !url.is_empty()),
+        });
+    }
+
+    let mut get_persons = connection
+        .prepare(
+            r#"select channel_pk, episode_pk, name, role, person_group, image, href from person
+            order by channel_pk, episode_pk, position"#,
+        )
+        .unwrap();
+
+    // The people on each channel and episode, as saved. A channel's own people have no episode pk.
+    let mut persons: HashMap<(String, String), Vec<Person>> = HashMap::new();
+    while get_persons.next().unwrap() == sqlite::State::Row {
+        let column = |idx| get_persons.read::<String>(idx).unwrap();
+
+        persons
+            .entry((column(0), column(1)))
+            .or_default()
+            .push(Person {
+                name: column(2),
+                role: column(3),
+                group: column(4),
+                image: Some(column(5)).filter(|image| !image.is_empty()),
+                href: Some(column(6)).filter(|href| !href.is_empty()),
+            });
+    }
+
+    let mut get_funding = connection
+        .prepare(r#"select channel_pk, url, title from funding order by channel_pk, position"#)
+        .unwrap();
+
+    let mut funding: HashMap<String, Vec<Funding>> = HashMap::new();
+    while get_funding.next().unwrap() == sqlite::State::Row {
+        let column = |idx| get_funding.read::<String>(idx).unwrap();
+
+        funding.entry(column(0)).or_default().push(Funding {
+            url: column(1),
+            title: column(2),
+        });
+    }
+
+    let mut get_enclosures = connection
+        .prepare(
+            r#"select episode_pk, url, mimetype, length, bitrate, title from enclosure
+            order by episode_pk, position"#,
+        )
+        .unwrap();
+
+    // Every variant of each episode, as saved.
+    let mut enclosures: HashMap<String, Vec<Enclosure>> = HashMap::new();
+    while get_enclosures.next().unwrap() == sqlite::State::Row {
+        let column = |idx| get_enclosures.read::<String>(idx).unwrap();
+        let number = |idx| Some(get_enclosures.read::<i64>(idx).unwrap() as u64).filter(|n| *n > 0);
+
+        enclosures.entry(column(0)).or_default().push(Enclosure {
+            url: column(1),
+            mimetype: Some(column(2)).filter(|mimetype| !mimetype.is_empty()),
+            length: number(3),
+            bitrate: number(4),
+            title: Some(column(5)).filter(|title| !title.is_empty()),
+        });
+    }
+
+    let mut get_episodes = connection
+        .prepare(
+            r#"select pk, channel_pk, title, url, date, description, duration, image, audio,
+            chapters_url, transcript_url, transcript_type, notes, season, season_name, number,
+            episode_type, location, location_geo, location_osm
+            from episode order by channel_pk, position"#,
+        )
+        .unwrap();
+
+    let mut saved_episodes: HashMap<String, Vec<String>> = HashMap::new();
+    while get_episodes.next().unwrap() == sqlite::State::Row {
+        let column = |idx| get_episodes.read::<String>(idx).unwrap();
+        let number = |idx| Some(get_episodes.read::<i64>(idx).unwrap() as u32).filter(|n| *n > 0);
+
+        saved_episodes.entry(column(1)).or_default().push(column(0));
+        saved.push(
+            blank
+                .new_episode()
+                .with_pk(column(0))
+                .with_channel(column(1))
+                .with_title(column(2))
+                .with_url(column(3))
+                .with_date(column(4))
+                .with_description(column(5))
+                .with_notes(column(12))
+                .with_duration(column(6))
+                .with_image(Some(column(7)).filter(|image| !image.is_empty()))
+                .with_audio(column(8))
+                .with_enclosures(enclosures.remove(&column(0)).unwrap_or_default())
+                .with_chapters(chapters.get(&column(0)).cloned().unwrap_or_default())
+                .with_chapters_url(Some(column(9)).filter(|url| !url.is_empty()))
+                .with_transcript_link(Some(column(10)).filter(|url| !url.is_empty()).map(|url| {
+                    TranscriptLink {
+                        url,
+                        mimetype: Some(column(11)).filter(|mimetype| !mimetype.is_empty()),
+                    }
+                }))
+                .with_season(number(13))
+                .with_season_name(Some(column(14)).filter(|name| !name.is_empty()))
+                .with_number(number(15))
+                .with_episode_type(EpisodeType::parse(&column(16)))
+                .with_persons(persons.remove(&(column(1), column(0))).unwrap_or_default())
+                .with_location(saved_location(column(17), column(18), column(19)))
+                .build(),
+        );
+    }
+
+    let mut get_channel_details = connection
+        .prepare(
+            r#"select pk, description, link, show_type, guid, locked, location, location_geo,
+            location_osm
+            from channel_detail"#,
+        )
+        .unwrap();
+
+    while get_channel_details.next().unwrap() == sqlite::State::Row {
+        let column = |idx| get_channel_details.read::<String>(idx).unwrap();
+
+        let pk = column(0);
+        let episodes = saved_episodes.remove(&pk).unwrap_or_default();
+
+        saved.push(
+            blank
+                .new_channel_detail()
+                .with_pk(pk.clone())
+                .with_description(column(1))
+                .with_link(column(2))
+                .with_show_type(ShowType::parse(&column(3)))
+                .with_guid(Some(column(4)).filter(|guid| !guid.is_empty()))
+                .with_locked(get_channel_details.read::<i64>(5).unwrap() != 0)
+                .with_location(saved_location(column(6), column(7), column(8)))
+                .with_persons(
+                    persons
+                        .remove(&(pk.clone(), String::new()))
+                        .unwrap_or_default(),
+                )
+                .with_funding(funding.remove(&pk).unwrap_or_default())
+                .with_episodes(&episodes)
+                .build(),
+        );
+    }
+    current.update(saved);
+
     for pk in &subscriptions {
-        loader.queue(LoaderQuery::ItunesLookup { pk: pk.to_owned() });
+        // Ended feeds won't have anything new.
+        if ended_feeds.contains(pk) {
+            continue;
+        }
+
+        loader.queue(match moved_feeds.get(pk) {
+            // Directories may not know that the feed moved.
+            Some(rss) => LoaderQuery::Rss {
+                pk: pk.to_owned(),
+                url: rss.to_owned(),
+            },
+            None => LoaderQuery::channel(pk.to_owned()),
+        });
+    }
+    for image in saved_images {
+        loader.queue(LoaderQuery::Image {
+            image: Arc::new(Image::new(&image)),
+            associated_query: None,
+        });
+    }
+
+    let mut get_progress = connection
+        .prepare(r#"select episode_pk, time, duration from episode_progress"#)
+        .unwrap();
+
+    let mut progress = vec![];
+    while get_progress.next().unwrap() == sqlite::State::Row {
+        progress.push((
+            get_progress.read::<String>(0).unwrap(),
+            Progress {
+                time: get_progress.read::<i64>(1).unwrap() as u64,
+                duration: get_progress.read::<i64>(2).unwrap() as u64,
+            },
+        ));
+    }
+    current.update(vec![StateAction::SetProgress(progress)]);
+
+    let mut get_played = connection
+        .prepare(r#"select episode_pk, played_at from played_episode"#)
+        .unwrap();
+
+    // When each played episode was marked as played.
+    let mut played = HashMap::new();
+    while get_played.next().unwrap() == sqlite::State::Row {
+        played.insert(
+            get_played.read::<String>(0).unwrap(),
+            get_played.read::<i64>(1).unwrap(),
+        );
+    }
+    current.update(
+        played
+            .keys()
+            .map(|episode_pk| StateAction::SetPlayed(episode_pk.to_owned(), true))
+            .collect(),
+    );
+
+    let mut get_queue = connection
+        .prepare(r#"select episode_pk, channel_pk, uri from queue order by position"#)
+        .unwrap();
+
+    let mut queue = vec![];
+    while get_queue.next().unwrap() == sqlite::State::Row {
+        queue.push(QueueItem {
+            episode_pk: get_queue.read::<String>(0).unwrap(),
+            channel_pk: get_queue.read::<String>(1).unwrap(),
+            uri: get_queue.read::<String>(2).unwrap(),
+        });
+    }
+    player.send(PlayerAction::SetQueue(queue)).unwrap();
+
+    let mut downloads = Downloads::open(&connection, &current, download_dir);
+
+    let mut get_download_policies = connection
+        .prepare(
+            r#"select channel_pk, keep_latest, unmetered_only, delete_played_after, max_bytes
+            from download_policy"#,
+        )
+        .unwrap();
+
+    let mut download_policies = vec![];
+    while get_download_policies.next().unwrap() == sqlite::State::Row {
+        let delete_played_after = get_download_policies.read::<i64>(3).unwrap();
+        let max_bytes = get_download_policies.read::<i64>(4).unwrap();
+
+        download_policies.push(StateAction::SetDownloadPolicy(
+            get_download_policies.read::<String>(0).unwrap(),
+            DownloadPolicy {
+                keep_latest: get_download_policies.read::<i64>(1).unwrap() as u32,
+                unmetered_only: get_download_policies.read::<i64>(2).unwrap() != 0,
+                delete_played_after: Some(delete_played_after as u32).filter(|days| *days > 0),
+                max_bytes: Some(max_bytes as u64).filter(|bytes| *bytes > 0),
+            },
+        ));
+    }
+    current.update(download_policies);
+
+    let mut new_episodes = NewEpisodes::open(&connection, &current);
+
+    let mut get_feed_caches = connection
+        .prepare(r#"select url, etag, last_modified, checked from feed_cache"#)
+        .unwrap();
+
+    let mut feed_caches = HashMap::new();
+    while get_feed_caches.next().unwrap() == sqlite::State::Row {
+        let etag = get_feed_caches.read::<String>(1).unwrap();
+        let last_modified = get_feed_caches.read::<String>(2).unwrap();
+        feed_caches.insert(
+            get_feed_caches.read::<String>(0).unwrap(),
+            FeedCache {
+                etag: Some(etag).filter(|etag| !etag.is_empty()),
+                last_modified: Some(last_modified).filter(|date| !date.is_empty()),
+                checked: get_feed_caches.read::<i64>(3).unwrap(),
+            },
+        );
     }
+    current.update(
+        feed_caches
+            .iter()
+            .map(|(url, cache)| StateAction::SetFeedCache(url.to_owned(), cache.clone()))
+            .collect(),
+    );
 
     let mut add_subscription = connection
         .prepare(r#"insert into subscription values(?)"#)
@@ -83,39 +563,797 @@ fn database_thread(recv: Receiver<DatabaseAction>, current: Arc<CurrentState>, l
         .prepare(r#"delete from subscription where pk = ?"#)
         .unwrap();
 
-    while let Ok(ev) = recv.recv() {
+    let mut set_progress = connection
+        .prepare(r#"insert or replace into episode_progress values(?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut add_played = connection
+        .prepare(r#"insert or replace into played_episode values(?, ?)"#)
+        .unwrap();
+
+    let mut remove_played = connection
+        .prepare(r#"delete from played_episode where episode_pk = ?"#)
+        .unwrap();
+
+    let mut clear_queue = connection.prepare(r#"delete from queue"#).unwrap();
+
+    let mut add_to_queue = connection
+        .prepare(r#"insert into queue values(?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut set_download_policy = connection
+        .prepare(r#"insert or replace into download_policy values(?, ?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut remove_download_policy = connection
+        .prepare(r#"delete from download_policy where channel_pk = ?"#)
+        .unwrap();
+
+    let mut set_channel_core = connection
+        .prepare(r#"insert or replace into channel_core values(?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut set_channel_detail = connection
+        .prepare(r#"insert or replace into channel_detail values(?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+        .unwrap();
+
+    // A subscription to the same feed under another pk, found by its podcast:guid.
+    let mut find_channel_guid = connection
+        .prepare(r#"select pk from channel_detail where guid = ? and pk != ?"#)
+        .unwrap();
+
+    let mut add_episode = connection
+        .prepare(
+            r#"insert or replace into episode values(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .unwrap();
+
+    let mut remove_persons = connection
+        .prepare(r#"delete from person where channel_pk = ?"#)
+        .unwrap();
+
+    let mut add_person = connection
+        .prepare(r#"insert into person values(?, ?, ?, ?, ?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut remove_enclosures = connection
+        .prepare(r#"delete from enclosure where channel_pk = ?"#)
+        .unwrap();
+
+    let mut add_enclosure = connection
+        .prepare(r#"insert or replace into enclosure values(?, ?, ?, ?, ?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut set_setting = connection
+        .prepare(r#"insert or replace into setting values(?, ?)"#)
+        .unwrap();
+
+    let mut remove_funding = connection
+        .prepare(r#"delete from funding where channel_pk = ?"#)
+        .unwrap();
+
+    let mut add_funding = connection
+        .prepare(r#"insert into funding values(?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut remove_chapters = connection
+        .prepare(r#"delete from chapter where episode_pk = ?"#)
+        .unwrap();
+
+    let mut add_chapter = connection
+        .prepare(r#"insert into chapter values(?, ?, ?, ?, ?, ?)"#)
+        .unwrap();
+
+    let mut remove_channel_core = connection
+        .prepare(r#"delete from channel_core where pk = ?"#)
+        .unwrap();
+
+    let mut remove_channel_detail = connection
+        .prepare(r#"delete from channel_detail where pk = ?"#)
+        .unwrap();
+
+    let mut remove_episodes = connection
+        .prepare(r#"delete from episode where channel_pk = ?"#)
+        .unwrap();
+
+    let mut set_feed_cache = connection
+        .prepare(r#"insert or replace into feed_cache values(?, ?, ?, ?)"#)
+        .unwrap();
+
+    // The last feed caches we checked, so we only look for changes when they changed.
+    let mut saved_feed_caches = current.get().feed_caches();
+
+    let mut refresh = Refresh::new();
+
+    // The core of each subscription when we last saved it.
+    let mut saved_cores: HashMap<String, Arc<Result<ChannelCore, StateError>>> = HashMap::new();
+
+    // The feed of each subscription when we last saved it, looked for new episodes and applied its
+    // download policy.
+    let mut refreshed_details: HashMap<String, Arc<Result<ChannelDetail, StateError>>> =
+        HashMap::new();
+
+    // Whether the network was metered when we last applied download policies.
+    let mut network_metered = current.get().network_metered();
+
+    // The last queue we saved, so we only write when it changed.
+    let mut saved_queue = current.get().queue();
+
+    // The last player state we saved, so we only write when something changed.
+    let mut saved_progress: Option<(String, Playback, u64)> = None;
+
+    // Saves an episode's chapters, if they changed.
+    let mut save_chapters = |episode: &Episode| {
+        let saved = chapters.get(episode.pk()).map(|saved| &saved[..]);
+        if saved.unwrap_or_default() == episode.feed_chapters() {
+            return;
+        }
+
+        remove_chapters.bind(1, episode.pk()).unwrap();
+        remove_chapters.next().unwrap();
+        remove_chapters.reset().unwrap();
+
+        for (position, chapter) in episode.feed_chapters().iter().enumerate() {
+            add_chapter.bind(1, episode.pk()).unwrap();
+            add_chapter.bind(2, position as i64).unwrap();
+            add_chapter.bind(3, chapter.start as i64).unwrap();
+            add_chapter.bind(4, &chapter.title as &str).unwrap();
+            add_chapter
+                .bind(5, chapter.image.as_deref().unwrap_or_default())
+                .unwrap();
+            add_chapter
+                .bind(6, chapter.url.as_deref().unwrap_or_default())
+                .unwrap();
+            add_chapter.next().unwrap();
+            add_chapter.reset().unwrap();
+        }
+
+        chapters.insert(episode.pk().to_owned(), episode.feed_chapters().to_vec());
+    };
+
+    // Episodes whose chapters file we asked the loader for.
+    let mut requested_chapters = HashSet::new();
+
+    // Episodes whose transcript we asked the loader for.
+    let mut requested_transcripts = HashSet::new();
+
+    // Downloaded episodes whose tags we asked the loader to read.
+    let mut requested_embedded_chapters = HashSet::new();
+
+    // Chapter images we asked the loader for.
+    let mut requested_chapter_images = HashSet::new();
+
+    // Images of people on shown channels and episodes that we asked the loader for.
+    let mut requested_person_images = HashSet::new();
+
+    let mut auto_played = AutoPlayed::default();
+
+    // The OPML importer sends a channel pk for each feed here once it has found them. It doesn't
+    // use `recv`, so that the thread stops once everyone else is done with the database.
+    let (send_imported, recv_imported) = channel();
+
+    loop {
+        let ev = match recv.recv_timeout(Duration::from_secs(1)) {
+            Ok(ev) => Some(ev),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        // Episodes to mark as played (true) or unplayed (false).
+        let mut set_played = vec![];
+
+        // Channels whose new episodes have been seen.
+        let mut clear_new = vec![];
+
+        // Channels to subscribe to, and OPML imports to reply to once they are subscribed.
+        let mut subscribe = vec![];
+        let mut imported = None;
+        if let Ok((channel_pks, done)) = recv_imported.try_recv() {
+            subscribe.extend(channel_pks);
+            imported = Some(done);
+        }
+
+        // Episodes to download, and episodes whose download should be deleted.
+        let mut start_downloads = vec![];
+        let mut delete_downloads = vec![];
+
+        let state = current.get();
+
+        let queue = state.queue();
+        if !Arc::ptr_eq(&queue, &saved_queue) {
+            clear_queue.next().unwrap();
+            clear_queue.reset().unwrap();
+
+            for (position, item) in queue.iter().enumerate() {
+                add_to_queue.bind(1, position as i64).unwrap();
+                add_to_queue.bind(2, &item.episode_pk as &str).unwrap();
+                add_to_queue.bind(3, &item.channel_pk as &str).unwrap();
+                add_to_queue.bind(4, &item.uri as &str).unwrap();
+                add_to_queue.next().unwrap();
+                add_to_queue.reset().unwrap();
+            }
+
+            saved_queue = queue;
+        }
+
+        downloads.save(&state);
+        let state_feed_caches = state.feed_caches();
+        if !Arc::ptr_eq(&state_feed_caches, &saved_feed_caches) {
+            for (url, cache) in state_feed_caches.iter() {
+                if feed_caches.get(url) != Some(cache) {
+                    set_feed_cache.bind(1, url as &str).unwrap();
+                    set_feed_cache
+                        .bind(2, cache.etag.as_deref().unwrap_or_default())
+                        .unwrap();
+                    set_feed_cache
+                        .bind(3, cache.last_modified.as_deref().unwrap_or_default())
+                        .unwrap();
+                    set_feed_cache.bind(4, cache.checked).unwrap();
+                    set_feed_cache.next().unwrap();
+                    set_feed_cache.reset().unwrap();
+
+                    feed_caches.insert(url.to_owned(), cache.clone());
+                }
+            }
+
+            saved_feed_caches = state_feed_caches;
+        }
+
+        // Chapters, their images and transcripts are only loaded for the episode being played.
+        if let Some(Ok(episode)) = state.playing_episode().as_deref() {
+            if let Some(url) = episode.chapters_url().filter(|_| episode.needs_chapters()) {
+                if requested_chapters.insert(episode.pk().to_owned()) {
+                    loader.queue(LoaderQuery::Chapters {
+                        episode_pk: episode.pk().to_owned(),
+                        url: url.to_owned(),
+                    });
+                }
+            }
+            if let Some(link) = episode
+                .transcript_link()
+                .filter(|_| episode.needs_transcript())
+            {
+                if requested_transcripts.insert(episode.pk().to_owned()) {
+                    loader.queue(LoaderQuery::Transcript {
+                        episode_pk: episode.pk().to_owned(),
+                        link: link.clone(),
+                    });
+                }
+            }
+
+            if let Some(Download::Done(path)) = state.download(episode.pk()) {
+                if requested_embedded_chapters.insert(episode.pk().to_owned()) {
+                    loader.queue(LoaderQuery::EmbeddedChapters {
+                        episode_pk: episode.pk().to_owned(),
+                        path: path.clone(),
+                    });
+                }
+            }
+            for chapter in episode.chapters() {
+                if let Some(image) = &chapter.image {
+                    if state.image(image).is_none()
+                        && requested_chapter_images.insert(image.to_owned())
+                    {
+                        loader.queue(LoaderQuery::Image {
+                            image: Arc::new(Image::new(image)),
+                            associated_query: None,
+                        });
+                    }
+                }
+            }
+
+            save_chapters(episode);
+        }
+
+        // People's images are only loaded for the channels and episodes being shown.
+        let mut person_images = vec![];
+        for channel in [state.search_focus(), state.home_focus()].iter().flatten() {
+            if let Some(Ok(details)) = channel.details().as_deref() {
+                person_images.extend(details.persons().iter().filter_map(|p| p.image.clone()));
+            }
+        }
+        for episode in [state.search_episode_focus(), state.home_episode_focus()]
+            .iter()
+            .flatten()
+        {
+            if let Some(Ok(episode)) = episode.get().as_deref() {
+                person_images.extend(episode.persons().iter().filter_map(|p| p.image.clone()));
+            }
+        }
+        for image in person_images {
+            if state.image(&image).is_none() && requested_person_images.insert(image.clone()) {
+                loader.queue(LoaderQuery::Image {
+                    image: Arc::new(Image::new(&image)),
+                    associated_query: None,
+                });
+            }
+        }
+
+        if let Some(player_state) = state.player_state().as_ref() {
+            if let Some(episode_pk) = auto_played.check(player_state, state.played_threshold()) {
+                if !played.contains_key(&episode_pk) {
+                    set_played.push((episode_pk, true));
+                }
+            }
+
+            let changed = match &saved_progress {
+                Some((episode_pk, playback, time)) => {
+                    *episode_pk != player_state.episode_pk
+                        || *playback != player_state.playback
+                        || (*time as i64 - player_state.time as i64).abs() >= 5000
+                }
+                None => true,
+            };
+
+            // Time and duration are 0 while the stream is loading.
+            if changed && player_state.duration > 0 {
+                set_progress
+                    .bind(1, &player_state.episode_pk as &str)
+                    .unwrap();
+                set_progress
+                    .bind(2, &player_state.channel_pk as &str)
+                    .unwrap();
+                set_progress.bind(3, player_state.time as i64).unwrap();
+                set_progress.bind(4, player_state.duration as i64).unwrap();
+                set_progress.next().unwrap();
+                set_progress.reset().unwrap();
+
+                saved_progress = Some((
+                    player_state.episode_pk.clone(),
+                    player_state.playback,
+                    player_state.time,
+                ));
+            }
+        }
+
+        refresh.tick(&state, &loader, &subscriptions);
+        for channel_pk in &subscriptions {
+            let core = match state.channel_ref(channel_pk.to_owned()).core() {
+                Some(core) => core,
+                None => continue,
+            };
+            if matches!(saved_cores.get(channel_pk), Some(prev) if Arc::ptr_eq(prev, &core)) {
+                continue;
+            }
+            saved_cores.insert(channel_pk.to_owned(), Arc::clone(&core));
+
+            // Keep what we had if the channel failed to load, so it still works offline.
+            if let Ok(core) = core.as_ref() {
+                set_channel_core.bind(1, channel_pk as &str).unwrap();
+                set_channel_core.bind(2, core.title()).unwrap();
+                set_channel_core.bind(3, core.author()).unwrap();
+                set_channel_core
+                    .bind(4, core.image_200().unwrap_or_default())
+                    .unwrap();
+                set_channel_core
+                    .bind(5, core.image_600().unwrap_or_default())
+                    .unwrap();
+                set_channel_core
+                    .bind(6, core.image_rss().unwrap_or_default())
+                    .unwrap();
+                set_channel_core
+                    .bind(7, core.rss().unwrap_or_default())
+                    .unwrap();
+                set_channel_core.bind(8, core.rss_moved() as i64).unwrap();
+                set_channel_core.bind(9, core.ended() as i64).unwrap();
+                set_channel_core.next().unwrap();
+                set_channel_core.reset().unwrap();
+            }
+        }
+
+        // Downloads may have been waiting for an unmetered network, so apply the policies again.
+        if state.network_metered() != network_metered {
+            network_metered = state.network_metered();
+            refreshed_details.clear();
+        }
+
+        // Save, look for new episodes and apply download policies whenever a subscribed feed is
+        // refreshed.
+        for channel_pk in &subscriptions {
+            let details = match state.channel_ref(channel_pk.to_owned()).details() {
+                Some(details) => details,
+                None => continue,
+            };
+            if matches!(refreshed_details.get(channel_pk), Some(prev) if Arc::ptr_eq(prev, &details))
+            {
+                continue;
+            }
+            refreshed_details.insert(channel_pk.to_owned(), Arc::clone(&details));
+
+            let details = match details.as_ref() {
+                Ok(details) => details,
+                Err(_) => continue,
+            };
+
+            connection.execute("begin").unwrap();
+
+            set_channel_detail.bind(1, channel_pk as &str).unwrap();
+            set_channel_detail.bind(2, details.description()).unwrap();
+            set_channel_detail.bind(3, details.link()).unwrap();
+            set_channel_detail
+                .bind(4, details.show_type().as_str())
+                .unwrap();
+            set_channel_detail
+                .bind(5, details.guid().unwrap_or_default())
+                .unwrap();
+            set_channel_detail.bind(6, details.locked() as i64).unwrap();
+            let location = details.location();
+            set_channel_detail
+                .bind(
+                    7,
+                    location
+                        .map(|location| &location.name as &str)
+                        .unwrap_or_default(),
+                )
+                .unwrap();
+            set_channel_detail
+                .bind(
+                    8,
+                    location
+                        .and_then(|location| location.geo.as_deref())
+                        .unwrap_or_default(),
+                )
+                .unwrap();
+            set_channel_detail
+                .bind(
+                    9,
+                    location
+                        .and_then(|location| location.osm.as_deref())
+                        .unwrap_or_default(),
+                )
+                .unwrap();
+            set_channel_detail.next().unwrap();
+            set_channel_detail.reset().unwrap();
+
+            remove_funding.bind(1, channel_pk as &str).unwrap();
+            remove_funding.next().unwrap();
+            remove_funding.reset().unwrap();
+
+            for (position, funding) in details.funding().iter().enumerate() {
+                add_funding.bind(1, channel_pk as &str).unwrap();
+                add_funding.bind(2, position as i64).unwrap();
+                add_funding.bind(3, &funding.url as &str).unwrap();
+                add_funding.bind(4, &funding.title as &str).unwrap();
+                add_funding.next().unwrap();
+                add_funding.reset().unwrap();
+            }
+
+            remove_persons.bind(1, channel_pk as &str).unwrap();
+            remove_persons.next().unwrap();
+            remove_persons.reset().unwrap();
+
+            // A channel's own people are saved without an episode pk.
+            let mut save_persons = |episode_pk: &str, persons: &[Person]| {
+                for (position, person) in persons.iter().enumerate() {
+                    add_person.bind(1, channel_pk as &str).unwrap();
+                    add_person.bind(2, episode_pk).unwrap();
+                    add_person.bind(3, position as i64).unwrap();
+                    add_person.bind(4, &person.name as &str).unwrap();
+                    add_person.bind(5, &person.role as &str).unwrap();
+                    add_person.bind(6, &person.group as &str).unwrap();
+                    add_person
+                        .bind(7, person.image.as_deref().unwrap_or_default())
+                        .unwrap();
+                    add_person
+                        .bind(8, person.href.as_deref().unwrap_or_default())
+                        .unwrap();
+                    add_person.next().unwrap();
+                    add_person.reset().unwrap();
+                }
+            };
+            save_persons("", details.persons());
+
+            remove_episodes.bind(1, channel_pk as &str).unwrap();
+            remove_episodes.next().unwrap();
+            remove_episodes.reset().unwrap();
+
+            remove_enclosures.bind(1, channel_pk as &str).unwrap();
+            remove_enclosures.next().unwrap();
+            remove_enclosures.reset().unwrap();
+
+            for (position, episode) in details.episodes().iter().enumerate() {
+                if let Some(Ok(episode)) = episode.get().as_deref() {
+                    add_episode.bind(1, episode.pk()).unwrap();
+                    add_episode.bind(2, channel_pk as &str).unwrap();
+                    add_episode.bind(3, position as i64).unwrap();
+                    add_episode.bind(4, episode.title()).unwrap();
+                    add_episode.bind(5, episode.url()).unwrap();
+                    add_episode.bind(6, episode.raw_date()).unwrap();
+                    add_episode.bind(7, episode.description()).unwrap();
+                    add_episode.bind(8, episode.raw_duration()).unwrap();
+                    add_episode
+                        .bind(9, episode.image_url().unwrap_or_default())
+                        .unwrap();
+                    add_episode.bind(10, episode.audio()).unwrap();
+                    add_episode
+                        .bind(11, episode.chapters_url().unwrap_or_default())
+                        .unwrap();
+                    let transcript = episode.transcript_link();
+                    add_episode
+                        .bind(
+                            12,
+                            transcript.map(|link| &link.url as &str).unwrap_or_default(),
+                        )
+                        .unwrap();
+                    add_episode
+                        .bind(
+                            13,
+                            transcript
+                                .and_then(|link| link.mimetype.as_deref())
+                                .unwrap_or_default(),
+                        )
+                        .unwrap();
+                    add_episode.bind(14, episode.notes()).unwrap();
+                    add_episode
+                        .bind(15, episode.season().unwrap_or(0) as i64)
+                        .unwrap();
+                    add_episode
+                        .bind(16, episode.season_name().unwrap_or_default())
+                        .unwrap();
+                    add_episode
+                        .bind(17, episode.number().unwrap_or(0) as i64)
+                        .unwrap();
+                    add_episode
+                        .bind(18, episode.episode_type().as_str())
+                        .unwrap();
+                    let location = episode.location();
+                    add_episode
+                        .bind(
+                            19,
+                            location
+                                .map(|location| &location.name as &str)
+                                .unwrap_or_default(),
+                        )
+                        .unwrap();
+                    add_episode
+                        .bind(
+                            20,
+                            location
+                                .and_then(|location| location.geo.as_deref())
+                                .unwrap_or_default(),
+                        )
+                        .unwrap();
+                    add_episode
+                        .bind(
+                            21,
+                            location
+                                .and_then(|location| location.osm.as_deref())
+                                .unwrap_or_default(),
+                        )
+                        .unwrap();
+                    add_episode.next().unwrap();
+                    add_episode.reset().unwrap();
+
+                    for (position, enclosure) in episode.enclosures().iter().enumerate() {
+                        add_enclosure.bind(1, channel_pk as &str).unwrap();
+                        add_enclosure.bind(2, episode.pk()).unwrap();
+                        add_enclosure.bind(3, position as i64).unwrap();
+                        add_enclosure.bind(4, &enclosure.url as &str).unwrap();
+                        add_enclosure
+                            .bind(5, enclosure.mimetype.as_deref().unwrap_or_default())
+                            .unwrap();
+                        add_enclosure
+                            .bind(6, enclosure.length.unwrap_or(0) as i64)
+                            .unwrap();
+                        add_enclosure
+                            .bind(7, enclosure.bitrate.unwrap_or(0) as i64)
+                            .unwrap();
+                        add_enclosure
+                            .bind(8, enclosure.title.as_deref().unwrap_or_default())
+                            .unwrap();
+                        add_enclosure.next().unwrap();
+                        add_enclosure.reset().unwrap();
+                    }
+
+                    save_chapters(episode);
+                    save_persons(episode.pk(), episode.persons());
+                }
+            }
+
+            connection.execute("commit").unwrap();
+
+            new_episodes.see(&current, channel_pk, details, &played);
+            let plan = download_policy::apply(&state, channel_pk, details, &played, now());
+            start_downloads.extend(
+                plan.download
+                    .into_iter()
+                    .map(|episode_pk| state.episode_ref(episode_pk)),
+            );
+            delete_downloads.extend(plan.delete);
+        }
+
         match ev {
-            DatabaseAction::Subscribe(channel) => {
-                let channel_pk = channel.pk().to_owned();
-                if !subscriptions.contains(&channel_pk) {
-                    add_subscription.bind(1, &channel_pk as &str).unwrap();
-                    add_subscription.next().unwrap();
-                    add_subscription.reset().unwrap();
-
-                    subscriptions.push(channel_pk.clone());
-                    send_subscriptions(&subscriptions);
-                    loader.queue(LoaderQuery::ItunesLookup { pk: channel_pk });
+            None => {}
+            Some(DatabaseAction::MarkPlayed(episode)) => {
+                set_played.push((episode.pk().to_owned(), true));
+            }
+            Some(DatabaseAction::MarkUnplayed(episode)) => {
+                set_played.push((episode.pk().to_owned(), false));
+            }
+            Some(DatabaseAction::Download(episode)) => {
+                start_downloads.push(episode);
+            }
+            Some(DatabaseAction::CancelDownload(episode)) => {
+                if matches!(state.download(episode.pk()), Some(download) if download.active()) {
+                    current.update(vec![StateAction::SetDownload(
+                        episode.pk().to_owned(),
+                        Some(Download::Cancelled),
+                    )]);
+                }
+            }
+            Some(DatabaseAction::DeleteDownload(episode)) => {
+                delete_downloads.push(episode.pk().to_owned());
+            }
+            Some(DatabaseAction::SetDownloadPolicy(channel, policy)) => {
+                set_download_policy.bind(1, channel.pk()).unwrap();
+                set_download_policy
+                    .bind(2, policy.keep_latest as i64)
+                    .unwrap();
+                set_download_policy
+                    .bind(3, policy.unmetered_only as i64)
+                    .unwrap();
+                set_download_policy
+                    .bind(4, policy.delete_played_after.unwrap_or(0) as i64)
+                    .unwrap();
+                set_download_policy
+                    .bind(5, policy.max_bytes.unwrap_or(0) as i64)
+                    .unwrap();
+                set_download_policy.next().unwrap();
+                set_download_policy.reset().unwrap();
+
+                current.update(vec![StateAction::SetDownloadPolicy(
+                    channel.pk().to_owned(),
+                    policy,
+                )]);
+
+                // Apply it on the next tick, once the state has the new policy.
+                refreshed_details.remove(channel.pk());
+            }
+            Some(DatabaseAction::SetEnclosurePreference(preference)) => {
+                set_setting.bind(1, "enclosure_preference").unwrap();
+                set_setting.bind(2, preference.as_str()).unwrap();
+                set_setting.next().unwrap();
+                set_setting.reset().unwrap();
+
+                current.update(vec![StateAction::SetEnclosurePreference(preference)]);
+            }
+            Some(DatabaseAction::SetVideoAsAudio(video_as_audio)) => {
+                set_setting.bind(1, "video_as_audio").unwrap();
+                set_setting
+                    .bind(2, if video_as_audio { "1" } else { "0" })
+                    .unwrap();
+                set_setting.next().unwrap();
+                set_setting.reset().unwrap();
+
+                current.update(vec![StateAction::SetVideoAsAudio(video_as_audio)]);
+            }
+            Some(DatabaseAction::SetPlayedThreshold(played_threshold)) => {
+                set_setting.bind(1, "played_threshold").unwrap();
+                set_setting
+                    .bind(2, played_threshold.to_string().as_str())
+                    .unwrap();
+                set_setting.next().unwrap();
+                set_setting.reset().unwrap();
+
+                current.update(vec![StateAction::SetPlayedThreshold(played_threshold)]);
+            }
+            Some(DatabaseAction::Subscribe(channel)) => {
+                if !subscription::is_duplicate(&mut find_channel_guid, &channel) {
+                    subscribe.push(channel.pk().to_owned());
                 }
             }
-            DatabaseAction::Unsubscribe(channel) => {
-                if let Some(pos) = subscriptions.iter().position(|x| x == channel.pk()) {
-                    let channel_pk = channel.pk();
+            Some(DatabaseAction::ImportOpml(path, done)) => {
+                subscription::import_opml(&state, &subscriptions, &path, done, &send_imported);
+            }
+            Some(DatabaseAction::ClearNewEpisodes(channel)) => {
+                clear_new.push(channel.pk().to_owned());
+            }
+            Some(DatabaseAction::ExportOpml(path, done)) => {
+                subscription::export_opml(&state, &subscriptions, &path, done);
+            }
+            Some(DatabaseAction::Unsubscribe(channel)) => {
+                // The channel may be subscribed to under another pk with the same podcast:guid.
+                let pos = subscriptions
+                    .iter()
+                    .position(|x| state.channel_ref(x.to_owned()).same_channel(&channel));
+                if let Some(pos) = pos {
+                    let channel_pk = subscriptions[pos].clone();
+                    let channel_pk = channel_pk.as_str();
                     remove_subscription.bind(1, channel_pk).unwrap();
                     remove_subscription.next().unwrap();
                     remove_subscription.reset().unwrap();
 
+                    remove_download_policy.bind(1, channel_pk).unwrap();
+                    remove_download_policy.next().unwrap();
+                    remove_download_policy.reset().unwrap();
+                    current.update(vec![StateAction::SetDownloadPolicy(
+                        channel_pk.to_owned(),
+                        DownloadPolicy::default(),
+                    )]);
+                    refreshed_details.remove(channel_pk);
+                    clear_new.push(channel_pk.to_owned());
+
+                    remove_channel_core.bind(1, channel_pk).unwrap();
+                    remove_channel_core.next().unwrap();
+                    remove_channel_core.reset().unwrap();
+                    remove_channel_detail.bind(1, channel_pk).unwrap();
+                    remove_channel_detail.next().unwrap();
+                    remove_channel_detail.reset().unwrap();
+                    remove_episodes.bind(1, channel_pk).unwrap();
+                    remove_episodes.next().unwrap();
+                    remove_episodes.reset().unwrap();
+                    remove_persons.bind(1, channel_pk).unwrap();
+                    remove_persons.next().unwrap();
+                    remove_persons.reset().unwrap();
+                    remove_funding.bind(1, channel_pk).unwrap();
+                    remove_funding.next().unwrap();
+                    remove_funding.reset().unwrap();
+                    remove_enclosures.bind(1, channel_pk).unwrap();
+                    remove_enclosures.next().unwrap();
+                    remove_enclosures.reset().unwrap();
+                    saved_cores.remove(channel_pk);
+
                     subscriptions.remove(pos);
                     send_subscriptions(&subscriptions);
                 }
             }
         }
+
+        let subscriptions_before = subscriptions.len();
+        for channel_pk in subscribe {
+            if !subscriptions.contains(&channel_pk) {
+                add_subscription.bind(1, &channel_pk as &str).unwrap();
+                add_subscription.next().unwrap();
+                add_subscription.reset().unwrap();
+
+                subscriptions.push(channel_pk.clone());
+                loader.queue(LoaderQuery::channel(channel_pk));
+            }
+        }
+        if subscriptions.len() != subscriptions_before {
+            send_subscriptions(&subscriptions);
+        }
+        if let Some(done) = imported {
+            let _ = done.send(Ok(subscriptions.len() - subscriptions_before));
+        }
+
+        for (episode_pk, is_played) in set_played {
+            if is_played {
+                let played_at = now();
+                add_played.bind(1, &episode_pk as &str).unwrap();
+                add_played.bind(2, played_at).unwrap();
+                add_played.next().unwrap();
+                add_played.reset().unwrap();
+                played.insert(episode_pk.clone(), played_at);
+
+                new_episodes.played(&current, &episode_pk);
+            } else {
+                remove_played.bind(1, &episode_pk as &str).unwrap();
+                remove_played.next().unwrap();
+                remove_played.reset().unwrap();
+                played.remove(&episode_pk);
+            }
+
+            current.update(vec![StateAction::SetPlayed(episode_pk, is_played)]);
+        }
+
+        for channel_pk in clear_new {
+            new_episodes.clear(&current, channel_pk);
+        }
+
+        downloads.start(&current, &loader, &state, start_downloads);
+        downloads.delete(&current, delete_downloads);
     }
 }
 
-pub fn new_database(current: Arc<CurrentState>, loader: Loader) -> Sender<DatabaseAction> {
+pub fn new_database(
+    current: Arc<CurrentState>,
+    loader: Loader,
+    player: Sender<PlayerAction>,
+) -> Sender<DatabaseAction> {
     let (send_cmd, recv_cmd) = channel();
     std::thread::spawn(move || {
-        database_thread(recv_cmd, current, loader);
+        database_thread(recv_cmd, current, loader, player);
     });
 
     send_cmd
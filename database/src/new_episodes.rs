@@ -0,0 +1,134 @@
+use crate::now;
+use sqlite::{Connection, Statement};
+use state::{ChannelDetail, CurrentState, StateAction};
+use std::collections::{HashMap, HashSet};
+
+/// Keeps track of which episodes of each subscription are new since the user last looked.
+pub(crate) struct NewEpisodes<'l> {
+    /// Every episode we've seen in each subscription.
+    seen: HashMap<String, HashSet<String>>,
+    /// The episodes in each subscription that are new.
+    new: HashMap<String, HashSet<String>>,
+    add_seen_episode: Statement<'l>,
+    clear_new_episode: Statement<'l>,
+    clear_new_episodes: Statement<'l>,
+}
+
+impl<'l> NewEpisodes<'l> {
+    /// Loads the episodes we've seen, and shows the ones that are new.
+    pub fn open(connection: &'l Connection, current: &CurrentState) -> NewEpisodes<'l> {
+        let mut get_seen_episodes = connection
+            .prepare(r#"select episode_pk, channel_pk, new from seen_episode"#)
+            .unwrap();
+
+        let mut seen: HashMap<String, HashSet<String>> = HashMap::new();
+        let mut new: HashMap<String, HashSet<String>> = HashMap::new();
+        while get_seen_episodes.next().unwrap() == sqlite::State::Row {
+            let episode_pk = get_seen_episodes.read::<String>(0).unwrap();
+            let channel_pk = get_seen_episodes.read::<String>(1).unwrap();
+            if get_seen_episodes.read::<i64>(2).unwrap() != 0 {
+                new.entry(channel_pk.clone())
+                    .or_default()
+                    .insert(episode_pk.clone());
+            }
+            seen.entry(channel_pk).or_default().insert(episode_pk);
+        }
+        current.update(
+            new.iter()
+                .map(|(channel_pk, episodes)| {
+                    StateAction::SetNewEpisodes(channel_pk.to_owned(), episodes.clone())
+                })
+                .collect(),
+        );
+
+        NewEpisodes {
+            seen,
+            new,
+            add_seen_episode: connection
+                .prepare(r#"insert or replace into seen_episode values(?, ?, ?, ?)"#)
+                .unwrap(),
+            clear_new_episode: connection
+                .prepare(r#"update seen_episode set new = 0 where episode_pk = ?"#)
+                .unwrap(),
+            clear_new_episodes: connection
+                .prepare(r#"update seen_episode set new = 0 where channel_pk = ?"#)
+                .unwrap(),
+        }
+    }
+
+    /// Looks for episodes we haven't seen in a refreshed subscription, given when each played
+    /// episode was marked as played.
+    pub fn see(
+        &mut self,
+        current: &CurrentState,
+        channel_pk: &str,
+        details: &ChannelDetail,
+        played: &HashMap<String, i64>,
+    ) {
+        let seen = self.seen.entry(channel_pk.to_owned()).or_default();
+
+        // Everything is new the first time we load a channel, which isn't useful.
+        let first_load = seen.is_empty();
+
+        let mut found_new = false;
+        for episode in details.episodes() {
+            if seen.insert(episode.pk().to_owned()) {
+                let is_new = !first_load && !played.contains_key(episode.pk());
+
+                self.add_seen_episode.bind(1, episode.pk()).unwrap();
+                self.add_seen_episode.bind(2, channel_pk).unwrap();
+                self.add_seen_episode.bind(3, now()).unwrap();
+                self.add_seen_episode.bind(4, is_new as i64).unwrap();
+                self.add_seen_episode.next().unwrap();
+                self.add_seen_episode.reset().unwrap();
+
+                if is_new {
+                    self.new
+                        .entry(channel_pk.to_owned())
+                        .or_default()
+                        .insert(episode.pk().to_owned());
+                    found_new = true;
+                }
+            }
+        }
+
+        if found_new {
+            current.update(vec![StateAction::SetNewEpisodes(
+                channel_pk.to_owned(),
+                self.new[channel_pk].clone(),
+            )]);
+        }
+    }
+
+    /// Played episodes aren't new anymore.
+    pub fn played(&mut self, current: &CurrentState, episode_pk: &str) {
+        for (channel_pk, episodes) in self.new.iter_mut() {
+            if episodes.remove(episode_pk) {
+                self.clear_new_episode.bind(1, episode_pk).unwrap();
+                self.clear_new_episode.next().unwrap();
+                self.clear_new_episode.reset().unwrap();
+
+                current.update(vec![StateAction::SetNewEpisodes(
+                    channel_pk.to_owned(),
+                    episodes.clone(),
+                )]);
+            }
+        }
+    }
+
+    /// Forgets which of a channel's episodes are new, once the user has seen them.
+    pub fn clear(&mut self, current: &CurrentState, channel_pk: String) {
+        if self.new.remove(&channel_pk).is_some() {
+            self.clear_new_episodes
+                .bind(1, &channel_pk as &str)
+                .unwrap();
+            self.clear_new_episodes.next().unwrap();
+            self.clear_new_episodes.reset().unwrap();
+
+            current.update(vec![StateAction::SetNewEpisodes(
+                channel_pk,
+                HashSet::new(),
+            )]);
+        }
+    }
+}
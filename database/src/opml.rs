@@ -0,0 +1,166 @@
+use state::StateError;
+use std::io::{Error as IoError, ErrorKind};
+use xml::escape::escape_str_attribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// A subscription, as listed in an OPML file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feed {
+    pub title: String,
+    pub url: String,
+}
+
+fn invalid_data(message: String) -> StateError {
+    IoError::new(ErrorKind::InvalidData, message).into()
+}
+
+/// Reads the feeds from an OPML file.
+///
+/// Outlines without an `xmlUrl` (like folders, or episodes in Overcast exports) are skipped, but
+/// their children are not.
+pub fn parse(source: &[u8]) -> Result<Vec<Feed>, StateError> {
+    let mut feeds: Vec<Feed> = vec![];
+    let mut saw_opml = false;
+
+    for event in EventReader::new(source) {
+        let event = event.map_err(|err| invalid_data(format!("Invalid OPML: {}", err)))?;
+
+        if let XmlEvent::StartElement {
+            name, attributes, ..
+        } = event
+        {
+            if name.local_name.eq_ignore_ascii_case("opml") {
+                saw_opml = true;
+            }
+
+            if !name.local_name.eq_ignore_ascii_case("outline") {
+                continue;
+            }
+
+            let attribute = |key: &str| {
+                attributes
+                    .iter()
+                    .find(|attribute| attribute.name.local_name.eq_ignore_ascii_case(key))
+                    .map(|attribute| attribute.value.trim().to_owned())
+                    .filter(|value| !value.is_empty())
+            };
+
+            if let Some(url) = attribute("xmlUrl") {
+                if feeds.iter().any(|feed| feed.url == url) {
+                    continue;
+                }
+
+                feeds.push(Feed {
+                    title: attribute("title")
+                        .or_else(|| attribute("text"))
+                        .unwrap_or_default(),
+                    url,
+                });
+            }
+        }
+    }
+
+    if !saw_opml {
+        return Err(invalid_data("Not an OPML file".to_owned()));
+    }
+
+    Ok(feeds)
+}
+
+/// Writes feeds as an OPML 2.0 document.
+pub fn write(feeds: &[Feed]) -> String {
+    let mut opml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="2.0">
+  <head>
+    <title>Pyrocast Subscriptions</title>
+  </head>
+  <body>
+"#,
+    );
+
+    for feed in feeds {
+        let title = escape_str_attribute(&feed.title);
+        opml.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\" />\n",
+            title,
+            title,
+            escape_str_attribute(&feed.url),
+        ));
+    }
+
+    opml.push_str("  </body>\n</opml>\n");
+
+    opml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(title: &str, url: &str) -> Feed {
+        Feed {
+            title: title.to_owned(),
+            url: url.to_owned(),
+        }
+    }
+
+    fn round_trip(feeds: &[Feed]) {
+        assert_eq!(&parse(write(feeds).as_bytes()).unwrap()[..], feeds);
+    }
+
+    #[test]
+    fn overcast() {
+        let feeds = parse(include_bytes!("../fixtures/overcast.opml")).unwrap();
+        assert_eq!(
+            feeds,
+            vec![
+                feed(
+                    "This American Life",
+                    "https://www.thisamericanlife.org/podcast/rss.xml"
+                ),
+                feed(
+                    "Hardcore History",
+                    "https://feeds.feedburner.com/dancarlin/history?format=xml"
+                ),
+            ]
+        );
+        round_trip(&feeds);
+    }
+
+    #[test]
+    fn antennapod() {
+        let feeds = parse(include_bytes!("../fixtures/antennapod.opml")).unwrap();
+        assert_eq!(
+            feeds,
+            vec![
+                feed("Planet Money", "https://feeds.npr.org/510289/podcast.xml"),
+                feed("Late Night Linux", "https://latenightlinux.com/feed/mp3"),
+                feed("Reply All", "https://feeds.megaphone.fm/replyall"),
+            ]
+        );
+        round_trip(&feeds);
+    }
+
+    #[test]
+    fn gpodder() {
+        let feeds = parse(include_bytes!("../fixtures/gpodder.opml")).unwrap();
+        assert_eq!(
+            feeds,
+            vec![
+                feed(
+                    "Radiolab & More <Extras>",
+                    "http://feeds.wnyc.org/radiolab?a=1&b=2"
+                ),
+                feed("Untitled", "https://example.com/untitled.xml"),
+            ]
+        );
+        round_trip(&feeds);
+    }
+
+    #[test]
+    fn not_opml() {
+        assert!(parse(b"<rss><channel /></rss>").is_err());
+        assert!(parse(b"<opml><body><outline").is_err());
+    }
+}
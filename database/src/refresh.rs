@@ -0,0 +1,49 @@
+use loader::{Loader, Query as LoaderQuery};
+use state::State;
+use std::time::{Duration, Instant};
+
+/// How often to check subscribed feeds for new episodes.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Refreshes subscriptions every `REFRESH_INTERVAL`.
+pub(crate) struct Refresh {
+    /// When we last queued a refresh of every subscription.
+    last_refresh: Instant,
+}
+
+impl Refresh {
+    /// Subscriptions are loaded when the database opens, so the first refresh is an interval away.
+    pub fn new() -> Refresh {
+        Refresh {
+            last_refresh: Instant::now(),
+        }
+    }
+
+    /// Asks the loader for every subscription that hasn't ended, if it's time.
+    pub fn tick(&mut self, state: &State, loader: &Loader, subscriptions: &[String]) {
+        if self.last_refresh.elapsed() < REFRESH_INTERVAL {
+            return;
+        }
+        self.last_refresh = Instant::now();
+
+        for channel_pk in subscriptions {
+            let core = state.channel_ref(channel_pk.to_owned()).core();
+            let core = core.as_deref().and_then(|core| core.as_ref().ok());
+            if core.map_or(false, |core| core.ended()) {
+                continue;
+            }
+            let rss = core
+                .and_then(|core| core.rss())
+                .filter(|rss| !rss.is_empty());
+
+            // We only need to look the channel up again if it didn't load last time.
+            loader.queue(match rss {
+                Some(rss) => LoaderQuery::Rss {
+                    pk: channel_pk.to_owned(),
+                    url: rss.to_owned(),
+                },
+                None => LoaderQuery::channel(channel_pk.to_owned()),
+            });
+        }
+    }
+}
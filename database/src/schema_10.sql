@@ -0,0 +1,15 @@
+alter table episode add column chapters_url text not null default "";
+
+create table chapter(
+  episode_pk text not null,
+  position integer not null,
+  start integer not null,
+  title text not null,
+  image text not null,
+  url text not null,
+  primary key (episode_pk, position));
+
+-- Parse every feed again, even if it has not changed, to fill in the chapters.
+delete from feed_cache;
+
+insert or replace into meta values("version", 10);
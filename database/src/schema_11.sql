@@ -0,0 +1,7 @@
+alter table episode add column transcript_url text not null default "";
+alter table episode add column transcript_type text not null default "";
+
+-- Parse every feed again, even if it has not changed, to fill in the transcript links.
+delete from feed_cache;
+
+insert or replace into meta values("version", 11);
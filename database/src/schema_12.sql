@@ -0,0 +1,6 @@
+alter table episode add column notes text not null default "";
+
+-- Parse every feed again, even if it has not changed, to fill in the show notes.
+delete from feed_cache;
+
+insert or replace into meta values("version", 12);
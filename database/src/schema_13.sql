@@ -0,0 +1,11 @@
+alter table episode add column season integer not null default 0;
+alter table episode add column season_name text not null default "";
+alter table episode add column number integer not null default 0;
+alter table episode add column episode_type text not null default "";
+
+alter table channel_detail add column show_type text not null default "";
+
+-- Parse every feed again, even if it has not changed, to fill in the seasons and episode types.
+delete from feed_cache;
+
+insert or replace into meta values("version", 13);
@@ -0,0 +1,34 @@
+alter table channel_detail add column guid text not null default "";
+alter table channel_detail add column locked integer not null default 0;
+alter table channel_detail add column location text not null default "";
+alter table channel_detail add column location_geo text not null default "";
+alter table channel_detail add column location_osm text not null default "";
+
+create index channel_detail_guid on channel_detail(guid);
+
+alter table episode add column location text not null default "";
+alter table episode add column location_geo text not null default "";
+alter table episode add column location_osm text not null default "";
+
+create table person(
+  channel_pk text not null,
+  episode_pk text not null,
+  position integer not null,
+  name text not null,
+  role text not null,
+  person_group text not null,
+  image text not null,
+  href text not null,
+  primary key (channel_pk, episode_pk, position));
+
+create table funding(
+  channel_pk text not null,
+  position integer not null,
+  url text not null,
+  title text not null,
+  primary key (channel_pk, position));
+
+-- Parse every feed again, even if it has not changed, to fill in the people, funding and locations.
+delete from feed_cache;
+
+insert or replace into meta values("version", 14);
@@ -0,0 +1,4 @@
+alter table channel_core add column rss_moved integer not null default 0;
+alter table channel_core add column ended integer not null default 0;
+
+insert or replace into meta values("version", 15);
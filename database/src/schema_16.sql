@@ -0,0 +1,21 @@
+create table enclosure(
+  channel_pk text not null,
+  episode_pk text not null,
+  position integer not null,
+  url text not null,
+  mimetype text not null,
+  length integer not null,
+  bitrate integer not null,
+  title text not null,
+  primary key (episode_pk, position));
+
+create index enclosure_channel on enclosure(channel_pk);
+
+create table setting(
+  id text primary key not null,
+  value text not null);
+
+-- Parse every feed again, even if it has not changed, to fill in the enclosures.
+delete from feed_cache;
+
+insert or replace into meta values("version", 16);
@@ -0,0 +1,5 @@
+-- Downloads are saved when they start, with the enclosure they started with.
+alter table download add column url text not null default '';
+alter table download add column done integer not null default 1;
+
+insert or replace into meta values("version", 17);
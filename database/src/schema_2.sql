@@ -0,0 +1,7 @@
+create table episode_progress(
+  episode_pk text primary key not null,
+  channel_pk text not null,
+  time integer not null,
+  duration integer not null);
+
+insert or replace into meta values("version", 2);
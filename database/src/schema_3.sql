@@ -0,0 +1,4 @@
+create table played_episode(
+  episode_pk text primary key not null);
+
+insert or replace into meta values("version", 3);
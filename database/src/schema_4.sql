@@ -0,0 +1,7 @@
+create table queue(
+  position integer primary key not null,
+  episode_pk text not null,
+  channel_pk text not null,
+  uri text not null);
+
+insert or replace into meta values("version", 4);
@@ -0,0 +1,5 @@
+create table download(
+  episode_pk text primary key not null,
+  path text not null);
+
+insert or replace into meta values("version", 5);
@@ -0,0 +1,11 @@
+create table download_policy(
+  channel_pk text primary key not null,
+  keep_latest integer not null,
+  unmetered_only integer not null,
+  delete_played_after integer not null,
+  max_bytes integer not null);
+
+alter table played_episode add column played_at integer not null default 0;
+update played_episode set played_at = strftime('%s', 'now');
+
+insert or replace into meta values("version", 6);
@@ -0,0 +1,7 @@
+create table seen_episode(
+  episode_pk text primary key not null,
+  channel_pk text not null,
+  first_seen integer not null,
+  new integer not null);
+
+insert or replace into meta values("version", 7);
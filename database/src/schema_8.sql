@@ -0,0 +1,7 @@
+create table feed_cache(
+  url text primary key not null,
+  etag text not null,
+  last_modified text not null,
+  checked integer not null);
+
+insert or replace into meta values("version", 8);
@@ -0,0 +1,29 @@
+create table channel_core(
+  pk text primary key not null,
+  title text not null,
+  author text not null,
+  image_200 text not null,
+  image_600 text not null,
+  image_rss text not null,
+  rss text not null);
+
+create table channel_detail(
+  pk text primary key not null,
+  description text not null,
+  link text not null);
+
+create table episode(
+  pk text primary key not null,
+  channel_pk text not null,
+  position integer not null,
+  title text not null,
+  url text not null,
+  date text not null,
+  description text not null,
+  duration text not null,
+  image text not null,
+  audio text not null);
+
+create index episode_channel on episode(channel_pk, position);
+
+insert or replace into meta values("version", 9);
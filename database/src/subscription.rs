@@ -0,0 +1,109 @@
+use crate::opml;
+use crate::OpmlReply;
+use async_std::task;
+use sqlite::Statement;
+use state::{ChannelRef, State, StateError};
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+/// Whether the same feed is already subscribed to under another pk, found by its podcast:guid.
+///
+/// Directories key the same feed differently. `find_channel_guid` looks up a channel detail by
+/// guid, excluding the given pk.
+pub(crate) fn is_duplicate(find_channel_guid: &mut Statement, channel: &ChannelRef) -> bool {
+    channel.guid().map_or(false, |guid| {
+        find_channel_guid.bind(1, &guid as &str).unwrap();
+        find_channel_guid.bind(2, channel.pk()).unwrap();
+        let found = find_channel_guid.next().unwrap() == sqlite::State::Row;
+        find_channel_guid.reset().unwrap();
+        found
+    })
+}
+
+/// Finds the iTunes id of each feed, falling back to keying the channel by its RSS URL.
+fn resolve_feeds(country: &str, feeds: Vec<opml::Feed>) -> Vec<String> {
+    feeds
+        .into_iter()
+        .map(|feed| {
+            task::block_on(loader::find_itunes_id(country, &feed.title, &feed.url))
+                .unwrap_or(feed.url)
+        })
+        .collect()
+}
+
+/// Reads the feeds in an OPML file that aren't subscribed to yet, and sends a channel pk for each
+/// to `imported` once they are found.
+pub(crate) fn import_opml(
+    state: &State,
+    subscriptions: &[String],
+    path: &Path,
+    done: OpmlReply,
+    imported: &Sender<(Vec<String>, OpmlReply)>,
+) {
+    let feeds = std::fs::read(path)
+        .map_err(StateError::from)
+        .and_then(|source| opml::parse(&source));
+
+    match feeds {
+        Ok(feeds) => {
+            let subscribed_urls: Vec<_> = subscriptions
+                .iter()
+                .filter_map(|channel_pk| {
+                    let core = state.channel_ref(channel_pk.to_owned()).core()?;
+                    let rss = core.as_ref().as_ref().ok()?.rss()?;
+                    Some(rss.to_owned())
+                })
+                .collect();
+            let feeds: Vec<_> = feeds
+                .into_iter()
+                .filter(|feed| {
+                    !subscriptions.contains(&feed.url) && !subscribed_urls.contains(&feed.url)
+                })
+                .collect();
+
+            // Looking up feeds is slow, so don't block the database while we do.
+            let country = state.country().to_owned();
+            let imported = imported.clone();
+            std::thread::spawn(move || {
+                let channel_pks = resolve_feeds(&country, feeds);
+                let _ = imported.send((channel_pks, done));
+            });
+        }
+        Err(err) => {
+            let _ = done.send(Err(err));
+        }
+    }
+}
+
+/// Writes every subscription that has loaded to an OPML file, and replies with how many there
+/// were.
+pub(crate) fn export_opml(state: &State, subscriptions: &[String], path: &Path, done: OpmlReply) {
+    let feeds: Vec<_> = subscriptions
+        .iter()
+        .filter_map(|channel_pk| {
+            let core = state.channel_ref(channel_pk.to_owned()).core();
+            let core = core.as_deref().and_then(|core| core.as_ref().ok());
+            let url = core
+                .and_then(|core| core.rss())
+                .filter(|rss| !rss.is_empty())
+                .map(|rss| rss.to_owned())
+                // Channels without an iTunes id are keyed by their RSS URL.
+                .or_else(|| Some(channel_pk.to_owned()).filter(|pk| loader::is_feed_url(pk)))?;
+
+            Some(opml::Feed {
+                title: core.map(|core| core.title().to_owned()).unwrap_or_default(),
+                url,
+            })
+        })
+        .collect();
+
+    let skipped = subscriptions.len() - feeds.len();
+    if skipped > 0 {
+        eprintln!("Not exporting {} channels that have not loaded", skipped);
+    }
+
+    let result = std::fs::write(path, opml::write(&feeds))
+        .map(|_| feeds.len())
+        .map_err(StateError::from);
+    let _ = done.send(result);
+}
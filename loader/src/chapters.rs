@@ -0,0 +1,115 @@
+use crate::element::{Element, Ns};
+use crate::http;
+use serde::Deserialize;
+use state::{Chapter, StateError};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonChapter {
+    /// Seconds from the start of the episode.
+    start_time: f64,
+    #[serde(default)]
+    title: String,
+    img: Option<String>,
+    url: Option<String>,
+    /// Chapters that aren't in the table of contents only change the artwork.
+    toc: Option<bool>,
+}
+
+/// A podcast:chapters JSON file.
+#[derive(Debug, Deserialize)]
+struct JsonChapters {
+    #[serde(default)]
+    chapters: Vec<JsonChapter>,
+}
+
+fn parse_json(body: &[u8]) -> Result<Vec<Chapter>, StateError> {
+    let chapters: JsonChapters = serde_json::from_slice(body).map_err(std::io::Error::from)?;
+
+    Ok(chapters
+        .chapters
+        .into_iter()
+        .filter(|chapter| chapter.toc != Some(false) && chapter.start_time >= 0.0)
+        .map(|chapter| Chapter {
+            start: (chapter.start_time * 1000.0) as u64,
+            title: chapter.title.trim().to_owned(),
+            image: chapter.img.filter(|img| !img.is_empty()),
+            url: chapter.url.filter(|url| !url.is_empty()),
+        })
+        .collect())
+}
+
+/// Downloads a podcast:chapters JSON file.
+pub async fn fetch(url: &str) -> Result<Vec<Chapter>, StateError> {
+    let mut res = http::get_success(url).await?;
+    parse_json(&res.body_bytes().await?)
+}
+
+/// Parses a normal play time, like "01:02:03.500", "02:03" or "3.5", into milliseconds.
+pub(crate) fn parse_npt(npt: &str) -> Option<u64> {
+    let mut seconds = 0.0;
+    for part in npt.trim().split(':') {
+        let part: f64 = part.parse().ok()?;
+        if part < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + part;
+    }
+
+    Some((seconds * 1000.0) as u64)
+}
+
+/// Reads Podlove Simple Chapters from an item.
+pub fn parse_podlove(item: &Element) -> Vec<Chapter> {
+    item.children(Ns::Podlove, "chapters")
+        .flat_map(|chapters| chapters.children(Ns::Podlove, "chapter"))
+        .filter_map(|chapter| {
+            Some(Chapter {
+                start: parse_npt(&chapter.attr("start")?)?,
+                title: chapter.attr("title").unwrap_or_default(),
+                image: chapter.attr("image"),
+                url: chapter.attr("href"),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_npt() {
+        assert_eq!(parse_npt("01:02:03.500"), Some(3_723_500));
+        assert_eq!(parse_npt("02:03"), Some(123_000));
+        assert_eq!(parse_npt("3.5"), Some(3_500));
+        assert_eq!(parse_npt("soon"), None);
+    }
+
+    #[test]
+    fn parses_json() {
+        let chapters = parse_json(
+            br#"{
+                "version": "1.2.0",
+                "chapters": [
+                    {"startTime": 0, "title": "Intro"},
+                    {"startTime": 30.5, "title": "Sponsor", "toc": false},
+                    {"startTime": 62, "title": "Interview", "img": "https://example.com/a.jpg",
+                     "url": "https://example.com"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "Intro");
+        assert_eq!(chapters[1].start, 62_000);
+        assert_eq!(
+            chapters[1].image.as_deref(),
+            Some("https://example.com/a.jpg")
+        );
+        assert_eq!(chapters[1].url.as_deref(), Some("https://example.com"));
+
+        assert!(parse_json(b"not json").is_err());
+    }
+}
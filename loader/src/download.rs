@@ -0,0 +1,182 @@
+use crate::http::{self, Redirected};
+use async_std::fs::{self, OpenOptions};
+use async_std::io::prelude::*;
+use state::{CurrentState, Download, StateAction, StateError};
+use std::io;
+use std::path::{Path, PathBuf};
+use surf::http_types::StatusCode;
+
+/// How often to report progress, in bytes.
+const REPORT_INTERVAL: u64 = 512 * 1024;
+
+/// Whether the download has not been cancelled or deleted.
+pub fn is_active(current: &CurrentState, episode_pk: &str) -> bool {
+    matches!(current.get().download(episode_pk), Some(download) if download.active())
+}
+
+/// Where a download is saved until it is complete.
+pub fn partial_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".part")
+}
+
+/// Where the ETag or Last-Modified date of a partial download is saved, so that we only resume
+/// it if the file hasn't changed.
+pub fn validator_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".part.validator")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Downloads `url` to `path`, resuming a partial download if there is one.
+///
+/// Progress is reported with `StateAction::SetDownload`. The download stops early if it is
+/// cancelled, leaving the partial file so that it can be resumed later, or if it is deleted.
+pub async fn fetch(
+    current: &CurrentState,
+    episode_pk: &str,
+    url: &str,
+    path: &Path,
+) -> Result<(), StateError> {
+    let partial = partial_path(path);
+    let validator_path = validator_path(path);
+
+    // Without a validator, we can't tell if the file changed since, so start over.
+    let validator = fs::read_to_string(&validator_path).await.ok();
+    let offset = match validator {
+        Some(_) => fs::metadata(&partial)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0),
+        None => 0,
+    };
+
+    let mut headers = vec![];
+    if let (true, Some(validator)) = (offset > 0, validator) {
+        headers.push(("range", format!("bytes={}-", offset)));
+        headers.push(("if-range", validator));
+    }
+
+    let Redirected { mut response, .. } = http::get(url, &headers).await?;
+    if offset > 0 && response.status() == StatusCode::RequestedRangeNotSatisfiable {
+        // The partial file may already be all of it, e.g. if we stopped before renaming it.
+        let size = response.header("content-range").and_then(|header| {
+            header
+                .last()
+                .as_str()
+                .rsplit('/')
+                .next()?
+                .parse::<u64>()
+                .ok()
+        });
+        if size == Some(offset) {
+            return finish(current, episode_pk, &partial, path).await;
+        }
+
+        // We can't tell what the partial file holds, so start over.
+        fs::remove_file(&partial).await.ok();
+        response = http::get(url, &[]).await?.response;
+    }
+    let mut response = http::success(url, response)?;
+
+    // Servers that don't support range requests send the whole file.
+    let resumed = response.status() == StatusCode::PartialContent;
+    let mut downloaded = if resumed { offset } else { 0 };
+    let total = response
+        .header("content-length")
+        .and_then(|header| header.last().to_string().parse::<u64>().ok())
+        .map(|len| len + downloaded);
+
+    if !resumed {
+        // If-Range needs a strong validator.
+        let validator = response
+            .header("etag")
+            .map(|header| header.last().as_str())
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| {
+                response
+                    .header("last-modified")
+                    .map(|header| header.last().as_str())
+            })
+            .map(str::to_owned);
+        match validator {
+            Some(validator) => fs::write(&validator_path, validator).await?,
+            None => {
+                fs::remove_file(&validator_path).await.ok();
+            }
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&partial)
+        .await?;
+
+    let mut buf = vec![0; 64 * 1024];
+    let mut reported = downloaded;
+    loop {
+        if !is_active(current, episode_pk) {
+            file.flush().await?;
+            if current.get().download(episode_pk).is_none() {
+                // Deleted, not cancelled. The database may have removed it already.
+                drop(file);
+                fs::remove_file(&partial).await.ok();
+                fs::remove_file(&validator_path).await.ok();
+            }
+            return Ok(());
+        }
+
+        let len = response.read(&mut buf).await?;
+        if len == 0 {
+            break;
+        }
+
+        file.write_all(&buf[..len]).await?;
+        downloaded += len as u64;
+
+        if downloaded - reported >= REPORT_INTERVAL {
+            reported = downloaded;
+            current.update(vec![StateAction::SetDownload(
+                episode_pk.to_owned(),
+                Some(Download::InProgress { downloaded, total }),
+            )]);
+        }
+    }
+
+    file.flush().await?;
+    if let Some(total) = total {
+        if downloaded != total {
+            // Keep what we have so that the next attempt can resume from there.
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("Download stopped after {} of {} bytes", downloaded, total),
+            )
+            .into());
+        }
+    }
+    finish(current, episode_pk, &partial, path).await
+}
+
+/// Moves a complete download into place.
+async fn finish(
+    current: &CurrentState,
+    episode_pk: &str,
+    partial: &Path,
+    path: &Path,
+) -> Result<(), StateError> {
+    fs::rename(partial, path).await?;
+    fs::remove_file(validator_path(path)).await.ok();
+
+    current.update(vec![StateAction::SetDownload(
+        episode_pk.to_owned(),
+        Some(Download::Done(path.to_owned())),
+    )]);
+
+    Ok(())
+}
@@ -0,0 +1,297 @@
+use state::StateError;
+use std::io::{Error as IoError, ErrorKind};
+use xml::reader::{ParserConfig, XmlEvent};
+
+/// The XML namespaces we understand, whatever prefix a feed gives them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ns {
+    /// Plain RSS elements, which have no namespace.
+    None,
+    Itunes,
+    Content,
+    Podcast,
+    Media,
+    Atom,
+    Podlove,
+    Other,
+}
+
+impl Ns {
+    fn from_uri(uri: Option<&str>) -> Ns {
+        let uri = match uri {
+            Some(uri) if !uri.is_empty() => uri,
+            _ => return Ns::None,
+        };
+
+        // Feeds are sloppy about the scheme, case and trailing slash.
+        let uri = uri.trim().trim_end_matches('/').to_ascii_lowercase();
+        let uri = uri
+            .trim_start_matches("https://")
+            .trim_start_matches("http://");
+
+        match uri {
+            "www.itunes.com/dtds/podcast-1.0.dtd" | "itunes.com/dtds/podcast-1.0.dtd" => Ns::Itunes,
+            "purl.org/rss/1.0/modules/content" => Ns::Content,
+            "podcastindex.org/namespace/1.0"
+            | "github.com/podcastindex-org/podcast-namespace/blob/main/docs/1.0.md" => Ns::Podcast,
+            "search.yahoo.com/mrss" => Ns::Media,
+            "www.w3.org/2005/atom" => Ns::Atom,
+            "podlove.org/simple-chapters" => Ns::Podlove,
+            _ => Ns::Other,
+        }
+    }
+}
+
+/// Prefixes feeds commonly use without declaring them, and what they usually mean.
+const CONVENTIONAL_PREFIXES: [(&str, &str); 6] = [
+    ("itunes", "http://www.itunes.com/dtds/podcast-1.0.dtd"),
+    ("content", "http://purl.org/rss/1.0/modules/content/"),
+    ("podcast", "https://podcastindex.org/namespace/1.0"),
+    ("media", "http://search.yahoo.com/mrss/"),
+    ("atom", "http://www.w3.org/2005/Atom"),
+    ("psc", "http://podlove.org/simple-chapters"),
+];
+
+/// HTML entities that show up in feeds even though XML doesn't define them.
+pub(crate) const HTML_ENTITIES: [(&str, &str); 10] = [
+    ("nbsp", "\u{a0}"),
+    ("ndash", "\u{2013}"),
+    ("mdash", "\u{2014}"),
+    ("lsquo", "\u{2018}"),
+    ("rsquo", "\u{2019}"),
+    ("ldquo", "\u{201c}"),
+    ("rdquo", "\u{201d}"),
+    ("hellip", "\u{2026}"),
+    ("copy", "\u{a9}"),
+    ("eacute", "\u{e9}"),
+];
+
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub ns: Ns,
+    pub name: String,
+    pub value: String,
+}
+
+/// An XML element, with its namespace resolved.
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub ns: Ns,
+    pub name: String,
+    pub attributes: Vec<Attribute>,
+    /// The text inside the element before its first child, including CDATA.
+    pub text: String,
+    pub children: Vec<Element>,
+    /// The text after the element's end tag, before its next sibling.
+    pub tail: String,
+}
+
+impl Element {
+    pub fn is(&self, ns: Ns, name: &str) -> bool {
+        self.ns == ns && self.name == name
+    }
+
+    pub fn children<'a>(&'a self, ns: Ns, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.is(ns, name))
+    }
+
+    pub fn child(&self, ns: Ns, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.is(ns, name))
+    }
+
+    /// The trimmed text of the first matching child, if it isn't empty.
+    pub fn child_text(&self, ns: Ns, name: &str) -> Option<String> {
+        self.children(ns, name)
+            .map(|child| child.text.trim())
+            .find(|text| !text.is_empty())
+            .map(|text| text.to_owned())
+    }
+
+    /// The text inside the element and all of its descendants.
+    pub fn deep_text(&self) -> String {
+        let mut text = self.text.clone();
+        for child in &self.children {
+            text.push_str(&child.deep_text());
+            text.push_str(&child.tail);
+        }
+        text
+    }
+
+    /// An attribute without a namespace, if it isn't empty.
+    pub fn attr(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.ns == Ns::None && attribute.name == name)
+            .map(|attribute| attribute.value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_owned())
+    }
+}
+
+fn invalid_data(message: String) -> StateError {
+    IoError::new(ErrorKind::InvalidData, message).into()
+}
+
+fn parse_strict(source: &[u8]) -> Result<Element, xml::reader::Error> {
+    let mut config = ParserConfig::new()
+        .trim_whitespace(false)
+        .whitespace_to_characters(true)
+        .cdata_to_characters(true)
+        .ignore_comments(true)
+        .coalesce_characters(true)
+        .replace_unknown_entity_references(true);
+    for (entity, value) in HTML_ENTITIES.iter() {
+        config = config.add_entity(*entity, *value);
+    }
+
+    // Open elements, innermost last.
+    let mut stack: Vec<Element> = vec![];
+    let mut root = None;
+
+    for event in config.create_reader(source) {
+        match event? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => stack.push(Element {
+                ns: Ns::from_uri(name.namespace.as_deref()),
+                name: name.local_name,
+                attributes: attributes
+                    .into_iter()
+                    .map(|attribute| Attribute {
+                        ns: Ns::from_uri(attribute.name.namespace.as_deref()),
+                        name: attribute.name.local_name,
+                        value: attribute.value,
+                    })
+                    .collect(),
+                text: String::new(),
+                children: vec![],
+                tail: String::new(),
+            }),
+            XmlEvent::EndElement { .. } => {
+                if let Some(element) = stack.pop() {
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => root = Some(element),
+                    }
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    match element.children.last_mut() {
+                        Some(child) => child.tail.push_str(&text),
+                        None => element.text.push_str(&text),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(root.unwrap_or(Element {
+        ns: Ns::None,
+        name: String::new(),
+        attributes: vec![],
+        text: String::new(),
+        children: vec![],
+        tail: String::new(),
+    }))
+}
+
+/// Declares conventional prefixes that are used but not declared, on the root element.
+fn declare_prefixes(source: &str) -> String {
+    let root = source.match_indices('<').map(|(idx, _)| idx).find(|idx| {
+        source[idx + 1..]
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_alphabetic())
+    });
+    let root = match root {
+        Some(root) => root,
+        None => return source.to_owned(),
+    };
+    let name_end = source[root..]
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .map_or(source.len(), |idx| root + idx);
+
+    let mut declarations = String::new();
+    for (prefix, uri) in CONVENTIONAL_PREFIXES.iter() {
+        let used =
+            source.contains(&format!("<{}:", prefix)) || source.contains(&format!(" {}:", prefix));
+        if used && !source.contains(&format!("xmlns:{}=", prefix)) {
+            declarations.push_str(&format!(" xmlns:{}=\"{}\"", prefix, uri));
+        }
+    }
+
+    format!(
+        "{}{}{}",
+        &source[..name_end],
+        declarations,
+        &source[name_end..]
+    )
+}
+
+/// Parses a feed into a tree of elements.
+///
+/// Feeds that use the usual prefixes (like `itunes:`) without declaring them are accepted.
+pub fn parse(source: &[u8]) -> Result<Element, StateError> {
+    parse_strict(source).or_else(|err| {
+        let original = String::from_utf8_lossy(source);
+        let declared = declare_prefixes(&original);
+        if declared == original {
+            return Err(invalid_data(format!("Invalid feed: {}", err)));
+        }
+
+        parse_strict(declared.as_bytes())
+            .map_err(|err| invalid_data(format!("Invalid feed: {}", err)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_prefixes() {
+        let root = parse(
+            br#"<rss xmlns:i="http://www.itunes.com/DTDs/Podcast-1.0.dtd">
+                <channel><i:author>Someone</i:author><author>Else</author></channel>
+            </rss>"#,
+        )
+        .unwrap();
+
+        let channel = root.child(Ns::None, "channel").unwrap();
+        assert_eq!(
+            channel.child_text(Ns::Itunes, "author").as_deref(),
+            Some("Someone")
+        );
+        assert_eq!(
+            channel.child_text(Ns::None, "author").as_deref(),
+            Some("Else")
+        );
+    }
+
+    #[test]
+    fn declares_missing_prefixes() {
+        let root = parse(br#"<rss version="2.0"><channel><itunes:author>Someone</itunes:author></channel></rss>"#)
+            .unwrap();
+
+        let channel = root.child(Ns::None, "channel").unwrap();
+        assert_eq!(
+            channel.child_text(Ns::Itunes, "author").as_deref(),
+            Some("Someone")
+        );
+    }
+
+    #[test]
+    fn keeps_cdata_and_entities() {
+        let root =
+            parse(b"<a><b><![CDATA[<p>Time: 10:30</p>]]></b><c>A&nbsp;&amp;&mdash;B</c></a>")
+                .unwrap();
+
+        assert_eq!(
+            root.child(Ns::None, "b").unwrap().text,
+            "<p>Time: 10:30</p>"
+        );
+        assert_eq!(root.child(Ns::None, "c").unwrap().text, "A\u{a0}&\u{2014}B");
+    }
+}
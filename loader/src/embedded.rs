@@ -0,0 +1,125 @@
+use crate::{id3, mp4};
+use async_std::task;
+use directories::ProjectDirs;
+use state::{Chapter, Image, StateError};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, Error as IoError, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use surf::url::Url;
+
+/// An image stored inside an audio file.
+#[derive(Debug, Clone)]
+pub struct Picture {
+    pub mimetype: String,
+    pub data: Vec<u8>,
+}
+
+/// A chapter read from an audio file's tags.
+#[derive(Debug, Clone)]
+pub struct EmbeddedChapter {
+    /// Milliseconds from the start of the episode.
+    pub start: u64,
+    pub title: String,
+    pub url: Option<String>,
+    pub picture: Option<Picture>,
+}
+
+/// Where chapter images taken out of downloads are kept, so they have a URL.
+fn pictures_dir() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("ca", "nettek", "Pyrocast")?;
+    Some(dirs.cache_dir().join("chapters"))
+}
+
+fn read_tags(path: &Path) -> Result<Vec<EmbeddedChapter>, StateError> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0; 8];
+    let len = file.read(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    if len >= 3 && &magic[0..3] == b"ID3" {
+        Ok(id3::read_chapters(file)?)
+    } else if len == 8 && &magic[4..8] == b"ftyp" {
+        Ok(mp4::read_chapters(file)?)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Saves a chapter's picture and returns its URL.
+fn save_picture(dir: &Path, episode_pk: &str, idx: usize, picture: &Picture) -> Option<String> {
+    let mut hasher = DefaultHasher::new();
+    episode_pk.hash(&mut hasher);
+    let extension = if picture.mimetype == "image/png" {
+        "png"
+    } else {
+        "jpg"
+    };
+    let path = dir.join(format!("{:016x}-{}.{}", hasher.finish(), idx, extension));
+
+    if let Err(err) = fs::create_dir_all(dir).and_then(|_| fs::write(&path, &picture.data)) {
+        eprintln!("Cannot save chapter image {}: {}", path.display(), err);
+        return None;
+    }
+    Url::from_file_path(&path).ok().map(|url| url.to_string())
+}
+
+/// Loads a chapter image saved by `read`.
+pub async fn load_picture(url: &str) -> Result<Image, StateError> {
+    let path = Url::parse(url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "Not a file URL"))?;
+    let mimetype = if path
+        .extension()
+        .map_or(false, |extension| extension == "png")
+    {
+        "image/png"
+    } else {
+        "image/jpeg"
+    };
+
+    Ok(Image {
+        mimetype: Some(mimetype.to_owned()),
+        data: Some(async_std::fs::read(&path).await?),
+        ..Image::new(url)
+    })
+}
+
+/// Reads the ID3 or MP4 chapters of a downloaded episode. Chapter images are saved to disk, and
+/// returned as loaded images so they can be shown right away.
+pub async fn read(episode_pk: &str, path: &Path) -> Result<(Vec<Chapter>, Vec<Image>), StateError> {
+    let episode_pk = episode_pk.to_owned();
+    let path = path.to_owned();
+
+    task::spawn_blocking(move || {
+        let dir = pictures_dir();
+        let mut chapters = vec![];
+        let mut images = vec![];
+
+        for (idx, embedded) in read_tags(&path)?.into_iter().enumerate() {
+            let image = match (&dir, &embedded.picture) {
+                (Some(dir), Some(picture)) => save_picture(dir, &episode_pk, idx, picture),
+                _ => None,
+            };
+            if let (Some(url), Some(picture)) = (&image, embedded.picture) {
+                images.push(Image {
+                    mimetype: Some(picture.mimetype),
+                    data: Some(picture.data),
+                    ..Image::new(url)
+                });
+            }
+
+            chapters.push(Chapter {
+                start: embedded.start,
+                title: embedded.title,
+                image,
+                url: embedded.url,
+            });
+        }
+
+        Ok((chapters, images))
+    })
+    .await
+}
@@ -0,0 +1,170 @@
+use crate::element::{Element, Ns};
+use state::Enclosure;
+
+/// A whole number from an attribute. Bitrates are sometimes written with decimals.
+fn number(value: Option<String>) -> Option<u64> {
+    let value = value?.parse::<f64>().ok()?;
+    Some(value as u64).filter(|value| *value > 0)
+}
+
+/// Adds a variant, or fills in what we didn't know about one we already have.
+fn add(enclosures: &mut Vec<Enclosure>, enclosure: Enclosure) {
+    if enclosure.url.is_empty() {
+        return;
+    }
+
+    match enclosures
+        .iter_mut()
+        .find(|known| known.url == enclosure.url)
+    {
+        Some(known) => {
+            known.mimetype = known.mimetype.take().or(enclosure.mimetype);
+            known.length = known.length.or(enclosure.length);
+            known.bitrate = known.bitrate.or(enclosure.bitrate);
+            known.title = known.title.take().or(enclosure.title);
+        }
+        None => enclosures.push(enclosure),
+    }
+}
+
+/// Every file an RSS item is published as, with the main enclosure first. media:content is a
+/// common fallback for feeds without an enclosure.
+pub fn parse(item: &Element) -> Vec<Enclosure> {
+    let mut enclosures = vec![];
+
+    for enclosure in item.children(Ns::None, "enclosure") {
+        add(
+            &mut enclosures,
+            Enclosure {
+                url: enclosure.attr("url").unwrap_or_default(),
+                mimetype: enclosure.attr("type"),
+                length: number(enclosure.attr("length")),
+                bitrate: None,
+                title: None,
+            },
+        );
+    }
+
+    let contents = item.children(Ns::Media, "content").chain(
+        item.children(Ns::Media, "group")
+            .flat_map(|group| group.children(Ns::Media, "content")),
+    );
+    for content in contents {
+        let medium = content.attr("medium");
+        let mimetype = content.attr("type");
+        let playable = match (medium.as_deref(), mimetype.as_deref()) {
+            (Some(medium), _) => medium == "audio" || medium == "video",
+            (None, Some(mimetype)) => {
+                mimetype.starts_with("audio/") || mimetype.starts_with("video/")
+            }
+            (None, None) => true,
+        };
+        if !playable {
+            continue;
+        }
+
+        add(
+            &mut enclosures,
+            Enclosure {
+                url: content.attr("url").unwrap_or_default(),
+                mimetype,
+                length: number(content.attr("fileSize")),
+                // Media RSS bitrates are in kilobits per second.
+                bitrate: number(content.attr("bitrate")).map(|bitrate| bitrate * 1000),
+                title: None,
+            },
+        );
+    }
+
+    for alternate in item.children(Ns::Podcast, "alternateEnclosure") {
+        // Each source is a copy of the same file. We can only stream the ones served over HTTP.
+        let url = alternate
+            .children(Ns::Podcast, "source")
+            .filter_map(|source| source.attr("uri"))
+            .find(|uri| uri.starts_with("https://") || uri.starts_with("http://"));
+
+        add(
+            &mut enclosures,
+            Enclosure {
+                url: url.unwrap_or_default(),
+                mimetype: alternate.attr("type"),
+                length: number(alternate.attr("length")),
+                bitrate: number(alternate.attr("bitrate")),
+                title: alternate.attr("title"),
+            },
+        );
+    }
+
+    enclosures
+}
+
+/// Every file an Atom entry is published as, from its enclosure links.
+pub fn parse_atom(entry: &Element) -> Vec<Enclosure> {
+    let mut enclosures = vec![];
+
+    for link in entry.children(Ns::Atom, "link") {
+        if link.attr("rel").as_deref() == Some("enclosure") {
+            add(
+                &mut enclosures,
+                Enclosure {
+                    url: link.attr("href").unwrap_or_default(),
+                    mimetype: link.attr("type"),
+                    length: number(link.attr("length")),
+                    bitrate: None,
+                    title: link.attr("title"),
+                },
+            );
+        }
+    }
+
+    enclosures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element;
+
+    #[test]
+    fn parses_alternate_enclosures() {
+        let item = element::parse(
+            br#"<item xmlns:podcast="https://podcastindex.org/namespace/1.0"
+                xmlns:media="http://search.yahoo.com/mrss/">
+                <enclosure url="https://example.com/1.mp3" type="audio/mpeg" length="57600000"/>
+                <media:group>
+                    <media:content url="https://example.com/1.mp3" bitrate="128"/>
+                    <media:content url="https://example.com/1.jpg" medium="image"/>
+                </media:group>
+                <podcast:alternateEnclosure type="audio/opus" length="14400000" bitrate="32000.5"
+                    title="Low bandwidth">
+                    <podcast:source uri="ipfs://QmdwGqd3d2gFv61wCdoSGDoxRWcJqa3GaaBzrdwECE2CN"/>
+                    <podcast:source uri="https://example.com/1.opus"/>
+                </podcast:alternateEnclosure>
+                <podcast:alternateEnclosure type="video/mp4">
+                    <podcast:source uri="magnet:?xt=urn:btih:c8a5d2e5"/>
+                </podcast:alternateEnclosure>
+            </item>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            parse(&item),
+            vec![
+                Enclosure {
+                    url: "https://example.com/1.mp3".to_owned(),
+                    mimetype: Some("audio/mpeg".to_owned()),
+                    length: Some(57_600_000),
+                    bitrate: Some(128_000),
+                    title: None,
+                },
+                Enclosure {
+                    url: "https://example.com/1.opus".to_owned(),
+                    mimetype: Some("audio/opus".to_owned()),
+                    length: Some(14_400_000),
+                    bitrate: Some(32_000),
+                    title: Some("Low bandwidth".to_owned()),
+                },
+            ]
+        );
+    }
+}
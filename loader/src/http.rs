@@ -0,0 +1,89 @@
+use state::StateError;
+use std::io::{Error as IoError, ErrorKind};
+use surf::http_types::StatusCode;
+use surf::url::Url;
+use surf::Response;
+
+/// How many redirects to follow before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// A response, once any redirects have been followed.
+pub struct Redirected {
+    pub response: Response,
+    /// Where the resource lives now, if every redirect on the way was permanent.
+    pub moved_to: Option<String>,
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MovedPermanently
+            | StatusCode::Found
+            | StatusCode::SeeOther
+            | StatusCode::TemporaryRedirect
+            | StatusCode::PermanentRedirect
+    )
+}
+
+/// Sends a GET request with `headers`, following redirects. Any status other than a redirect is
+/// returned as is.
+pub async fn get(url: &str, headers: &[(&str, String)]) -> Result<Redirected, StateError> {
+    let mut location = url.to_owned();
+    let mut moved_to = None;
+    // It only moved if every redirect on the way was permanent.
+    let mut permanent = true;
+    let mut redirects = 0;
+
+    loop {
+        let mut request = surf::get(&location);
+        for (name, value) in headers {
+            request = request.set_header(*name, value.as_str());
+        }
+
+        let response = request.await?;
+        let status = response.status();
+        if !is_redirect(status) {
+            return Ok(Redirected { response, moved_to });
+        }
+
+        redirects += 1;
+        let next = response
+            .header("location")
+            .map(|header| header.last().to_string())
+            .and_then(|next| Url::parse(&location).ok()?.join(&next).ok())
+            .filter(|_| redirects <= MAX_REDIRECTS)
+            .ok_or_else(|| {
+                StateError::from(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("{} redirected too many times", url),
+                ))
+            })?;
+
+        permanent &= matches!(
+            status,
+            StatusCode::MovedPermanently | StatusCode::PermanentRedirect
+        );
+        location = next.to_string();
+        if permanent {
+            moved_to = Some(location.clone());
+        }
+    }
+}
+
+/// Fails unless the server sent what was asked for, rather than an error page.
+pub fn success(url: &str, response: Response) -> Result<Response, StateError> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(IoError::new(
+            ErrorKind::Other,
+            format!("Could not load {}: {}", url, response.status()),
+        )
+        .into())
+    }
+}
+
+/// Sends a GET request, following redirects, and fails unless the server sent what was asked for.
+pub async fn get_success(url: &str) -> Result<Response, StateError> {
+    success(url, get(url, &[]).await?.response)
+}
@@ -0,0 +1,308 @@
+use crate::embedded::{EmbeddedChapter, Picture};
+use std::collections::HashSet;
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
+
+/// A frame inside an ID3v2 tag, or inside a CHAP frame.
+struct Frame<'a> {
+    id: &'a [u8],
+    data: Vec<u8>,
+}
+
+/// Reverses ID3 unsynchronisation, which inserts a zero after every 0xff.
+fn resync(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = 0;
+    for &byte in data {
+        if !(prev == 0xff && byte == 0) {
+            out.push(byte);
+        }
+        prev = byte;
+    }
+    out
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 7) | (*byte & 0x7f) as usize)
+}
+
+fn be(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 8) | *byte as usize)
+}
+
+fn frames(data: &[u8], version: u8) -> Vec<Frame<'_>> {
+    let mut frames = vec![];
+    let mut pos = 0;
+    while pos + 10 <= data.len() && data[pos] != 0 {
+        let id = &data[pos..pos + 4];
+        let size = if version >= 4 {
+            syncsafe(&data[pos + 4..pos + 8])
+        } else {
+            be(&data[pos + 4..pos + 8])
+        };
+        let flags = data[pos + 9];
+        let start = pos + 10;
+        let end = (start + size).min(data.len());
+        let mut body = &data[start..end];
+
+        // Version 2.4 frames can have a data length indicator before the data.
+        if version >= 4 && flags & 0x01 != 0 && body.len() >= 4 {
+            body = &body[4..];
+        }
+        let body = if version >= 4 && flags & 0x02 != 0 {
+            resync(body)
+        } else {
+            body.to_vec()
+        };
+
+        frames.push(Frame { id, data: body });
+        pos = end;
+    }
+    frames
+}
+
+/// Splits off a string terminated according to its text encoding.
+fn split_terminated(data: &[u8], encoding: u8) -> (&[u8], &[u8]) {
+    if encoding == 1 || encoding == 2 {
+        let mut i = 0;
+        while i + 1 < data.len() {
+            if data[i] == 0 && data[i + 1] == 0 {
+                return (&data[..i], &data[i + 2..]);
+            }
+            i += 2;
+        }
+        (data, &[])
+    } else {
+        match data.iter().position(|byte| *byte == 0) {
+            Some(i) => (&data[..i], &data[i + 1..]),
+            None => (data, &[]),
+        }
+    }
+}
+
+fn decode(data: &[u8], encoding: u8) -> String {
+    let utf16 = |data: &[u8], big_endian: bool| {
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|pair| {
+                if big_endian {
+                    u16::from_be_bytes([pair[0], pair[1]])
+                } else {
+                    u16::from_le_bytes([pair[0], pair[1]])
+                }
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    let text = match encoding {
+        1 if data.starts_with(&[0xfe, 0xff]) => utf16(&data[2..], true),
+        1 if data.starts_with(&[0xff, 0xfe]) => utf16(&data[2..], false),
+        1 | 2 => utf16(data, true),
+        3 => String::from_utf8_lossy(data).into_owned(),
+        _ => data.iter().map(|byte| *byte as char).collect(),
+    };
+    text.trim_end_matches('\0').trim().to_owned()
+}
+
+fn text_frame(data: &[u8]) -> Option<String> {
+    let (encoding, text) = data.split_first()?;
+    Some(decode(text, *encoding)).filter(|text| !text.is_empty())
+}
+
+fn url_frame(data: &[u8]) -> Option<String> {
+    let (encoding, rest) = data.split_first()?;
+    let (_description, url) = split_terminated(rest, *encoding);
+    Some(decode(url, 0)).filter(|url| !url.is_empty())
+}
+
+fn picture_frame(data: &[u8]) -> Option<Picture> {
+    let (encoding, rest) = data.split_first()?;
+    let (mimetype, rest) = split_terminated(rest, 0);
+    let (_picture_type, rest) = rest.split_first()?;
+    let (_description, image) = split_terminated(rest, *encoding);
+    if image.is_empty() {
+        return None;
+    }
+
+    let mimetype = match decode(mimetype, 0).to_ascii_lowercase().as_str() {
+        "" | "image/jpg" | "jpg" | "jpeg" => "image/jpeg".to_owned(),
+        "png" => "image/png".to_owned(),
+        mimetype => mimetype.to_owned(),
+    };
+    Some(Picture {
+        mimetype,
+        data: image.to_vec(),
+    })
+}
+
+fn chapter_frame(data: &[u8], version: u8) -> Option<(String, EmbeddedChapter)> {
+    let (element_id, rest) = split_terminated(data, 0);
+    if rest.len() < 16 {
+        return None;
+    }
+    let start = be(&rest[0..4]) as u64;
+    let subframes = frames(&rest[16..], version);
+
+    let mut chapter = EmbeddedChapter {
+        start,
+        title: String::new(),
+        url: None,
+        picture: None,
+    };
+    for frame in subframes {
+        match frame.id {
+            b"TIT2" => chapter.title = text_frame(&frame.data).unwrap_or_default(),
+            b"WXXX" => chapter.url = url_frame(&frame.data),
+            b"APIC" => chapter.picture = picture_frame(&frame.data),
+            _ => {}
+        }
+    }
+
+    Some((decode(element_id, 0), chapter))
+}
+
+/// The element IDs listed by the top-level table of contents, if there is one.
+fn table_of_contents(data: &[u8]) -> Option<HashSet<String>> {
+    let (_element_id, rest) = split_terminated(data, 0);
+    let (flags, rest) = rest.split_first()?;
+    // Only the top-level table lists the chapters to show.
+    if flags & 0x02 == 0 {
+        return None;
+    }
+    let (count, mut rest) = rest.split_first()?;
+
+    let mut ids = HashSet::new();
+    for _ in 0..*count {
+        let (id, next) = split_terminated(rest, 0);
+        ids.insert(decode(id, 0));
+        rest = next;
+    }
+    Some(ids)
+}
+
+/// Reads the CHAP frames of an ID3v2.3 or ID3v2.4 tag at the start of a file.
+pub fn read_chapters<R: Read>(mut file: R) -> IoResult<Vec<EmbeddedChapter>> {
+    let mut header = [0; 10];
+    match file.read_exact(&mut header) {
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(vec![]),
+        result => result?,
+    }
+    if &header[0..3] != b"ID3" {
+        return Ok(vec![]);
+    }
+    let version = header[3];
+    let flags = header[5];
+    if !(3..=4).contains(&version) {
+        return Ok(vec![]);
+    }
+
+    let mut tag = vec![0; syncsafe(&header[6..10])];
+    file.read_exact(&mut tag)
+        .map_err(|err| IoError::new(ErrorKind::InvalidData, err))?;
+
+    if version == 3 && flags & 0x80 != 0 {
+        tag = resync(&tag);
+    }
+    if flags & 0x40 != 0 && tag.len() >= 4 {
+        let extended = if version >= 4 {
+            syncsafe(&tag[0..4])
+        } else {
+            be(&tag[0..4]) + 4
+        };
+        tag.drain(..extended.min(tag.len()));
+    }
+
+    let mut chapters = vec![];
+    let mut toc = None;
+    for frame in frames(&tag, version) {
+        match frame.id {
+            b"CHAP" => chapters.extend(chapter_frame(&frame.data, version)),
+            b"CTOC" => toc = toc.or_else(|| table_of_contents(&frame.data)),
+            _ => {}
+        }
+    }
+
+    Ok(chapters
+        .into_iter()
+        .filter(|(id, _)| toc.as_ref().map_or(true, |toc| toc.contains(id)))
+        .map(|(_, chapter)| chapter)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    fn chap(id: &str, start: u32, title: &str, extra: &[u8]) -> Vec<u8> {
+        let mut data = id.as_bytes().to_vec();
+        data.push(0);
+        data.extend_from_slice(&start.to_be_bytes());
+        data.extend_from_slice(&(start + 1000).to_be_bytes());
+        data.extend_from_slice(&[0xff; 8]);
+        let mut title_frame = vec![3];
+        title_frame.extend_from_slice(title.as_bytes());
+        data.extend(frame(b"TIT2", &title_frame));
+        data.extend_from_slice(extra);
+        frame(b"CHAP", &data)
+    }
+
+    #[test]
+    fn reads_chapters() {
+        let mut picture = vec![0];
+        picture.extend_from_slice(b"image/png\0\x03cover\0PNGDATA");
+
+        let mut toc = b"toc\0\x03\x02".to_vec();
+        toc.extend_from_slice(b"ch0\0ch1\0");
+
+        let mut frames = vec![];
+        frames.extend(frame(b"TIT2", b"\x03Episode"));
+        frames.extend(frame(b"CTOC", &toc));
+        frames.extend(chap("ch0", 0, "Intro", &[]));
+        frames.extend(chap("ch1", 62_500, "Interview", &frame(b"APIC", &picture)));
+        frames.extend(chap("hidden", 90_000, "Ad", &[]));
+        frames.extend_from_slice(&[0; 32]);
+
+        let mut file = b"ID3\x03\x00\x00".to_vec();
+        let size = frames.len();
+        file.extend_from_slice(&[
+            (size >> 21) as u8 & 0x7f,
+            (size >> 14) as u8 & 0x7f,
+            (size >> 7) as u8 & 0x7f,
+            size as u8 & 0x7f,
+        ]);
+        file.extend(frames);
+        file.extend_from_slice(b"audio");
+
+        let chapters = read_chapters(&file[..]).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "Intro");
+        assert_eq!(chapters[1].start, 62_500);
+        assert_eq!(chapters[1].title, "Interview");
+
+        let picture = chapters[1].picture.as_ref().unwrap();
+        assert_eq!(picture.mimetype, "image/png");
+        assert_eq!(picture.data, b"PNGDATA");
+
+        assert!(read_chapters(&b"not a tag"[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decodes_text() {
+        assert_eq!(decode(b"caf\xe9", 0), "café");
+        assert_eq!(decode(b"\xff\xfeh\0i\0\0\0", 1), "hi");
+        assert_eq!(decode(b"\0h\0i", 2), "hi");
+        assert_eq!(decode("café\0".as_bytes(), 3), "café");
+    }
+}
@@ -0,0 +1,235 @@
+use async_std::fs;
+use async_std::prelude::*;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use state::{Image, StateError};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How much artwork to keep on disk, in bytes.
+const MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// What we know about a cached image, stored next to it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    mimetype: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the image was last used (seconds since the epoch).
+    used: i64,
+}
+
+/// Seconds since the epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Artwork saved between runs, so it doesn't have to be downloaded every launch.
+///
+/// Each image is stored as a pair of files named after a hash of its URL: the image itself, and
+/// its validators so it can be revalidated. The least recently used images are deleted when the
+/// cache grows past its limit.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ImageCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> ImageCache {
+        ImageCache { dir, max_bytes }
+    }
+
+    /// The cache in the user's cache directory (e.g., ~/.cache/pyrocast/images).
+    pub fn open() -> Option<ImageCache> {
+        let dirs = ProjectDirs::from("ca", "nettek", "Pyrocast")?;
+        let dir = dirs.cache_dir().join("images");
+        if let Err(err) = std::fs::create_dir_all(&dir) {
+            eprintln!("Cannot create image cache {}: {}", dir.display(), err);
+            return None;
+        }
+
+        Some(ImageCache::new(dir, MAX_BYTES))
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        let name = format!("{:016x}", hasher.finish());
+
+        (
+            self.dir.join(format!("{}.img", name)),
+            self.dir.join(format!("{}.json", name)),
+        )
+    }
+
+    async fn read_entry(path: &Path) -> Option<Entry> {
+        serde_json::from_slice(&fs::read(path).await.ok()?).ok()
+    }
+
+    async fn write_entry(path: &Path, entry: &Entry) -> Result<(), StateError> {
+        let json = serde_json::to_vec(entry).map_err(std::io::Error::from)?;
+        fs::write(path, json).await?;
+        Ok(())
+    }
+
+    /// Reads an image from the cache, and marks it as used.
+    pub async fn get(&self, url: &str) -> Option<Image> {
+        let (data_path, entry_path) = self.paths(url);
+        let mut entry = Self::read_entry(&entry_path).await?;
+        // Hashes can collide.
+        if entry.url != url {
+            return None;
+        }
+        let data = fs::read(&data_path).await.ok()?;
+
+        entry.used = now();
+        if let Err(err) = Self::write_entry(&entry_path, &entry).await {
+            eprintln!("Cannot update image cache: {}", err);
+        }
+
+        Some(Image {
+            pk: entry.url,
+            mimetype: entry.mimetype,
+            etag: entry.etag,
+            last_modified: entry.last_modified,
+            data: Some(data),
+            thumbnails: vec![],
+        })
+    }
+
+    /// Saves a loaded image, making room for it if needed.
+    pub async fn put(&self, image: &Image) -> Result<(), StateError> {
+        let data = match &image.data {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+
+        let (data_path, entry_path) = self.paths(&image.pk);
+        fs::write(&data_path, data).await?;
+        Self::write_entry(
+            &entry_path,
+            &Entry {
+                url: image.pk.clone(),
+                mimetype: image.mimetype.clone(),
+                etag: image.etag.clone(),
+                last_modified: image.last_modified.clone(),
+                used: now(),
+            },
+        )
+        .await?;
+
+        self.evict(&data_path).await
+    }
+
+    /// Deletes the least recently used images, other than `keep`, until the cache fits in its
+    /// limit.
+    async fn evict(&self, keep: &Path) -> Result<(), StateError> {
+        let mut images = vec![];
+        let mut dir = fs::read_dir(&self.dir).await?;
+        while let Some(file) = dir.next().await {
+            let path: PathBuf = file?.path().into();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("img") {
+                continue;
+            }
+
+            let used = if path == keep {
+                i64::MAX
+            } else {
+                Self::read_entry(&path.with_extension("json"))
+                    .await
+                    .map(|entry| entry.used)
+                    .unwrap_or(0)
+            };
+            let size = fs::metadata(&path).await?.len();
+            images.push((path, size, used));
+        }
+
+        for path in evictions(images, self.max_bytes) {
+            fs::remove_file(&path).await?;
+            let _ = fs::remove_file(path.with_extension("json")).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Picks which images to delete, oldest first, so the rest take up at most `max_bytes`.
+///
+/// `images` are (path, size, last used).
+fn evictions(mut images: Vec<(PathBuf, u64, i64)>, max_bytes: u64) -> Vec<PathBuf> {
+    let mut used: u64 = images.iter().map(|(_, size, _)| size).sum();
+    images.sort_by_key(|(_, _, last_used)| *last_used);
+
+    let mut evicted = vec![];
+    for (path, size, _) in images {
+        if used <= max_bytes {
+            break;
+        }
+        used -= size;
+        evicted.push(path);
+    }
+
+    evicted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let images = vec![
+            (PathBuf::from("a.img"), 10, 3),
+            (PathBuf::from("b.img"), 10, 1),
+            (PathBuf::from("c.img"), 10, 2),
+        ];
+
+        assert!(evictions(images.clone(), 30).is_empty());
+        assert_eq!(evictions(images.clone(), 25), vec![PathBuf::from("b.img")]);
+        assert_eq!(
+            evictions(images, 10),
+            vec![PathBuf::from("b.img"), PathBuf::from("c.img")]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("pyrocast-image-cache-{}", now()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = ImageCache::new(dir.clone(), 4);
+
+        let image = |url: &str| Image {
+            data: Some(vec![1, 2, 3]),
+            etag: Some(String::from("\"abc\"")),
+            ..Image::new(url)
+        };
+
+        async_std::task::block_on(async {
+            cache
+                .put(&image("https://example.com/a.jpg"))
+                .await
+                .unwrap();
+            let cached = cache.get("https://example.com/a.jpg").await.unwrap();
+            assert_eq!(cached.data, Some(vec![1, 2, 3]));
+            assert_eq!(cached.etag.as_deref(), Some("\"abc\""));
+            assert!(cache.get("https://example.com/b.jpg").await.is_none());
+
+            // Only one fits, so the older one goes.
+            cache
+                .put(&image("https://example.com/b.jpg"))
+                .await
+                .unwrap();
+            assert!(cache.get("https://example.com/a.jpg").await.is_none());
+            assert!(cache.get("https://example.com/b.jpg").await.is_some());
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
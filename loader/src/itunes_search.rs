@@ -40,6 +40,20 @@ impl ItunesSearch {
         Ok(wrapper)
     }
 
+    /// Looks for the iTunes id of the channel with the given feed.
+    ///
+    /// iTunes can't be searched by feed URL, so this searches by title and checks the feed.
+    pub async fn find_feed(country: &str, title: &str, rss: &str) -> Option<String> {
+        let search = ItunesSearch::fetch(country, true, 50, title).await.ok()?;
+        let rss = normalize_feed_url(rss);
+
+        search
+            .results
+            .iter()
+            .find(|item| normalize_feed_url(&item.rss) == rss)
+            .map(|item| item.pk.to_string())
+    }
+
     pub fn results(&self) -> Vec<String> {
         self.results
             .iter()
@@ -68,3 +82,13 @@ impl ItunesSearch {
         actions
     }
 }
+
+/// The same feed is often listed with and without https, or with a trailing slash.
+fn normalize_feed_url(url: &str) -> &str {
+    let url = url.trim();
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    url.trim_end_matches('/')
+}
@@ -1,25 +1,49 @@
+mod chapters;
+mod download;
+mod element;
+mod embedded;
+mod enclosures;
+mod http;
+mod id3;
+mod image_cache;
 mod itunes_channel;
 mod itunes_chart;
 mod itunes_lookup;
 mod itunes_search;
+mod mp4;
+mod notes;
 mod query;
 mod rss;
+mod thumbnail;
+mod transcript;
 
 use async_std::task;
+pub use download::{partial_path, validator_path};
+pub use image_cache::ImageCache;
 use itunes_channel::ItunesChannel;
 use itunes_chart::ItunesChart;
 use itunes_lookup::ItunesLookup;
 use itunes_search::ItunesSearch;
-pub use query::Query;
-use rss::Rss;
-use state::{CurrentState, Image, StateAction};
+pub use query::{is_feed_url, Query};
+use rss::{Fetched, Rss};
+use state::{CurrentState, Download, Image, StateAction, StateError};
 use std::cmp::Reverse;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, VecDeque};
 use std::sync::{Arc, Mutex};
 use surf::http_types::StatusCode;
 use surf::mime::STAR_STAR;
 
-async fn handle_query(current: &CurrentState, query: &Query, request_idx: usize) -> Vec<Query> {
+/// Finds the iTunes id for a feed URL, given the channel's title.
+pub async fn find_itunes_id(country: &str, title: &str, rss: &str) -> Option<String> {
+    ItunesSearch::find_feed(country, title, rss).await
+}
+
+async fn handle_query(
+    current: &CurrentState,
+    images: Option<&ImageCache>,
+    query: &Query,
+    request_idx: usize,
+) -> Vec<Query> {
     let state = current.get();
     match query {
         Query::ItunesChart => {
@@ -84,9 +108,17 @@ async fn handle_query(current: &CurrentState, query: &Query, request_idx: usize)
                         .cores(current)
                         .iter()
                         .filter_map(|core| {
+                            // iTunes may not know that the feed moved.
+                            let saved = state.channel_ref(core.pk().to_owned()).core();
+                            let moved = saved
+                                .as_deref()
+                                .and_then(|saved| saved.as_ref().ok())
+                                .filter(|saved| saved.rss_moved())
+                                .and_then(|saved| saved.rss());
+
                             Some(Query::Rss {
                                 pk: core.pk().to_owned(),
-                                url: core.rss()?.to_owned(),
+                                url: moved.or_else(|| core.rss())?.to_owned(),
                             })
                         })
                         .collect()
@@ -99,6 +131,10 @@ async fn handle_query(current: &CurrentState, query: &Query, request_idx: usize)
                     vec![]
                 }
             },
+            Err(_) if is_feed_url(pk) => vec![Query::Rss {
+                pk: pk.to_owned(),
+                url: pk.to_owned(),
+            }],
             Err(_err) => {
                 let err = std::io::Error::new(
                     std::io::ErrorKind::InvalidData,
@@ -116,69 +152,171 @@ async fn handle_query(current: &CurrentState, query: &Query, request_idx: usize)
                 vec![]
             }
         },
-        Query::Rss { url, pk } => match Rss::fetch(url, pk).await {
-            Ok(rss) => {
-                current.update(rss.channel.to_actions(current));
-
-                vec![Query::Image {
-                    image: Arc::new(Image::new(&rss.channel.image_rss())),
-                    associated_query: None,
-                }]
-            }
-            Err(err) => {
-                current.update(vec![
-                    StateAction::SetChannelCore(pk.to_owned(), Err(err.clone())),
-                    StateAction::SetChannelDetail(pk.to_owned(), Err(err)),
-                ]);
+        Query::Rss { url, pk } => {
+            // A 304 is only useful if we already have the feed.
+            let loaded = matches!(
+                state.channel_ref(pk.to_owned()).details().as_deref(),
+                Some(Ok(_))
+            );
+            let cache = state.feed_cache(url).filter(|_| loaded);
+            let ended = matches!(
+                state.channel_ref(pk.to_owned()).core().as_deref(),
+                Some(Ok(core)) if core.ended()
+            );
 
-                vec![]
+            match Rss::fetch(url, pk, cache).await {
+                Ok((Fetched::Feed(rss, cache), redirected_to)) => {
+                    let mut actions = rss.channel.to_actions(current);
+                    // The validators belong to wherever the feed was served from.
+                    let cache_url = redirected_to.as_deref().unwrap_or(url);
+                    actions.push(StateAction::SetFeedCache(cache_url.to_owned(), cache));
+                    // Hosts that can't redirect use itunes:new-feed-url instead.
+                    if let Some(moved_to) =
+                        redirected_to.or_else(|| rss.channel.new_feed_url.clone())
+                    {
+                        actions.push(StateAction::SetChannelRss(pk.to_owned(), moved_to));
+                    }
+                    if ended {
+                        actions.push(StateAction::SetChannelEnded(pk.to_owned(), false));
+                    }
+                    current.update(actions);
+
+                    vec![Query::Image {
+                        image: Arc::new(Image::new(&rss.channel.image_rss())),
+                        associated_query: None,
+                    }]
+                }
+                Ok((Fetched::NotModified(cache), redirected_to)) => {
+                    let mut actions = vec![StateAction::SetFeedCache(
+                        redirected_to.as_deref().unwrap_or(url).to_owned(),
+                        cache,
+                    )];
+                    if let Some(moved_to) = redirected_to {
+                        actions.push(StateAction::SetChannelRss(pk.to_owned(), moved_to));
+                    }
+                    current.update(actions);
+
+                    vec![]
+                }
+                Ok((Fetched::Gone, _)) if loaded => {
+                    // Keep the episodes we have, but stop expecting new ones.
+                    current.update(vec![StateAction::SetChannelEnded(pk.to_owned(), true)]);
+
+                    vec![]
+                }
+                Ok((Fetched::Gone, _)) => {
+                    let err = StateError::from(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        "This feed is gone",
+                    ));
+                    current.update(vec![
+                        StateAction::SetChannelCore(pk.to_owned(), Err(err.clone())),
+                        StateAction::SetChannelDetail(pk.to_owned(), Err(err)),
+                    ]);
+
+                    vec![]
+                }
+                Err(err) => {
+                    current.update(vec![
+                        StateAction::SetChannelCore(pk.to_owned(), Err(err.clone())),
+                        StateAction::SetChannelDetail(pk.to_owned(), Err(err)),
+                    ]);
+
+                    vec![]
+                }
             }
-        },
+        }
+        // Chapter images taken out of downloads.
+        Query::Image { image, .. } if image.pk.starts_with("file:") => {
+            let loaded = match embedded::load_picture(&image.pk).await {
+                Ok(loaded) => Ok(thumbnail::with_thumbnails(loaded).await),
+                Err(err) => Err(err),
+            };
+            current.update(vec![StateAction::SetImage(image.pk.clone(), loaded)]);
+
+            Vec::default()
+        }
         Query::Image { image, .. } => {
-            let mut request = surf::get(&image.pk);
-            if let Some(etag) = &image.etag {
-                request = request.set_header("if-none-match", etag.clone());
+            // Show the copy on disk right away, then check that it is still fresh.
+            let cached = match images {
+                Some(images) if !image.loaded() => images.get(&image.pk).await,
+                _ => None,
+            };
+            let validators = cached.as_ref().unwrap_or(image);
+
+            let mut headers = vec![];
+            if let Some(etag) = &validators.etag {
+                headers.push(("if-none-match", etag.clone()));
             }
-            if let Some(last_modified) = &image.last_modified {
-                request = request.set_header("if-modified-since", last_modified.clone());
+            if let Some(last_modified) = &validators.last_modified {
+                headers.push(("if-modified-since", last_modified.clone()));
             }
 
-            match request.await {
-                Ok(mut response) => {
-                    if response.status() != StatusCode::NotModified {
-                        match response.body_bytes().await {
-                            Ok(body) => {
-                                let mime = response.mime().unwrap_or(STAR_STAR);
-                                current.update(vec![StateAction::SetImage(
-                                    image.pk.clone(),
-                                    Ok(Image {
-                                        pk: image.pk.clone(),
-                                        mimetype: Some(mime.to_string()),
-                                        etag: Some(
-                                            response
-                                                .header("etag")
-                                                .map(|header| header.last().to_string())
-                                                .unwrap_or_default(),
-                                        ),
-                                        last_modified: Some(
-                                            response
-                                                .header("last-modified")
-                                                .map(|header| header.last().to_string())
-                                                .unwrap_or_default(),
-                                        ),
-                                        data: Some(body),
-                                    }),
-                                )]);
-                            }
-                            Err(err) => {
-                                current.update(vec![StateAction::SetImage(
-                                    image.pk.clone(),
-                                    Err(err.into()),
-                                )]);
+            let had_cached = cached.is_some();
+            if let Some(cached) = cached {
+                let cached = thumbnail::with_thumbnails(cached).await;
+                current.update(vec![StateAction::SetImage(image.pk.clone(), Ok(cached))]);
+            }
+
+            match http::get(&image.pk, &headers)
+                .await
+                .map(|fetched| fetched.response)
+            {
+                Ok(response) if response.status() == StatusCode::NotModified => {}
+                Ok(response) if !response.status().is_success() => {
+                    // Don't replace the cached copy with an error page.
+                    if !had_cached {
+                        let err = std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            format!("Could not load {}: {}", &image.pk, response.status()),
+                        );
+                        current.update(vec![StateAction::SetImage(
+                            image.pk.clone(),
+                            Err(err.into()),
+                        )]);
+                    }
+                }
+                Ok(mut response) => match response.body_bytes().await {
+                    Ok(body) => {
+                        let mime = response.mime().unwrap_or(STAR_STAR);
+                        let loaded = Image {
+                            pk: image.pk.clone(),
+                            mimetype: Some(mime.to_string()),
+                            etag: Some(
+                                response
+                                    .header("etag")
+                                    .map(|header| header.last().to_string())
+                                    .unwrap_or_default(),
+                            ),
+                            last_modified: Some(
+                                response
+                                    .header("last-modified")
+                                    .map(|header| header.last().to_string())
+                                    .unwrap_or_default(),
+                            ),
+                            data: Some(body),
+                            thumbnails: vec![],
+                        };
+
+                        if let Some(images) = images {
+                            if let Err(err) = images.put(&loaded).await {
+                                eprintln!("Cannot cache {}: {}", &image.pk, err);
                             }
                         }
+
+                        let loaded = thumbnail::with_thumbnails(loaded).await;
+                        current.update(vec![StateAction::SetImage(image.pk.clone(), Ok(loaded))]);
                     }
-                }
+                    // Keep showing the cached copy when offline.
+                    Err(_) if had_cached => {}
+                    Err(err) => {
+                        current.update(vec![StateAction::SetImage(
+                            image.pk.clone(),
+                            Err(err.into()),
+                        )]);
+                    }
+                },
+                Err(_) if had_cached => {}
                 Err(err) => {
                     current.update(vec![StateAction::SetImage(
                         image.pk.clone(),
@@ -187,11 +325,77 @@ async fn handle_query(current: &CurrentState, query: &Query, request_idx: usize)
                 }
             }
 
+            Vec::default()
+        }
+        Query::Chapters { episode_pk, url } => {
+            let episode = state.episode_ref(episode_pk.to_owned()).get();
+            if let Some(Ok(episode)) = episode.as_deref() {
+                match chapters::fetch(url).await {
+                    Ok(chapters) => {
+                        current.update(vec![episode.clone().with_chapters(chapters).build()]);
+                    }
+                    Err(err) => eprintln!("Cannot load chapters from {}: {}", url, err),
+                }
+            }
+
+            Vec::default()
+        }
+        Query::Transcript { episode_pk, link } => {
+            let episode = state.episode_ref(episode_pk.to_owned()).get();
+            if let Some(Ok(episode)) = episode.as_deref() {
+                match transcript::fetch(link).await {
+                    Ok(transcript) => {
+                        current.update(vec![episode.clone().with_transcript(transcript).build()]);
+                    }
+                    Err(err) => eprintln!("Cannot load transcript from {}: {}", link.url, err),
+                }
+            }
+
+            Vec::default()
+        }
+        Query::EmbeddedChapters { episode_pk, path } => {
+            match embedded::read(episode_pk, path).await {
+                Ok((chapters, pictures)) => {
+                    let mut actions = vec![];
+                    for picture in pictures {
+                        let picture = thumbnail::with_thumbnails(picture).await;
+                        actions.push(StateAction::SetImage(picture.pk.clone(), Ok(picture)));
+                    }
+
+                    let episode = current.get().episode_ref(episode_pk.to_owned()).get();
+                    if let Some(Ok(episode)) = episode.as_deref() {
+                        actions.push(episode.clone().with_embedded_chapters(chapters).build());
+                    }
+                    current.update(actions);
+                }
+                Err(err) => eprintln!("Cannot read chapters from {}: {}", path.display(), err),
+            }
+
+            Vec::default()
+        }
+        Query::Download {
+            episode_pk,
+            url,
+            path,
+        } => {
+            // It may have been cancelled while queued.
+            if download::is_active(current, episode_pk) {
+                if let Err(err) = download::fetch(current, episode_pk, url, path).await {
+                    current.update(vec![StateAction::SetDownload(
+                        episode_pk.to_owned(),
+                        Some(Download::Failed(err)),
+                    )]);
+                }
+            }
+
             Vec::default()
         }
     }
 }
 
+/// How many episodes to download at once, so that they don't hold up everything else.
+const MAX_DOWNLOADS: usize = 2;
+
 #[derive(Debug)]
 struct LoaderPriv {
     queries: BinaryHeap<(Query, Reverse<usize>)>,
@@ -200,11 +404,15 @@ struct LoaderPriv {
     /// Used to break Query priority ties (oldest first).
     total_queries: usize,
     max_search_index: usize,
+    /// Downloads have their own workers, so they wait here instead of in `queries`.
+    downloads: VecDeque<Query>,
+    downloads_in_flight: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct Loader {
     current: Arc<CurrentState>,
+    images: Option<ImageCache>,
     data: Arc<Mutex<LoaderPriv>>,
 }
 
@@ -212,12 +420,15 @@ impl Loader {
     pub fn new(current: Arc<CurrentState>, max_in_flight: usize) -> Loader {
         Loader {
             current,
+            images: ImageCache::open(),
             data: Arc::new(Mutex::new(LoaderPriv {
                 total_queries: 0,
                 queries: BinaryHeap::new(),
                 in_flight: 0,
                 max_search_index: 0,
                 max_in_flight,
+                downloads: VecDeque::new(),
+                downloads_in_flight: 0,
             })),
         }
     }
@@ -227,6 +438,15 @@ impl Loader {
     }
 
     fn _queue(&self, query: Query, data: &mut LoaderPriv) {
+        if let Query::Download { .. } = query {
+            data.downloads.push_back(query);
+            if data.downloads_in_flight < MAX_DOWNLOADS {
+                data.downloads_in_flight += 1;
+                self.spawn_download_worker();
+            }
+            return;
+        }
+
         if query.is_search() {
             data.max_search_index = data.total_queries;
         }
@@ -268,12 +488,38 @@ impl Loader {
         None
     }
 
+    fn pop_download(&self) -> Option<Query> {
+        let mut data = self.data.lock().unwrap();
+        let query = data.downloads.pop_front();
+        if query.is_none() {
+            // Free the worker while we hold the lock, so that a new download can't be missed.
+            data.downloads_in_flight -= 1;
+        }
+
+        query
+    }
+
+    fn spawn_download_worker(&self) {
+        let scheduler = self.clone();
+        task::spawn(async move {
+            while let Some(query) = scheduler.pop_download() {
+                handle_query(&scheduler.current, scheduler.images.as_ref(), &query, 0).await;
+            }
+        });
+    }
+
     fn spawn_worker(&self) {
         let scheduler = self.clone();
         task::spawn(async move {
             while let Some((query, request_idx)) = scheduler.pop() {
                 let now = std::time::Instant::now();
-                let next: Vec<Query> = handle_query(&scheduler.current, &query, request_idx).await;
+                let next: Vec<Query> = handle_query(
+                    &scheduler.current,
+                    scheduler.images.as_ref(),
+                    &query,
+                    request_idx,
+                )
+                .await;
                 eprintln!("Task: {:?}", now.elapsed());
 
                 let mut data = scheduler.data.lock().unwrap();
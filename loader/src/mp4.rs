@@ -0,0 +1,465 @@
+use crate::embedded::{EmbeddedChapter, Picture};
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom};
+
+/// The biggest moov box we are willing to read into memory.
+const MAX_MOOV: u64 = 64 * 1024 * 1024;
+
+/// Chapter tracks have a sample per chapter, so there is no need to read more than this.
+const MAX_SAMPLES: usize = 64 * 1024;
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn u64_at(data: &[u8], pos: usize) -> Option<u64> {
+    let bytes = data.get(pos..pos + 8)?;
+    let mut array = [0; 8];
+    array.copy_from_slice(bytes);
+    Some(u64::from_be_bytes(array))
+}
+
+/// The boxes directly inside `data`, as (type, contents).
+fn boxes(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut boxes = vec![];
+    let mut pos = 0;
+    while let Some(size) = u32_at(data, pos) {
+        let kind = &data[pos + 4..(pos + 8).min(data.len())];
+        let (header, size) = match size {
+            0 => (8, data.len() - pos),
+            1 => match u64_at(data, pos + 8) {
+                Some(size) => (16, size as usize),
+                None => break,
+            },
+            size => (8, size as usize),
+        };
+        if size < header || pos + size > data.len() {
+            break;
+        }
+        boxes.push((kind, &data[pos + header..pos + size]));
+        pos += size;
+    }
+    boxes
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    boxes(data)
+        .into_iter()
+        .find(|(child_kind, _)| *child_kind == kind)
+        .map(|(_, contents)| contents)
+}
+
+fn path<'a>(data: &'a [u8], kinds: &[&[u8]]) -> Option<&'a [u8]> {
+    kinds.iter().try_fold(data, |data, kind| child(data, kind))
+}
+
+/// Finds the moov box at the top level of the file and reads it.
+fn read_moov<R: Read + Seek>(file: &mut R) -> IoResult<Option<Vec<u8>>> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut pos = 0;
+    while pos + 8 <= len {
+        file.seek(SeekFrom::Start(pos))?;
+        let mut header = [0; 16];
+        file.read_exact(&mut header[..8])?;
+        let (header_len, size) = match u32_at(&header, 0).unwrap_or(0) {
+            0 => (8, len - pos),
+            1 => {
+                file.read_exact(&mut header[8..])?;
+                (16, u64_at(&header, 8).unwrap_or(0))
+            }
+            size => (8, size as u64),
+        };
+        if size < header_len {
+            return Err(invalid_data("Invalid MP4 box"));
+        }
+
+        if &header[4..8] == b"moov" {
+            if size > MAX_MOOV {
+                return Err(invalid_data("MP4 moov box is too big"));
+            }
+            let mut moov = vec![0; (size - header_len) as usize];
+            file.read_exact(&mut moov)?;
+            return Ok(Some(moov));
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+/// Nero chapters, in moov/udta/chpl.
+fn nero_chapters(moov: &[u8]) -> Vec<EmbeddedChapter> {
+    let chpl = match path(moov, &[b"udta", b"chpl"]) {
+        Some(chpl) if !chpl.is_empty() => chpl,
+        _ => return vec![],
+    };
+    // Version 1 has four more reserved bytes.
+    let mut pos = if chpl[0] == 1 { 8 } else { 4 };
+    let count = chpl.get(pos).copied().unwrap_or(0);
+    pos += 1;
+
+    let mut chapters = vec![];
+    for _ in 0..count {
+        let start = match u64_at(chpl, pos) {
+            Some(start) => start,
+            None => break,
+        };
+        let len = chpl.get(pos + 8).copied().unwrap_or(0) as usize;
+        let title = match chpl.get(pos + 9..pos + 9 + len) {
+            Some(title) => String::from_utf8_lossy(title).trim().to_owned(),
+            None => break,
+        };
+        pos += 9 + len;
+
+        chapters.push(EmbeddedChapter {
+            // In units of 100 nanoseconds.
+            start: start / 10_000,
+            title,
+            url: None,
+            picture: None,
+        });
+    }
+    chapters
+}
+
+struct Track<'a> {
+    id: u32,
+    handler: &'a [u8],
+    chapter_tracks: Vec<u32>,
+    timescale: u32,
+    stbl: &'a [u8],
+}
+
+impl<'a> Track<'a> {
+    fn parse(trak: &'a [u8]) -> Option<Track<'a>> {
+        let tkhd = child(trak, b"tkhd")?;
+        let id = u32_at(tkhd, if tkhd.first() == Some(&1) { 20 } else { 12 })?;
+
+        let mdhd = path(trak, &[b"mdia", b"mdhd"])?;
+        let timescale = u32_at(mdhd, if mdhd.first() == Some(&1) { 20 } else { 12 })?;
+        let handler = path(trak, &[b"mdia", b"hdlr"])?.get(8..12)?;
+        let stbl = path(trak, &[b"mdia", b"minf", b"stbl"])?;
+
+        let chapter_tracks = path(trak, &[b"tref", b"chap"])
+            .map(|chap| {
+                chap.chunks_exact(4)
+                    .filter_map(|id| u32_at(id, 0))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Track {
+            id,
+            handler,
+            chapter_tracks,
+            timescale,
+            stbl,
+        })
+    }
+
+    /// The start (in milliseconds), offset and size of each sample.
+    fn samples(&self) -> Option<Vec<(u64, u64, usize)>> {
+        let stsz = child(self.stbl, b"stsz")?;
+        let fixed_size = u32_at(stsz, 4)?;
+        let count = u32_at(stsz, 8)? as usize;
+        // Don't allocate for a count that the box can't back up.
+        if count > MAX_SAMPLES || (fixed_size == 0 && count > stsz.len().saturating_sub(12) / 4) {
+            return None;
+        }
+        let sizes: Vec<usize> = (0..count)
+            .map(|i| {
+                if fixed_size != 0 {
+                    Some(fixed_size as usize)
+                } else {
+                    u32_at(stsz, 12 + i * 4).map(|size| size as usize)
+                }
+            })
+            .collect::<Option<_>>()?;
+
+        let chunk_offsets: Vec<u64> = if let Some(stco) = child(self.stbl, b"stco") {
+            (0..u32_at(stco, 4)? as usize)
+                .map(|i| u32_at(stco, 8 + i * 4).map(u64::from))
+                .collect::<Option<_>>()?
+        } else {
+            let co64 = child(self.stbl, b"co64")?;
+            (0..u32_at(co64, 4)? as usize)
+                .map(|i| u64_at(co64, 8 + i * 8))
+                .collect::<Option<_>>()?
+        };
+
+        // (first chunk, samples per chunk), with chunks numbered from 1.
+        let stsc = child(self.stbl, b"stsc")?;
+        let runs: Vec<(usize, usize)> = (0..u32_at(stsc, 4)? as usize)
+            .map(|i| {
+                Some((
+                    u32_at(stsc, 8 + i * 12)? as usize,
+                    u32_at(stsc, 12 + i * 12)? as usize,
+                ))
+            })
+            .collect::<Option<_>>()?;
+
+        let mut offsets = Vec::with_capacity(count);
+        for (chunk, chunk_offset) in chunk_offsets.iter().enumerate() {
+            let per_chunk = runs
+                .iter()
+                .rev()
+                .find(|(first, _)| *first <= chunk + 1)
+                .map_or(0, |(_, per_chunk)| *per_chunk);
+            let mut offset = *chunk_offset;
+            for _ in 0..per_chunk {
+                match sizes.get(offsets.len()) {
+                    Some(size) => {
+                        offsets.push(offset);
+                        offset += *size as u64;
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let stts = child(self.stbl, b"stts")?;
+        let mut starts = Vec::with_capacity(count);
+        let mut time = 0u64;
+        for i in 0..u32_at(stts, 4)? as usize {
+            let samples = u32_at(stts, 8 + i * 8)?;
+            let delta = u32_at(stts, 12 + i * 8)? as u64;
+            for _ in 0..(samples as usize).min(count - starts.len()) {
+                starts.push(time * 1000 / self.timescale.max(1) as u64);
+                time += delta;
+            }
+        }
+
+        Some(
+            starts
+                .into_iter()
+                .zip(offsets)
+                .zip(sizes)
+                .map(|((start, offset), size)| (start, offset, size))
+                .collect(),
+        )
+    }
+}
+
+fn read_sample<R: Read + Seek>(file: &mut R, offset: u64, size: usize) -> IoResult<Vec<u8>> {
+    // Chapter samples are small; anything else is not what we are looking for.
+    if size > 16 * 1024 * 1024 {
+        return Err(invalid_data("MP4 chapter sample is too big"));
+    }
+    let mut data = vec![0; size];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// A text sample is a 16-bit length followed by UTF-8 or UTF-16 text.
+fn sample_text(data: &[u8]) -> String {
+    let len = data
+        .get(0..2)
+        .map_or(0, |len| u16::from_be_bytes([len[0], len[1]]) as usize);
+    let text = data.get(2..2 + len).unwrap_or_default();
+
+    if text.starts_with(&[0xfe, 0xff]) {
+        let units: Vec<u16> = text[2..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(text).into_owned()
+    }
+}
+
+fn picture(data: Vec<u8>) -> Picture {
+    let mimetype = if data.starts_with(b"\x89PNG") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    };
+    Picture {
+        mimetype: mimetype.to_owned(),
+        data,
+    }
+}
+
+/// QuickTime chapters: a text track, and optionally a video track of images, that another track
+/// refers to with tref/chap.
+fn track_chapters<R: Read + Seek>(file: &mut R, moov: &[u8]) -> IoResult<Vec<EmbeddedChapter>> {
+    let tracks: Vec<Track> = boxes(moov)
+        .into_iter()
+        .filter(|(kind, _)| *kind == b"trak")
+        .filter_map(|(_, trak)| Track::parse(trak))
+        .collect();
+    let chapter_ids: Vec<u32> = tracks
+        .iter()
+        .flat_map(|track| track.chapter_tracks.iter().copied())
+        .collect();
+    let chapter_track = |handler: &[u8]| {
+        tracks
+            .iter()
+            .find(|track| chapter_ids.contains(&track.id) && track.handler == handler)
+    };
+
+    let text = match chapter_track(b"text").or_else(|| chapter_track(b"sbtl")) {
+        Some(text) => text,
+        None => return Ok(vec![]),
+    };
+    let images = chapter_track(b"vide")
+        .and_then(|track| track.samples())
+        .unwrap_or_default();
+
+    let mut chapters = vec![];
+    for (start, offset, size) in text.samples().unwrap_or_default() {
+        let title = sample_text(&read_sample(file, offset, size)?);
+        let image = images
+            .iter()
+            .rev()
+            .find(|(image_start, _, _)| *image_start <= start);
+        let picture = match image {
+            Some((_, offset, size)) => Some(picture(read_sample(file, *offset, *size)?)),
+            None => None,
+        };
+
+        chapters.push(EmbeddedChapter {
+            start,
+            title: title.trim().to_owned(),
+            url: None,
+            picture,
+        });
+    }
+    Ok(chapters)
+}
+
+/// Reads QuickTime chapter tracks, falling back to Nero chapters.
+pub fn read_chapters<R: Read + Seek>(mut file: R) -> IoResult<Vec<EmbeddedChapter>> {
+    let moov = match read_moov(&mut file)? {
+        Some(moov) => moov,
+        None => return Ok(vec![]),
+    };
+
+    let chapters = track_chapters(&mut file, &moov)?;
+    if chapters.is_empty() {
+        Ok(nero_chapters(&moov))
+    } else {
+        Ok(chapters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mp4_box(kind: &[u8], contents: &[u8]) -> Vec<u8> {
+        let mut data = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(contents);
+        data
+    }
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes().to_vec())
+            .collect()
+    }
+
+    fn trak(id: u32, handler: &[u8], tref: &[u8], samples: &[(u32, u32, u32)]) -> Vec<u8> {
+        // (duration, offset, size), one sample per chunk.
+        let tkhd = u32s(&[0, 0, 0, id]);
+        let mdhd = u32s(&[0, 0, 0, 1000]);
+        let mut hdlr = u32s(&[0, 0]);
+        hdlr.extend_from_slice(handler);
+
+        let mut stts = u32s(&[0, samples.len() as u32]);
+        let mut stsz = u32s(&[0, 0, samples.len() as u32]);
+        let mut stco = u32s(&[0, samples.len() as u32]);
+        for (duration, offset, size) in samples {
+            stts.extend(u32s(&[1, *duration]));
+            stsz.extend(u32s(&[*size]));
+            stco.extend(u32s(&[*offset]));
+        }
+        let stsc = u32s(&[0, 1, 1, 1, 1]);
+
+        let mut stbl = mp4_box(b"stts", &stts);
+        stbl.extend(mp4_box(b"stsz", &stsz));
+        stbl.extend(mp4_box(b"stsc", &stsc));
+        stbl.extend(mp4_box(b"stco", &stco));
+
+        let mut mdia = mp4_box(b"mdhd", &mdhd);
+        mdia.extend(mp4_box(b"hdlr", &hdlr));
+        mdia.extend(mp4_box(b"minf", &mp4_box(b"stbl", &stbl)));
+
+        let mut trak = mp4_box(b"tkhd", &tkhd);
+        trak.extend_from_slice(tref);
+        trak.extend(mp4_box(b"mdia", &mdia));
+        mp4_box(b"trak", &trak)
+    }
+
+    #[test]
+    fn reads_chapter_tracks() {
+        // The samples go in an mdat at the start of the file.
+        let mut mdat = vec![];
+        mdat.extend_from_slice(b"\x00\x05Intro");
+        mdat.extend_from_slice(b"\x00\x04News");
+        mdat.extend_from_slice(b"JPEG");
+        let base = 8;
+
+        let tref = mp4_box(b"tref", &mp4_box(b"chap", &u32s(&[2, 3])));
+        let mut moov = trak(1, b"soun", &tref, &[]);
+        moov.extend(trak(
+            2,
+            b"text",
+            &[],
+            &[(30_000, base, 7), (60_000, base + 7, 6)],
+        ));
+        moov.extend(trak(3, b"vide", &[], &[(90_000, base + 13, 4)]));
+
+        let mut file = mp4_box(b"mdat", &mdat);
+        file.extend(mp4_box(b"moov", &moov));
+
+        let chapters = read_chapters(Cursor::new(file)).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].start, 0);
+        assert_eq!(chapters[0].title, "Intro");
+        assert_eq!(chapters[1].start, 30_000);
+        assert_eq!(chapters[1].title, "News");
+        assert_eq!(chapters[1].picture.as_ref().unwrap().data, b"JPEG");
+    }
+
+    #[test]
+    fn ignores_bad_boxes() {
+        assert!(Track::parse(&mp4_box(b"tkhd", &[])).is_none());
+
+        // Far more samples than the box has sizes for.
+        let stbl = mp4_box(b"stsz", &u32s(&[0, 0, u32::MAX]));
+        let track = Track {
+            id: 1,
+            handler: b"text",
+            chapter_tracks: vec![],
+            timescale: 1000,
+            stbl: &stbl,
+        };
+        assert!(track.samples().is_none());
+    }
+
+    #[test]
+    fn reads_nero_chapters() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        chpl.extend_from_slice(&0u64.to_be_bytes());
+        chpl.extend_from_slice(b"\x05Intro");
+        chpl.extend_from_slice(&(625_000_000u64).to_be_bytes());
+        chpl.extend_from_slice(b"\x04News");
+        let moov = mp4_box(b"udta", &mp4_box(b"chpl", &chpl));
+
+        let mut file = mp4_box(b"ftyp", b"M4A ");
+        file.extend(mp4_box(b"moov", &moov));
+
+        let chapters = read_chapters(Cursor::new(file)).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].start, 62_500);
+        assert_eq!(chapters[1].title, "News");
+    }
+}
@@ -0,0 +1,427 @@
+use crate::element::HTML_ENTITIES;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use state::timestamp_href;
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+
+lazy_static! {
+    static ref ENTITY: Regex =
+        Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z][a-zA-Z0-9]*);").unwrap();
+    static ref HREF: Regex =
+        Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap();
+    static ref URL: Regex = Regex::new(r#"https?://[^\s<>"']+"#).unwrap();
+    static ref TIMESTAMP: Regex = Regex::new(r"(?:(\d{1,2}):)?(\d{1,2}):(\d{2})").unwrap();
+    static ref TAG: Regex =
+        Regex::new(r"(?s)<!--.*?-->|<(/?)([a-zA-Z][a-zA-Z0-9]*)([^>]*)>").unwrap();
+}
+
+/// Decodes HTML entities, leaving ones we don't know as they are. Feeds often escape the HTML in
+/// CDATA sections, so these are still there after the XML parser is done.
+pub(crate) fn decode_entities(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |caps: &Captures| {
+            let name = &caps[1];
+            let decoded =
+                if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    u32::from_str_radix(hex, 16)
+                        .ok()
+                        .and_then(std::char::from_u32)
+                        .map(String::from)
+                } else if let Some(decimal) = name.strip_prefix('#') {
+                    decimal
+                        .parse()
+                        .ok()
+                        .and_then(std::char::from_u32)
+                        .map(String::from)
+                } else {
+                    match name {
+                        "amp" => Some("&".to_owned()),
+                        "lt" => Some("<".to_owned()),
+                        "gt" => Some(">".to_owned()),
+                        "quot" => Some("\"".to_owned()),
+                        "apos" => Some("'".to_owned()),
+                        _ => HTML_ENTITIES
+                            .iter()
+                            .find(|(entity, _)| *entity == name)
+                            .map(|(_, value)| (*value).to_owned()),
+                    }
+                };
+            decoded.unwrap_or_else(|| caps[0].to_owned())
+        })
+        .into_owned()
+}
+
+/// Only links that open in a browser or mail client are kept.
+fn safe_href(href: &str) -> Option<String> {
+    let href = decode_entities(href.trim());
+    let lower = href.to_ascii_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("mailto:")
+    {
+        Some(href)
+    } else {
+        None
+    }
+}
+
+/// Finds timestamps like "12:34" or "1:02:03", and the time they point to in ms. Times of day
+/// like "10:30 am" and parts of longer numbers are left alone.
+fn find_timestamps(text: &str) -> Vec<(usize, usize, u64)> {
+    TIMESTAMP
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let whole = caps.get(0).unwrap();
+            let before = text[..whole.start()].chars().next_back();
+            let after = &text[whole.end()..];
+            if before.map_or(false, |c| c.is_ascii_digit() || c == ':' || c == '.')
+                || after.starts_with(|c: char| c.is_ascii_digit() || c == ':')
+            {
+                return None;
+            }
+            let after = after.trim_start().to_ascii_lowercase();
+            if ["am", "pm", "a.m", "p.m"]
+                .iter()
+                .any(|suffix| after.starts_with(suffix))
+            {
+                return None;
+            }
+
+            let hours: Option<u64> = caps.get(1).and_then(|hours| hours.as_str().parse().ok());
+            let minutes: u64 = caps[2].parse().ok()?;
+            let seconds: u64 = caps[3].parse().ok()?;
+            if seconds >= 60 || (hours.is_some() && minutes >= 60) {
+                return None;
+            }
+            let time = ((hours.unwrap_or(0) * 60 + minutes) * 60 + seconds) * 1000;
+            Some((whole.start(), whole.end(), time))
+        })
+        .collect()
+}
+
+/// Builds the markup, keeping track of what is open so the output is always well formed.
+struct Writer {
+    out: String,
+    /// Tags we opened and haven't closed yet, innermost last.
+    open: Vec<&'static str>,
+    /// The item count of each open list, or None for bulleted lists.
+    lists: Vec<Option<u32>>,
+    /// Whether any text has been written yet.
+    written: bool,
+    /// Line breaks written since the last text.
+    newlines: usize,
+    /// Whitespace was skipped and the next text needs a space in front.
+    space: bool,
+}
+
+impl Writer {
+    fn new() -> Writer {
+        Writer {
+            out: String::new(),
+            open: vec![],
+            lists: vec![],
+            written: false,
+            newlines: 0,
+            space: false,
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.out.push_str(&escape_str_pcdata(text));
+        self.written = true;
+        self.newlines = 0;
+    }
+
+    fn push_link(&mut self, href: &str, text: &str) {
+        self.out.push_str(&format!(
+            "<a href=\"{}\">{}</a>",
+            escape_str_attribute(href),
+            escape_str_pcdata(text)
+        ));
+        self.written = true;
+        self.newlines = 0;
+    }
+
+    /// Writes text, turning timestamps into links that seek.
+    fn push_timestamps(&mut self, text: &str) {
+        let mut last = 0;
+        for (start, end, time) in find_timestamps(text) {
+            self.push_text(&text[last..start]);
+            self.push_link(&timestamp_href(time), &text[start..end]);
+            last = end;
+        }
+        self.push_text(&text[last..]);
+    }
+
+    /// Writes text, collapsing whitespace like a browser would, and turning bare URLs and
+    /// timestamps into links.
+    fn text(&mut self, text: &str) {
+        let text = decode_entities(text);
+        let mut collapsed = String::new();
+        let mut started = self.written && self.newlines == 0;
+        for c in text.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                self.space = true;
+                continue;
+            }
+            if self.space && started {
+                collapsed.push(' ');
+            }
+            self.space = false;
+            started = true;
+            collapsed.push(c);
+        }
+
+        if self.open.contains(&"a") {
+            self.push_text(&collapsed);
+            return;
+        }
+
+        let mut last = 0;
+        let urls: Vec<(usize, usize)> = URL
+            .find_iter(&collapsed)
+            .map(|url| {
+                let trimmed = url
+                    .as_str()
+                    .trim_end_matches(&['.', ',', ';', ':', '!', '?', ')'][..]);
+                (url.start(), url.start() + trimmed.len())
+            })
+            .collect();
+        for (start, end) in urls {
+            self.push_timestamps(&collapsed[last..start]);
+            self.push_link(&collapsed[start..end], &collapsed[start..end]);
+            last = end;
+        }
+        self.push_timestamps(&collapsed[last..]);
+    }
+
+    /// Makes sure the text so far ends with at least `count` line breaks.
+    fn line_breaks(&mut self, count: usize) {
+        self.space = false;
+        if !self.written {
+            return;
+        }
+        while self.newlines < count {
+            self.out.push('\n');
+            self.newlines += 1;
+        }
+    }
+
+    fn open_tag(&mut self, tag: &'static str, attributes: &str) {
+        // Keep the space before the tag, rather than at the start of its text.
+        if self.space && self.written && self.newlines == 0 {
+            self.out.push(' ');
+            self.space = false;
+        }
+        self.out.push_str(&format!("<{}{}>", tag, attributes));
+        self.open.push(tag);
+    }
+
+    /// Closes a tag, and anything opened inside it that wasn't closed.
+    fn close_tag(&mut self, tag: &str) {
+        if let Some(pos) = self.open.iter().rposition(|open| *open == tag) {
+            for open in self.open.drain(pos..).rev() {
+                self.out.push_str(&format!("</{}>", open));
+            }
+        }
+    }
+
+    fn start_element(&mut self, name: &str, attributes: &str) {
+        match name {
+            "b" | "strong" => self.open_tag("b", ""),
+            "i" | "em" | "cite" => self.open_tag("i", ""),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.line_breaks(2);
+                self.open_tag("b", "");
+            }
+            "a" if !self.open.contains(&"a") => {
+                let href = HREF
+                    .captures(attributes)
+                    .and_then(|caps| caps.get(1).or_else(|| caps.get(2)).or_else(|| caps.get(3)))
+                    .and_then(|href| safe_href(href.as_str()));
+                if let Some(href) = href {
+                    let attribute = format!(" href=\"{}\"", escape_str_attribute(&href));
+                    self.open_tag("a", &attribute);
+                }
+            }
+            "br" => {
+                self.space = false;
+                if self.written {
+                    self.out.push('\n');
+                    self.newlines += 1;
+                }
+            }
+            "p" | "div" | "blockquote" | "pre" | "table" | "tr" => self.line_breaks(2),
+            "ul" => {
+                self.line_breaks(1);
+                self.lists.push(None);
+            }
+            "ol" => {
+                self.line_breaks(1);
+                self.lists.push(Some(0));
+            }
+            "li" => {
+                self.line_breaks(1);
+                let indent = "    ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(count)) => {
+                        *count += 1;
+                        format!("{}{}. ", indent, count)
+                    }
+                    _ => format!("{}\u{2022} ", indent),
+                };
+                self.push_text(&marker);
+            }
+            _ => {}
+        }
+    }
+
+    fn end_element(&mut self, name: &str) {
+        match name {
+            "b" | "strong" => self.close_tag("b"),
+            "i" | "em" | "cite" => self.close_tag("i"),
+            "a" => self.close_tag("a"),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_tag("b");
+                self.line_breaks(2);
+            }
+            "p" | "div" | "blockquote" | "pre" | "table" | "tr" => self.line_breaks(2),
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.line_breaks(2);
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> String {
+        let trimmed = self.out.trim_end().len();
+        self.out.truncate(trimmed);
+        for open in self.open.drain(..).rev() {
+            self.out.push_str(&format!("</{}>", open));
+        }
+        self.out
+    }
+}
+
+/// Turns show notes into the subset of Pango markup we show: bold, italics, links, line breaks
+/// and lists. Everything else is dropped, and text is escaped. Notes without any tags are taken
+/// to be plain text, where line breaks are kept.
+pub fn to_markup(notes: &str) -> String {
+    let mut writer = Writer::new();
+
+    if !TAG.is_match(notes) {
+        for line in notes.trim().lines() {
+            if line.trim().is_empty() {
+                writer.line_breaks(2);
+            } else {
+                writer.line_breaks(1);
+                writer.text(line);
+            }
+        }
+        return writer.finish();
+    }
+
+    // Elements whose content isn't text, until their end tag.
+    let mut skip: Option<String> = None;
+    let mut last = 0;
+    for caps in TAG.captures_iter(notes) {
+        let whole = caps.get(0).unwrap();
+        if skip.is_none() {
+            writer.text(&notes[last..whole.start()]);
+        }
+        last = whole.end();
+
+        let name = match caps.get(2) {
+            Some(name) => name.as_str().to_ascii_lowercase(),
+            // A comment.
+            None => continue,
+        };
+        let closing = &caps[1] == "/";
+
+        if let Some(skipping) = &skip {
+            if closing && *skipping == name {
+                skip = None;
+            }
+        } else if closing {
+            writer.end_element(&name);
+        } else if name == "script" || name == "style" {
+            skip = Some(name);
+        } else {
+            writer.start_element(&name, &caps[3]);
+        }
+    }
+    if skip.is_none() {
+        writer.text(&notes[last..]);
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_formatting() {
+        assert_eq!(
+            to_markup(
+                "<p>In this <strong>episode</strong>, we <em>talk</em> about \
+                 <a href=\"https://example.com/?a=1&amp;b=2\" target=\"_blank\">things</a>.</p>\
+                 <p>Second<br/>line &amp; more&#8230;</p>"
+            ),
+            "In this <b>episode</b>, we <i>talk</i> about \
+             <a href=\"https://example.com/?a=1&amp;b=2\">things</a>.\n\n\
+             Second\nline &amp; more\u{2026}"
+        );
+    }
+
+    #[test]
+    fn formats_lists() {
+        assert_eq!(
+            to_markup(
+                "<p>Links:</p><ul><li>One</li><li>Two</li></ul><ol><li>A</li><li>B</li></ol>"
+            ),
+            "Links:\n\n\u{2022} One\n\u{2022} Two\n\n1. A\n2. B"
+        );
+    }
+
+    #[test]
+    fn drops_unsafe_markup() {
+        assert_eq!(
+            to_markup(
+                "<div onclick=\"evil()\"><script>alert(1)</script><style>p { }</style>\
+                 <a href=\"javascript:alert(1)\">click</a> <img src=\"x.png\"> \
+                 <span foreground=\"red\">text</span> 1 < 2<!-- hidden --></div><b>unclosed"
+            ),
+            "click text 1 &lt; 2\n\n<b>unclosed</b>"
+        );
+    }
+
+    #[test]
+    fn links_timestamps() {
+        assert_eq!(
+            to_markup(
+                "<p>00:00 Intro<br>(12:34) Topic<br>1:02:03 End</p>\
+                 <p>Live at 10:30 am, 123:45, 1:2:3 and https://example.com/12:34</p>\
+                 <p><a href=\"https://example.com/\">Out at 5:00</a></p>"
+            ),
+            "<a href=\"pyrocast-time:0\">00:00</a> Intro\n\
+             (<a href=\"pyrocast-time:754000\">12:34</a>) Topic\n\
+             <a href=\"pyrocast-time:3723000\">1:02:03</a> End\n\n\
+             Live at 10:30 am, 123:45, 1:2:3 and \
+             <a href=\"https://example.com/12:34\">https://example.com/12:34</a>\n\n\
+             <a href=\"https://example.com/\">Out at 5:00</a>"
+        );
+    }
+
+    #[test]
+    fn links_plain_text() {
+        assert_eq!(
+            to_markup("Notes at https://example.com/notes.\n\nThanks & bye\nfor now"),
+            "Notes at <a href=\"https://example.com/notes\">https://example.com/notes</a>.\n\n\
+             Thanks &amp; bye\nfor now"
+        );
+    }
+}
@@ -1,6 +1,15 @@
-use state::Image;
+use state::{Image, TranscriptLink};
 use std::cmp::{Ord, Ordering};
+use std::path::PathBuf;
 use std::sync::Arc;
+use surf::url::Url;
+
+/// Whether a channel pk (or search query) is the URL of a feed, rather than an iTunes id.
+pub fn is_feed_url(pk: &str) -> bool {
+    Url::parse(pk)
+        .map(|url| matches!(url.scheme(), "http" | "https"))
+        .unwrap_or(false)
+}
 
 #[derive(Debug)]
 pub enum Query {
@@ -19,16 +28,52 @@ pub enum Query {
         image: Arc<Image>,
         associated_query: Option<usize>,
     },
+    Download {
+        episode_pk: String,
+        url: String,
+        path: PathBuf,
+    },
+    /// Loads an episode's podcast:chapters file.
+    Chapters {
+        episode_pk: String,
+        url: String,
+    },
+    /// Reads the chapters in a downloaded episode's tags.
+    EmbeddedChapters {
+        episode_pk: String,
+        path: PathBuf,
+    },
+    /// Loads an episode's podcast:transcript.
+    Transcript {
+        episode_pk: String,
+        link: TranscriptLink,
+    },
 }
 
 impl Query {
+    /// Loads a channel from iTunes, or straight from its feed if it is keyed by its RSS URL.
+    pub fn channel(pk: String) -> Query {
+        if is_feed_url(&pk) {
+            Query::Rss {
+                url: pk.clone(),
+                pk,
+            }
+        } else {
+            Query::ItunesLookup { pk }
+        }
+    }
+
     pub fn priority(&self) -> usize {
         match self {
             Query::Rss { .. } => 3000,
+            Query::Chapters { .. } => 2500,
+            Query::Transcript { .. } => 2400,
+            Query::EmbeddedChapters { .. } => 2300,
             Query::ItunesLookup { .. } => 2000,
             Query::ItunesSearch { .. } => 1001,
             Query::ItunesChart { .. } => 1000,
             Query::Image { image, .. } if !image.loaded() => 500,
+            Query::Download { .. } => 100,
             Query::Image { .. } => 1,
         }
     }
@@ -1,70 +1,242 @@
+use crate::chapters;
+use crate::element::{self, Element, Ns};
+use crate::enclosures;
+use crate::http::{self, Redirected};
+use crate::notes;
+use crate::query::is_feed_url;
+use crate::transcript;
 use regex::Regex;
-use serde::Deserialize;
-use serde_xml_rs::from_reader;
-use state::{ChannelCore, ChannelDetail, CurrentState, Episode, StateAction, StateError};
+use state::{
+    ChannelCore, ChannelDetail, Chapter, CurrentState, Enclosure, Episode, EpisodeType, FeedCache,
+    Funding, Location, Person, ShowType, StateAction, StateError, TranscriptLink,
+};
+use std::io::{Error as IoError, ErrorKind};
+use std::time::{SystemTime, UNIX_EPOCH};
+use surf::http_types::StatusCode;
+
+/// The text of an Atom text construct, which may be plain text, escaped HTML or XHTML.
+fn atom_text(parent: &Element, name: &str) -> Option<String> {
+    parent
+        .children(Ns::Atom, name)
+        .map(|child| child.deep_text().trim().to_owned())
+        .find(|text| !text.is_empty())
+}
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct RssImage {
-    #[serde(default)]
-    pub href: String,
+/// The href of the first Atom link with a relation. Links without one are alternates.
+fn atom_link(parent: &Element, rel: &str) -> Option<String> {
+    parent
+        .children(Ns::Atom, "link")
+        .find(|link| link.attr("rel").as_deref().unwrap_or("alternate") == rel)
+        .and_then(|link| link.attr("href"))
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct RssImageLink {
-    #[serde(default)]
-    pub url: String,
+/// A season or episode number. podcast:season and podcast:episode win over the iTunes tags.
+fn number(item: &Element, name: &str) -> Option<u32> {
+    let parse = |text: String| text.trim().parse().ok().filter(|number| *number > 0);
+    item.child_text(Ns::Podcast, name)
+        .and_then(parse)
+        .or_else(|| item.child_text(Ns::Itunes, name).and_then(parse))
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct RssEnclosure {
-    #[serde(default)]
-    pub url: String,
+fn season_name(item: &Element) -> Option<String> {
+    item.child(Ns::Podcast, "season")
+        .and_then(|season| season.attr("name"))
+        .filter(|name| !name.trim().is_empty())
+}
+
+fn episode_type(item: &Element) -> EpisodeType {
+    item.child_text(Ns::Itunes, "episodeType")
+        .map(|episode_type| EpisodeType::parse(&episode_type))
+        .unwrap_or_default()
+}
+
+/// The podcast:person tags of a channel or item. People without a role are hosts.
+fn persons(parent: &Element) -> Vec<Person> {
+    parent
+        .children(Ns::Podcast, "person")
+        .filter_map(|person| {
+            let name = person.deep_text().trim().to_owned();
+            if name.is_empty() {
+                return None;
+            }
+
+            Some(Person {
+                name,
+                role: person
+                    .attr("role")
+                    .map(|role| role.to_lowercase())
+                    .unwrap_or_else(|| "host".to_owned()),
+                group: person
+                    .attr("group")
+                    .map(|group| group.to_lowercase())
+                    .unwrap_or_else(|| "cast".to_owned()),
+                image: person.attr("img"),
+                href: person.attr("href"),
+            })
+        })
+        .collect()
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+fn location(parent: &Element) -> Option<Location> {
+    let location = parent.child(Ns::Podcast, "location")?;
+    let name = location.deep_text().trim().to_owned();
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(Location {
+        name,
+        geo: location.attr("geo"),
+        osm: location.attr("osm"),
+    })
+}
+
+fn funding(channel: &Element) -> Vec<Funding> {
+    channel
+        .children(Ns::Podcast, "funding")
+        .filter_map(|funding| {
+            Some(Funding {
+                url: funding.attr("url")?,
+                title: funding.deep_text().trim().to_owned(),
+            })
+        })
+        .collect()
+}
+
+fn locked(channel: &Element) -> bool {
+    channel
+        .child_text(Ns::Podcast, "locked")
+        .map_or(false, |locked| locked.eq_ignore_ascii_case("yes"))
+}
+
+#[derive(Debug, Default)]
 pub struct RssEpisode {
-    #[serde(rename = "guid")]
     /// Static identifier. Enclosure url is fallback.
     pub pk: Option<String>,
 
-    #[serde(rename = "itunes-title")]
     /// Fallback is fallback_title.
     pub title: Option<String>,
-
-    #[serde(rename = "title", default)]
     pub fallback_title: String,
 
-    #[serde(rename = "link", default)]
     pub url: String,
-
-    #[serde(rename = "pubDate", default)]
     pub date: String,
 
-    #[serde(rename = "itunes-summary", default)]
     pub description: Option<String>,
-
-    #[serde(rename = "description"default)]
     pub fallback_description: String,
+    /// Show notes, as markup once parsed.
+    pub notes: String,
 
-    #[serde(rename = "itunes-duration", default)]
     pub duration: String,
+    pub image: Option<String>,
+    /// Every variant of the episode, with the main enclosure first.
+    pub enclosures: Vec<Enclosure>,
+
+    pub chapters: Vec<Chapter>,
+    pub chapters_url: Option<String>,
+    pub transcript: Option<TranscriptLink>,
 
-    #[serde(rename = "itunes-image")]
-    pub image: Option<RssImage>,
+    pub season: Option<u32>,
+    pub season_name: Option<String>,
+    pub number: Option<u32>,
+    pub episode_type: EpisodeType,
 
-    #[serde(default)]
-    pub enclosure: RssEnclosure,
+    pub persons: Vec<Person>,
+    pub location: Option<Location>,
 }
 
 impl RssEpisode {
+    fn from_element(item: &Element) -> RssEpisode {
+        RssEpisode {
+            pk: item.child_text(Ns::None, "guid"),
+            title: item.child_text(Ns::Itunes, "title"),
+            fallback_title: item.child_text(Ns::None, "title").unwrap_or_default(),
+            url: item.child_text(Ns::None, "link").unwrap_or_default(),
+            date: item.child_text(Ns::None, "pubDate").unwrap_or_default(),
+            description: item.child_text(Ns::Itunes, "summary"),
+            fallback_description: item
+                .child_text(Ns::None, "description")
+                .or_else(|| item.child_text(Ns::Content, "encoded"))
+                .unwrap_or_default(),
+            notes: item
+                .child_text(Ns::Content, "encoded")
+                .or_else(|| item.child_text(Ns::None, "description"))
+                .or_else(|| item.child_text(Ns::Itunes, "summary"))
+                .unwrap_or_default(),
+            duration: item.child_text(Ns::Itunes, "duration").unwrap_or_default(),
+            image: item
+                .child(Ns::Itunes, "image")
+                .and_then(|image| image.attr("href"))
+                .or_else(|| {
+                    item.child(Ns::Media, "thumbnail")
+                        .and_then(|thumbnail| thumbnail.attr("url"))
+                }),
+            enclosures: enclosures::parse(item),
+            chapters: chapters::parse_podlove(item),
+            chapters_url: item
+                .child(Ns::Podcast, "chapters")
+                .and_then(|chapters| chapters.attr("url")),
+            transcript: transcript::best_link(item),
+            season: number(item, "season"),
+            season_name: season_name(item),
+            number: number(item, "episode"),
+            episode_type: episode_type(item),
+            persons: persons(item),
+            location: location(item),
+        }
+    }
+
+    fn from_atom(entry: &Element) -> RssEpisode {
+        RssEpisode {
+            pk: entry.child_text(Ns::Atom, "id"),
+            title: entry.child_text(Ns::Itunes, "title"),
+            fallback_title: atom_text(entry, "title").unwrap_or_default(),
+            url: atom_link(entry, "alternate").unwrap_or_default(),
+            date: entry
+                .child_text(Ns::Atom, "published")
+                .or_else(|| entry.child_text(Ns::Atom, "updated"))
+                .unwrap_or_default(),
+            description: entry.child_text(Ns::Itunes, "summary"),
+            fallback_description: atom_text(entry, "summary")
+                .or_else(|| atom_text(entry, "content"))
+                .unwrap_or_default(),
+            notes: atom_text(entry, "content")
+                .or_else(|| atom_text(entry, "summary"))
+                .or_else(|| entry.child_text(Ns::Itunes, "summary"))
+                .unwrap_or_default(),
+            duration: entry.child_text(Ns::Itunes, "duration").unwrap_or_default(),
+            image: entry
+                .child(Ns::Itunes, "image")
+                .and_then(|image| image.attr("href"))
+                .or_else(|| {
+                    entry
+                        .child(Ns::Media, "thumbnail")
+                        .and_then(|thumbnail| thumbnail.attr("url"))
+                }),
+            enclosures: enclosures::parse_atom(entry),
+            chapters: chapters::parse_podlove(entry),
+            chapters_url: entry
+                .child(Ns::Podcast, "chapters")
+                .and_then(|chapters| chapters.attr("url")),
+            transcript: transcript::best_link(entry),
+            season: number(entry, "season"),
+            season_name: season_name(entry),
+            number: number(entry, "episode"),
+            episode_type: episode_type(entry),
+            persons: persons(entry),
+            location: location(entry),
+        }
+    }
+
     pub fn pk(&self) -> String {
-        self.pk
-            .clone()
-            .unwrap_or_else(|| self.enclosure.url.clone())
+        self.pk.clone().unwrap_or_else(|| self.enclosure_url())
+    }
+
+    /// The URL of the main enclosure.
+    pub fn enclosure_url(&self) -> String {
+        self.enclosures
+            .first()
+            .map(|enclosure| enclosure.url.clone())
+            .unwrap_or_default()
     }
 
     pub fn title(&self) -> String {
@@ -89,57 +261,131 @@ impl RssEpisode {
             .with_url(self.url.to_owned())
             .with_date(self.date.to_owned())
             .with_description(self.description())
+            .with_notes(self.notes.clone())
             .with_duration(self.duration.clone())
-            .with_image(self.image.as_ref().map(|img| img.href.clone()))
-            .with_audio(self.enclosure.url.to_owned())
+            .with_image(self.image.clone())
+            .with_audio(self.enclosure_url())
+            .with_enclosures(self.enclosures.clone())
+            .with_chapters(self.chapters.clone())
+            .with_chapters_url(self.chapters_url.clone())
+            .with_transcript_link(self.transcript.clone())
+            .with_season(self.season)
+            .with_season_name(self.season_name.clone())
+            .with_number(self.number)
+            .with_episode_type(self.episode_type)
+            .with_persons(self.persons.clone())
+            .with_location(self.location.clone())
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Default)]
 pub struct RssChannel {
-    #[serde(default)]
     pub title: String,
 
-    #[serde(rename = "itunes-summary")]
     pub description: Option<String>,
-
-    #[serde(rename = "description", default)]
     pub fallback_description: String,
 
-    #[serde(default)]
     pub link: String,
-
-    #[serde(rename = "itunes-author", default)]
     pub author: String,
 
-    #[serde(rename = "itunes-image")]
-    pub image_rss: Option<RssImage>,
+    pub image_rss: Option<String>,
+    pub image_rss_fallback: Option<String>,
+
+    pub show_type: ShowType,
 
-    #[serde(rename = "image")]
-    pub image_rss_fallback: Option<RssImageLink>,
+    pub guid: Option<String>,
+    pub locked: bool,
+    pub persons: Vec<Person>,
+    pub funding: Vec<Funding>,
+    pub location: Option<Location>,
+
+    /// Where the feed says it moved to, from itunes:new-feed-url.
+    pub new_feed_url: Option<String>,
 
-    #[serde(rename = "item", default)]
     pub episodes: Vec<RssEpisode>,
 
-    #[serde(default)]
     pub pk: String,
-
-    #[serde(default)]
     pub self_url: String,
 }
 
 impl RssChannel {
+    fn from_element(channel: &Element) -> RssChannel {
+        RssChannel {
+            title: channel.child_text(Ns::None, "title").unwrap_or_default(),
+            description: channel.child_text(Ns::Itunes, "summary"),
+            fallback_description: channel
+                .child_text(Ns::None, "description")
+                .unwrap_or_default(),
+            link: channel.child_text(Ns::None, "link").unwrap_or_default(),
+            author: channel.child_text(Ns::Itunes, "author").unwrap_or_default(),
+            image_rss: channel
+                .child(Ns::Itunes, "image")
+                .and_then(|image| image.attr("href")),
+            image_rss_fallback: channel
+                .child(Ns::None, "image")
+                .and_then(|image| image.child_text(Ns::None, "url")),
+            show_type: channel
+                .child_text(Ns::Itunes, "type")
+                .map(|show_type| ShowType::parse(&show_type))
+                .unwrap_or_default(),
+            guid: channel.child_text(Ns::Podcast, "guid"),
+            locked: locked(channel),
+            persons: persons(channel),
+            funding: funding(channel),
+            location: location(channel),
+            new_feed_url: channel.child_text(Ns::Itunes, "new-feed-url"),
+            episodes: channel
+                .children(Ns::None, "item")
+                .map(RssEpisode::from_element)
+                .collect(),
+            pk: String::new(),
+            self_url: String::new(),
+        }
+    }
+
+    fn from_atom(feed: &Element) -> RssChannel {
+        RssChannel {
+            title: atom_text(feed, "title").unwrap_or_default(),
+            description: feed.child_text(Ns::Itunes, "summary"),
+            fallback_description: atom_text(feed, "subtitle").unwrap_or_default(),
+            link: atom_link(feed, "alternate").unwrap_or_default(),
+            author: feed
+                .child_text(Ns::Itunes, "author")
+                .or_else(|| {
+                    feed.child(Ns::Atom, "author")
+                        .and_then(|author| author.child_text(Ns::Atom, "name"))
+                })
+                .unwrap_or_default(),
+            image_rss: feed
+                .child(Ns::Itunes, "image")
+                .and_then(|image| image.attr("href")),
+            image_rss_fallback: feed
+                .child_text(Ns::Atom, "logo")
+                .or_else(|| feed.child_text(Ns::Atom, "icon")),
+            show_type: feed
+                .child_text(Ns::Itunes, "type")
+                .map(|show_type| ShowType::parse(&show_type))
+                .unwrap_or_default(),
+            guid: feed.child_text(Ns::Podcast, "guid"),
+            locked: locked(feed),
+            persons: persons(feed),
+            funding: funding(feed),
+            location: location(feed),
+            new_feed_url: feed.child_text(Ns::Itunes, "new-feed-url"),
+            episodes: feed
+                .children(Ns::Atom, "entry")
+                .map(RssEpisode::from_atom)
+                .collect(),
+            pk: String::new(),
+            self_url: String::new(),
+        }
+    }
+
     pub fn image_rss(&self) -> String {
         self.image_rss
-            .as_ref()
-            .map(|img| img.href.to_owned())
-            .unwrap_or_else(|| {
-                self.image_rss_fallback
-                    .as_ref()
-                    .map(|img| img.url.to_owned())
-                    .unwrap_or_default()
-            })
+            .clone()
+            .or_else(|| self.image_rss_fallback.clone())
+            .unwrap_or_default()
     }
 
     pub fn to_channel_core(&self, current: &CurrentState) -> ChannelCore {
@@ -164,6 +410,12 @@ impl RssChannel {
                     .unwrap_or_else(|| self.fallback_description.clone()),
             )
             .with_link(self.link.clone())
+            .with_show_type(self.show_type)
+            .with_guid(self.guid.clone())
+            .with_locked(self.locked)
+            .with_persons(self.persons.clone())
+            .with_funding(self.funding.clone())
+            .with_location(self.location.clone())
             .with_episodes(&self.episodes.iter().map(|ep| ep.pk()).collect::<Vec<_>>())
     }
 
@@ -191,26 +443,100 @@ impl RssChannel {
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug)]
 pub struct Rss {
     pub channel: RssChannel,
 }
 
+/// What fetching a feed found.
+#[derive(Debug)]
+pub enum Fetched {
+    /// The feed, and validators for checking it again.
+    Feed(Box<Rss>, FeedCache),
+    /// The feed hasn't changed since it was cached.
+    NotModified(FeedCache),
+    /// The feed is gone for good (HTTP 410).
+    Gone,
+}
+
 impl Rss {
-    pub async fn fetch(url: &str, pk: &str) -> Result<Rss, StateError> {
-        let pk = pk.to_owned();
-        let url = url.to_owned();
+    /// Downloads and parses a feed, unless `cache` shows it hasn't changed. Also returns where the
+    /// feed lives now, if it permanently redirected.
+    pub async fn fetch(
+        url: &str,
+        pk: &str,
+        cache: Option<&FeedCache>,
+    ) -> Result<(Fetched, Option<String>), StateError> {
+        let mut headers = vec![];
+        if let Some(etag) = cache.and_then(|cache| cache.etag.as_ref()) {
+            headers.push(("if-none-match", etag.clone()));
+        }
+        if let Some(last_modified) = cache.and_then(|cache| cache.last_modified.as_ref()) {
+            headers.push(("if-modified-since", last_modified.clone()));
+        }
+
+        let Redirected {
+            response: res,
+            moved_to,
+        } = http::get(url, &headers).await?;
 
-        let mut res = surf::get(&url).await?;
-        let body = res.body_string().await?;
+        if res.status() == StatusCode::Gone {
+            return Ok((Fetched::Gone, moved_to));
+        }
+
+        let header = |name: &str| {
+            res.header(name)
+                .map(|header| header.last().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let mut next_cache = FeedCache {
+            etag: header("etag"),
+            last_modified: header("last-modified"),
+            checked: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs() as i64)
+                .unwrap_or(0),
+        };
+
+        if res.status() == StatusCode::NotModified {
+            if let Some(cache) = cache {
+                // Servers don't have to repeat the validators.
+                next_cache.etag = next_cache.etag.or_else(|| cache.etag.clone());
+                next_cache.last_modified = next_cache
+                    .last_modified
+                    .or_else(|| cache.last_modified.clone());
+            }
+
+            return Ok((Fetched::NotModified(next_cache), moved_to));
+        }
 
-        // HACK: Namespaces are problematic, as our serde parser considers "<abc:tag>" and "<abc>"
-        // to be the same. Convert "<abc:" and "</abc>" to "<abc-" and "</abc-"
-        let re = Regex::new(r"(?P<tag></?\w*):").unwrap();
-        let body = re.replace_all(&body, "$tag-");
+        let mut res = http::success(url, res)?;
+        let body = res.body_bytes().await?;
+        let feed = Rss::parse(&body, pk, moved_to.as_deref().unwrap_or(url))?;
 
-        let mut feed: Rss = from_reader(body.as_bytes())?;
+        Ok((Fetched::Feed(Box::new(feed), next_cache), moved_to))
+    }
+
+    /// Parses the body of an RSS or Atom feed. `pk` is the channel it belongs to and `url` is where
+    /// it came from.
+    pub fn parse(body: &[u8], pk: &str, url: &str) -> Result<Rss, StateError> {
+        let root = element::parse(body)?;
+        let channel = if root.is(Ns::Atom, "feed") {
+            Some(RssChannel::from_atom(&root))
+        } else if root.is(Ns::None, "rss") {
+            root.child(Ns::None, "channel")
+                .map(RssChannel::from_element)
+        } else {
+            None
+        };
+        let mut feed = Rss {
+            channel: channel.ok_or_else(|| {
+                StateError::from(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Not an RSS or Atom feed",
+                ))
+            })?,
+        };
 
         // HACK: itunes_summary isn't allowed to have HTML, but that doesn't stop
         // some podcasts.
@@ -231,11 +557,230 @@ impl Rss {
                 *description = detag.replace_all(description, "").to_string();
             }
             ep.fallback_description = detag.replace_all(&ep.fallback_description, "").to_string();
+            ep.notes = notes::to_markup(&ep.notes);
         }
 
-        feed.channel.pk = pk;
-        feed.channel.self_url = url;
+        feed.channel.pk = pk.to_owned();
+        feed.channel.self_url = url.to_owned();
+
+        // Some feeds point itunes:new-feed-url back at themselves.
+        feed.channel.new_feed_url = feed
+            .channel
+            .new_feed_url
+            .take()
+            .filter(|new_feed_url| is_feed_url(new_feed_url) && new_feed_url != url);
 
         Ok(feed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &[u8]) -> RssChannel {
+        Rss::parse(source, "pk", "https://example.com/feed")
+            .unwrap()
+            .channel
+    }
+
+    #[test]
+    fn libsyn() {
+        let channel = parse(include_bytes!("../fixtures/libsyn.xml"));
+        assert_eq!(channel.title, "The Example Show");
+        assert_eq!(channel.author, "Example Media");
+        assert_eq!(
+            channel.image_rss(),
+            "https://ssl-static.libsyn.com/p/assets/example/cover.jpg"
+        );
+        assert_eq!(
+            channel.description.as_deref(),
+            Some("A weekly show about examples.")
+        );
+        assert_eq!(channel.episodes.len(), 2);
+
+        let episode = &channel.episodes[0];
+        assert_eq!(episode.pk(), "b6a1c1e4-2b1b-4b8e-9f2a-3f3a2f6b1a02");
+        assert_eq!(episode.title(), "Colons, Everywhere");
+        assert_eq!(episode.duration, "01:02:03");
+        assert_eq!(episode.description(), "At 10:30 we talk about the notes.");
+        assert_eq!(
+            episode.notes,
+            "At <a href=\"pyrocast-time:630000\">10:30</a> we talk about \
+             <a href=\"https://example.com/notes\">the notes</a>."
+        );
+        assert_eq!(
+            episode.enclosure_url(),
+            "https://traffic.libsyn.com/secure/example/ep2.mp3?dest-id=1"
+        );
+        assert_eq!(
+            episode.image.as_deref(),
+            Some("https://ssl-static.libsyn.com/p/assets/example/2.jpg")
+        );
+
+        assert_eq!(channel.episodes[1].title(), "Episode 1");
+    }
+
+    #[test]
+    fn other_prefix() {
+        let channel = parse(include_bytes!("../fixtures/other-prefix.xml"));
+        assert_eq!(channel.title, "Prefixed & Proud");
+        assert_eq!(channel.author, "Someone\u{a0}Else");
+        assert_eq!(
+            channel.image_rss(),
+            "https://prefixed.example.org/cover.png"
+        );
+        assert_eq!(
+            channel.fallback_description,
+            "Uses \u{201c}it\u{201d} instead of \u{201c}itunes\u{201d}\u{2026}"
+        );
+
+        let episode = &channel.episodes[0];
+        assert_eq!(episode.duration, "45:00");
+        assert_eq!(episode.description(), "Show notes: none.");
+    }
+
+    #[test]
+    fn undeclared_prefix() {
+        let channel = parse(include_bytes!("../fixtures/wordpress-undeclared.xml"));
+        assert_eq!(channel.author, "Blog Author");
+        assert_eq!(
+            channel.image_rss(),
+            "https://blog.example.net/wp-content/uploads/cover.jpg"
+        );
+        assert_eq!(channel.episodes[0].duration, "12:34");
+        assert_eq!(
+            channel.episodes[0].description(),
+            "Listen: it\u{2019}s here."
+        );
+    }
+
+    #[test]
+    fn podcast_namespace() {
+        let channel = parse(include_bytes!("../fixtures/podcast-namespace.xml"));
+        let episode = &channel.episodes[0];
+        assert_eq!(episode.pk(), "nerds-42");
+        assert_eq!(episode.enclosure_url(), "https://nerds.example.com/42.mp3");
+        assert_eq!(
+            episode.image.as_deref(),
+            Some("https://nerds.example.com/42.jpg")
+        );
+        assert_eq!(
+            episode.chapters_url.as_deref(),
+            Some("https://nerds.example.com/42.json")
+        );
+        assert_eq!(
+            episode.transcript.as_ref().map(|link| link.url.as_str()),
+            Some("https://nerds.example.com/42.vtt")
+        );
+
+        assert_eq!(channel.show_type, ShowType::Serial);
+        assert_eq!(episode.season, Some(2));
+        assert_eq!(episode.season_name.as_deref(), Some("Rebuilding"));
+        assert_eq!(episode.number, Some(5));
+        assert_eq!(episode.episode_type, EpisodeType::Bonus);
+
+        assert_eq!(episode.persons.len(), 1);
+        assert_eq!(episode.persons[0].name, "Linus");
+        assert!(episode.persons[0].is_guest());
+        assert_eq!(
+            episode.persons[0].image.as_deref(),
+            Some("https://nerds.example.com/linus.jpg")
+        );
+
+        let trailer = &channel.episodes[1];
+        assert_eq!(trailer.season, Some(1));
+        assert_eq!(trailer.number, None);
+        assert_eq!(trailer.episode_type, EpisodeType::Trailer);
+
+        assert_eq!(
+            channel.guid.as_deref(),
+            Some("917393e3-1b1e-5cef-ace4-edaa54e1f810")
+        );
+        assert!(channel.locked);
+        assert_eq!(
+            channel.funding,
+            vec![Funding {
+                url: "https://nerds.example.com/donate".to_owned(),
+                title: "Support us".to_owned(),
+            }]
+        );
+        assert_eq!(
+            channel
+                .location
+                .as_ref()
+                .map(|location| location.name.as_str()),
+            Some("Austin, TX")
+        );
+        assert_eq!(channel.persons.len(), 2);
+        assert!(channel.persons[0].is_host());
+        assert_eq!(
+            channel.persons[0].href.as_deref(),
+            Some("https://nerds.example.com/ada")
+        );
+        assert_eq!(channel.persons[1].role, "producer");
+        assert_eq!(channel.persons[1].group, "audio post-production");
+        assert_eq!(
+            channel.new_feed_url.as_deref(),
+            Some("https://new.example.com/nerds.xml")
+        );
+    }
+
+    #[test]
+    fn new_feed_url_is_not_self() {
+        let channel = Rss::parse(
+            include_bytes!("../fixtures/podcast-namespace.xml"),
+            "pk",
+            "https://new.example.com/nerds.xml",
+        )
+        .unwrap()
+        .channel;
+        assert_eq!(channel.new_feed_url, None);
+    }
+
+    #[test]
+    fn podlove_chapters() {
+        let channel = parse(include_bytes!("../fixtures/podlove.xml"));
+        let chapters = &channel.episodes[0].chapters;
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[0].title, "Welcome");
+        assert_eq!(chapters[1].start, 187_500);
+        assert_eq!(
+            chapters[1].url.as_deref(),
+            Some("https://podlove.example.de/links")
+        );
+        assert_eq!(chapters[2].start, 3_723_000);
+    }
+
+    #[test]
+    fn atom() {
+        let channel = parse(include_bytes!("../fixtures/atom.xml"));
+        assert_eq!(channel.title, "Atomic Audio");
+        assert_eq!(channel.author, "Ada Atom");
+        assert_eq!(channel.link, "https://atomic.example.com/");
+        assert_eq!(channel.image_rss(), "https://atomic.example.com/logo.png");
+        assert_eq!(channel.fallback_description, "Podcasting, the Atom way.");
+        assert_eq!(channel.episodes.len(), 2);
+
+        let episode = &channel.episodes[0];
+        assert_eq!(episode.pk(), "tag:atomic.example.com,2020:2");
+        assert_eq!(episode.title(), "Second Entry");
+        assert_eq!(episode.url, "https://atomic.example.com/2");
+        assert_eq!(episode.date, "2020-10-05T09:00:00Z");
+        assert_eq!(episode.description(), "Rich notes for the second entry.");
+        assert_eq!(
+            episode.enclosure_url(),
+            "https://atomic.example.com/media/2.ogg"
+        );
+
+        let episode = &channel.episodes[1];
+        assert_eq!(episode.date, "2020-09-28T09:00:00Z");
+        assert_eq!(episode.description(), "Plain summary & more");
+    }
+
+    #[test]
+    fn not_rss() {
+        assert!(Rss::parse(b"<opml><body /></opml>", "pk", "url").is_err());
+        assert!(Rss::parse(b"<rss><channel>", "pk", "url").is_err());
+    }
+}
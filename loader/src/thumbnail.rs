@@ -0,0 +1,121 @@
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
+use state::{Image, Thumbnail};
+
+/// The sizes the UI shows artwork at: list rows, search results and Now Playing.
+const SIZES: [u32; 3] = [64, 200, 600];
+
+/// The quality of JPEG thumbnails, from 1 to 100.
+const JPEG_QUALITY: u8 = 85;
+
+/// Photos stay JPEGs, which are much smaller than PNGs. Everything else may have transparency.
+fn encode(image: &DynamicImage, size: u32, jpeg: bool) -> Option<Thumbnail> {
+    let (format, mimetype) = if jpeg {
+        (ImageOutputFormat::Jpeg(JPEG_QUALITY), "image/jpeg")
+    } else {
+        (ImageOutputFormat::Png, "image/png")
+    };
+
+    let mut data = vec![];
+    image
+        .thumbnail(size, size)
+        .write_to(&mut data, format)
+        .ok()?;
+
+    Some(Thumbnail {
+        size,
+        mimetype: String::from(mimetype),
+        data,
+    })
+}
+
+/// Makes a smaller copy of the image for each size it is bigger than.
+///
+/// This decodes the whole image, so it should not be run on the UI thread.
+pub fn thumbnails(data: &[u8]) -> Vec<Thumbnail> {
+    let image = match image::load_from_memory(data) {
+        Ok(image) => image,
+        Err(err) => {
+            eprintln!("Cannot make thumbnails: {}", err);
+            return vec![];
+        }
+    };
+    let (width, height) = image.dimensions();
+    let jpeg = image::guess_format(data).ok() == Some(ImageFormat::Jpeg);
+
+    SIZES
+        .iter()
+        .filter(|size| width.max(height) > **size)
+        .filter_map(|size| encode(&image, *size, jpeg))
+        .collect()
+}
+
+/// Adds thumbnails to a loaded image, off the async executor.
+pub async fn with_thumbnails(mut image: Image) -> Image {
+    if let Some(data) = image.data.take() {
+        let (data, thumbnails) = async_std::task::spawn_blocking(move || {
+            let thumbnails = thumbnails(&data);
+            (data, thumbnails)
+        })
+        .await;
+
+        image.data = Some(data);
+        image.thumbnails = thumbnails;
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn encoded(width: u32, height: u32, format: ImageOutputFormat) -> Vec<u8> {
+        let mut data = vec![];
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut data, format)
+            .unwrap();
+        data
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        encoded(width, height, ImageOutputFormat::Png)
+    }
+
+    #[test]
+    fn makes_smaller_copies() {
+        let thumbnails = thumbnails(&png(300, 300));
+        let sizes: Vec<_> = thumbnails.iter().map(|thumbnail| thumbnail.size).collect();
+        assert_eq!(sizes, vec![64, 200]);
+
+        let thumbnail = image::load_from_memory(&thumbnails[1].data).unwrap();
+        assert_eq!(thumbnail.dimensions(), (200, 200));
+    }
+
+    #[test]
+    fn keeps_jpegs_as_jpegs() {
+        let jpeg = thumbnails(&encoded(300, 300, ImageOutputFormat::Jpeg(90)));
+        assert_eq!(jpeg[0].mimetype, "image/jpeg");
+        assert_eq!(
+            image::guess_format(&jpeg[0].data).unwrap(),
+            ImageFormat::Jpeg
+        );
+
+        let png = thumbnails(&png(300, 300));
+        assert_eq!(png[0].mimetype, "image/png");
+    }
+
+    #[test]
+    fn keeps_aspect_ratio() {
+        let thumbnails = thumbnails(&png(100, 50));
+        assert_eq!(thumbnails.len(), 1);
+
+        let thumbnail = image::load_from_memory(&thumbnails[0].data).unwrap();
+        assert_eq!(thumbnail.dimensions(), (64, 32));
+    }
+
+    #[test]
+    fn ignores_garbage() {
+        assert!(thumbnails(b"not an image").is_empty());
+    }
+}
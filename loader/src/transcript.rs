@@ -0,0 +1,345 @@
+use crate::chapters::parse_npt;
+use crate::element::{Element, Ns};
+use crate::http;
+use crate::notes::decode_entities;
+use regex::Regex;
+use serde::Deserialize;
+use state::{Segment, StateError, TranscriptLink};
+use std::io::{Error as IoError, ErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Vtt,
+    Srt,
+    Html,
+}
+
+impl Format {
+    fn from_mimetype(mimetype: &str) -> Option<Format> {
+        match mimetype.split(';').next()?.trim() {
+            "application/json" => Some(Format::Json),
+            "text/vtt" => Some(Format::Vtt),
+            "application/srt" | "application/x-subrip" | "text/srt" => Some(Format::Srt),
+            "text/html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    fn from_url(url: &str) -> Option<Format> {
+        let path = url.split(&['?', '#'][..]).next()?;
+        match path.rsplit('.').next()?.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "vtt" => Some(Format::Vtt),
+            "srt" => Some(Format::Srt),
+            "html" | "htm" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    /// Guesses from the first few characters, for servers that send the wrong mimetype.
+    fn from_body(body: &str) -> Option<Format> {
+        let body = body.trim_start_matches('\u{feff}').trim_start();
+        if body.starts_with("WEBVTT") {
+            Some(Format::Vtt)
+        } else if body.starts_with('{') {
+            Some(Format::Json)
+        } else if body.starts_with('<') {
+            Some(Format::Html)
+        } else if body.contains("-->") {
+            Some(Format::Srt)
+        } else {
+            None
+        }
+    }
+}
+
+/// Picks the transcript to load from an item's podcast:transcript links. Timed formats are
+/// preferred over HTML.
+pub fn best_link(item: &Element) -> Option<TranscriptLink> {
+    item.children(Ns::Podcast, "transcript")
+        .filter_map(|transcript| {
+            let link = TranscriptLink {
+                url: transcript.attr("url")?,
+                mimetype: transcript.attr("type"),
+            };
+            let format = link
+                .mimetype
+                .as_deref()
+                .and_then(Format::from_mimetype)
+                .or_else(|| Format::from_url(&link.url));
+            let rank = match format {
+                Some(Format::Json) => 0,
+                Some(Format::Vtt) => 1,
+                Some(Format::Srt) => 2,
+                Some(Format::Html) => 3,
+                None => 4,
+            };
+
+            Some((rank, link))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, link)| link)
+}
+
+/// Joins lines and collapses runs of whitespace.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parses SRT and WebVTT, which only differ in details.
+fn parse_cues(body: &str) -> Vec<Segment> {
+    let voice = Regex::new(r"<v(?:\.[^\s>]*)?\s+([^>]+)>").unwrap();
+    let detag = Regex::new(r"<[^>]+>").unwrap();
+    let body = body.replace("\r\n", "\n").replace('\r', "\n");
+
+    body.split("\n\n")
+        .filter_map(|block| {
+            let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+            let timing = lines.next()?;
+            let mut timing = timing.split("-->");
+            let start = parse_npt(&timing.next()?.trim().replace(',', "."))?;
+            let end = timing
+                .next()
+                .and_then(|end| end.split_whitespace().next())
+                .and_then(|end| parse_npt(&end.replace(',', ".")));
+
+            let text = lines.collect::<Vec<_>>().join("\n");
+            let speaker = voice
+                .captures(&text)
+                .map(|captures| decode_entities(captures[1].trim()));
+            let text = collapse_whitespace(&decode_entities(&detag.replace_all(&text, "")));
+
+            Some(Segment {
+                start,
+                end,
+                speaker,
+                text,
+            })
+        })
+        .filter(|segment| !segment.text.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSegment {
+    speaker: Option<String>,
+    /// Seconds from the start of the episode.
+    start_time: f64,
+    end_time: Option<f64>,
+    #[serde(default)]
+    body: String,
+}
+
+/// A podcast:transcript JSON file.
+#[derive(Debug, Deserialize)]
+struct JsonTranscript {
+    #[serde(default)]
+    segments: Vec<JsonSegment>,
+}
+
+fn parse_json(body: &[u8]) -> Result<Vec<Segment>, StateError> {
+    let transcript: JsonTranscript = serde_json::from_slice(body).map_err(IoError::from)?;
+
+    Ok(transcript
+        .segments
+        .into_iter()
+        .filter(|segment| segment.start_time >= 0.0 && !segment.body.trim().is_empty())
+        .map(|segment| Segment {
+            start: (segment.start_time * 1000.0) as u64,
+            end: segment.end_time.map(|end| (end * 1000.0) as u64),
+            speaker: segment
+                .speaker
+                .map(|speaker| speaker.trim().to_owned())
+                .filter(|speaker| !speaker.is_empty()),
+            text: segment.body.trim().to_owned(),
+        })
+        .collect())
+}
+
+/// Parses the HTML transcripts the podcast namespace describes, where a `<cite>` names the
+/// speaker and a `<time>` starts each segment. HTML without any times is one segment.
+fn parse_html(body: &str) -> Vec<Segment> {
+    let token = Regex::new(r"(?is)<cite[^>]*>(.*?)</cite>|<time[^>]*>(.*?)</time>|<(?:script|style)[^>]*>.*?</(?:script|style)>|<[^>]*>|[^<]+").unwrap();
+    let detag = Regex::new(r"<[^>]+>").unwrap();
+
+    let mut segments: Vec<Segment> = vec![];
+    let mut speaker = None;
+    let mut text = String::new();
+
+    for captures in token.captures_iter(body) {
+        if let Some(cite) = captures.get(1) {
+            let cite = collapse_whitespace(&decode_entities(&detag.replace_all(cite.as_str(), "")));
+            speaker = Some(cite.trim_end_matches(':').trim().to_owned()).filter(|s| !s.is_empty());
+        } else if let Some(time) = captures.get(2) {
+            if let Some(start) = parse_npt(time.as_str()) {
+                if let Some(last) = segments.last_mut() {
+                    last.text = collapse_whitespace(&text);
+                } else if !collapse_whitespace(&text).is_empty() {
+                    segments.push(Segment {
+                        start: 0,
+                        end: None,
+                        speaker: None,
+                        text: collapse_whitespace(&text),
+                    });
+                }
+                text.clear();
+                segments.push(Segment {
+                    start,
+                    end: None,
+                    speaker: speaker.take(),
+                    text: String::new(),
+                });
+            }
+        } else if captures[0].starts_with('<') {
+            text.push(' ');
+        } else {
+            text.push_str(&decode_entities(&captures[0]));
+        }
+    }
+
+    match segments.last_mut() {
+        Some(last) => last.text = collapse_whitespace(&text),
+        None => segments.push(Segment {
+            start: 0,
+            end: None,
+            speaker: None,
+            text: collapse_whitespace(&text),
+        }),
+    }
+
+    segments
+        .into_iter()
+        .filter(|segment| !segment.text.is_empty())
+        .collect()
+}
+
+fn parse(mimetype: Option<&str>, url: &str, body: &[u8]) -> Result<Vec<Segment>, StateError> {
+    let text = String::from_utf8_lossy(body);
+    let format = mimetype
+        .and_then(Format::from_mimetype)
+        .or_else(|| Format::from_url(url))
+        .or_else(|| Format::from_body(&text))
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "Unknown transcript format"))?;
+
+    match format {
+        Format::Json => parse_json(body),
+        Format::Vtt | Format::Srt => Ok(parse_cues(&text)),
+        Format::Html => Ok(parse_html(&text)),
+    }
+}
+
+/// Downloads a transcript and splits it into segments.
+pub async fn fetch(link: &TranscriptLink) -> Result<Vec<Segment>, StateError> {
+    let mut res = http::get_success(&link.url).await?;
+    let body = res.body_bytes().await?;
+
+    // Trust the feed over the server, which often sends text/plain.
+    let mimetype = link
+        .mimetype
+        .clone()
+        .or_else(|| res.mime().map(|mime| mime.essence_str().to_owned()));
+    parse(mimetype.as_deref(), &link.url, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_srt() {
+        let segments = parse(
+            Some("application/srt"),
+            "https://example.com/1.srt",
+            b"1\r\n00:00:00,500 --> 00:00:02,000\r\nHello &amp; welcome\r\nto the show.\r\n\r\n2\r\n00:00:02,500 --> 00:00:04,000\r\nThanks.\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].start, 500);
+        assert_eq!(segments[0].end, Some(2_000));
+        assert_eq!(segments[0].text, "Hello & welcome to the show.");
+        assert_eq!(segments[1].start, 2_500);
+    }
+
+    #[test]
+    fn parses_vtt() {
+        let segments = parse(
+            None,
+            "https://example.com/transcript",
+            b"WEBVTT\n\nNOTE a comment\n\nintro\n00:01.000 --> 00:04.000 align:start\n<v Jane Doe>Hi <b>there</b></v>\n\n01:00:00.000 --> 01:00:01.000\n<v.loud Joe>Bye\n",
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].start, 1_000);
+        assert_eq!(segments[0].end, Some(4_000));
+        assert_eq!(segments[0].speaker.as_deref(), Some("Jane Doe"));
+        assert_eq!(segments[0].text, "Hi there");
+        assert_eq!(segments[1].start, 3_600_000);
+        assert_eq!(segments[1].speaker.as_deref(), Some("Joe"));
+    }
+
+    #[test]
+    fn parses_json() {
+        let segments = parse(
+            Some("application/json"),
+            "https://example.com/1.json",
+            br#"{
+                "version": "1.0.0",
+                "segments": [
+                    {"speaker": "Jane", "startTime": 0.5, "endTime": 1.5, "body": "Hello"},
+                    {"startTime": 1.5, "endTime": 2, "body": " "},
+                    {"speaker": "Joe", "startTime": 2, "body": "Hi"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].speaker.as_deref(), Some("Jane"));
+        assert_eq!(segments[0].end, Some(1_500));
+        assert_eq!(segments[1].start, 2_000);
+        assert_eq!(segments[1].end, None);
+    }
+
+    #[test]
+    fn parses_html() {
+        let segments = parse(
+            Some("text/html"),
+            "https://example.com/1.html",
+            b"<html><body>\
+                <cite>Jane:</cite><time>00:00:00</time><p>Hello &amp; welcome.</p>\
+                <cite>Joe:</cite><time>00:01:05</time><p>Thanks,</p><p>Jane.</p>\
+            </body></html>",
+        )
+        .unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].speaker.as_deref(), Some("Jane"));
+        assert_eq!(segments[0].text, "Hello & welcome.");
+        assert_eq!(segments[1].start, 65_000);
+        assert_eq!(segments[1].text, "Thanks, Jane.");
+
+        let untimed = parse(Some("text/html"), "", b"<p>Just text.</p>").unwrap();
+        assert_eq!(untimed.len(), 1);
+        assert_eq!(untimed[0].text, "Just text.");
+    }
+
+    #[test]
+    fn prefers_timed_transcripts() {
+        let item = crate::element::parse(
+            br#"<item xmlns:podcast="https://podcastindex.org/namespace/1.0">
+                <podcast:transcript url="https://example.com/1.html" type="text/html" />
+                <podcast:transcript url="https://example.com/1.vtt" type="text/vtt" />
+                <podcast:transcript url="https://example.com/1.srt" />
+            </item>"#,
+        )
+        .unwrap();
+
+        let link = best_link(&item).unwrap();
+        assert_eq!(link.url, "https://example.com/1.vtt");
+        assert_eq!(link.mimetype.as_deref(), Some("text/vtt"));
+    }
+}
@@ -6,11 +6,25 @@ use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 
 pub(crate) enum DesktopAction {
-    Play { artist: String, title: String },
+    Play {
+        artist: String,
+        title: String,
+        art_url: Option<String>,
+    },
+    /// The artwork changed, like when a chapter with its own image starts.
+    SetArt(Option<String>),
     Pause,
     Stop,
 }
 
+fn metadata(artist: &str, title: &str, art_url: Option<String>) -> Metadata {
+    let mut metadata = Metadata::new();
+    metadata.artist = Some(vec![artist.to_owned()]);
+    metadata.title = Some(title.to_owned());
+    metadata.art_url = art_url;
+    metadata
+}
+
 pub(crate) fn init_desktop_connection(send: Sender<PlayerAction>) -> glib::Sender<DesktopAction> {
     let mpris = MprisPlayer::new(
         "ca.nettek.pyrocast".to_owned(),
@@ -57,11 +71,17 @@ pub(crate) fn init_desktop_connection(send: Sender<PlayerAction>) -> glib::Sende
 
     let inhibit_cookie = Arc::new(Mutex::new(None));
 
+    // The artist and title being shown, so the artwork can be changed on its own.
+    let mut playing: Option<(String, String)> = None;
+
     rx.attach(None, move |command| {
         // TODO: seek
-        // TODO: image
         match command {
-            DesktopAction::Play { artist, title } => {
+            DesktopAction::Play {
+                artist,
+                title,
+                art_url,
+            } => {
                 if let Some(app) = gio::Application::get_default()
                     .and_then(|app| app.downcast::<gtk::Application>().ok())
                 {
@@ -75,13 +95,16 @@ pub(crate) fn init_desktop_connection(send: Sender<PlayerAction>) -> glib::Sende
                         ));
                     }
                 }
-                let mut metadata = Metadata::new();
-                metadata.artist = Some(vec![artist]);
-                metadata.title = Some(title);
-                mpris.set_metadata(metadata);
+                mpris.set_metadata(metadata(&artist, &title, art_url));
+                playing = Some((artist, title));
 
                 mpris.set_playback_status(PlaybackStatus::Playing);
             }
+            DesktopAction::SetArt(art_url) => {
+                if let Some((artist, title)) = &playing {
+                    mpris.set_metadata(metadata(artist, title, art_url));
+                }
+            }
             DesktopAction::Pause => {
                 mpris.set_playback_status(PlaybackStatus::Paused);
 
@@ -98,6 +121,7 @@ pub(crate) fn init_desktop_connection(send: Sender<PlayerAction>) -> glib::Sende
             DesktopAction::Stop => {
                 mpris.set_playback_status(PlaybackStatus::Stopped);
                 mpris.set_metadata(Metadata::new());
+                playing = None;
 
                 if let Some(app) = gio::Application::get_default()
                     .and_then(|app| app.downcast::<gtk::Application>().ok())
@@ -1,4 +1,5 @@
 mod desktop;
+mod video;
 
 use crate::desktop::{init_desktop_connection, DesktopAction};
 use gstreamer as gst;
@@ -6,10 +7,14 @@ use gstreamer::prelude::*;
 use gstreamer::ClockTime;
 use gstreamer_player as gplayer;
 use gstreamer_player::PlayerState as GPlayerState;
-use state::{CurrentState, Playback, PlayerState, StateAction};
+use state::{
+    next_chapter_start, previous_chapter_start, CurrentState, Playback, PlayerState, QueueItem,
+    StateAction,
+};
 use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
+pub use video::VideoSink;
 
 #[derive(Debug, Clone)]
 pub enum PlayerAction {
@@ -24,12 +29,32 @@ pub enum PlayerAction {
     SeekBack,
     SetTime(u64),
     SetRate(f64),
+    /// Seeks to the start of the next chapter of the playing episode.
+    NextChapter,
+    /// Seeks to the start of the current chapter, or the one before it if we're at its start.
+    PreviousChapter,
+
+    /// Adds an episode to the end of the queue.
+    Enqueue(QueueItem),
+    /// Adds an episode to the start of the queue.
+    PlayNext(QueueItem),
+    /// Stops the current episode and plays the first queued episode.
+    SkipToNext,
+    MoveInQueue {
+        from: usize,
+        to: usize,
+    },
+    RemoveFromQueue(usize),
+    ClearQueue,
+    /// Replaces the queue, e.g., with the one saved in the database.
+    SetQueue(Vec<QueueItem>),
 }
 
 fn audio_thread(
     send: Sender<PlayerAction>,
     recv: Receiver<PlayerAction>,
     current: Arc<CurrentState>,
+    video_sink: Option<VideoSink>,
 ) {
     gst::init().unwrap();
 
@@ -37,14 +62,30 @@ fn audio_thread(
     let audio_loop = glib::MainLoop::new(None, false);
 
     let dispatcher = gplayer::PlayerGMainContextSignalDispatcher::new(None);
+    let renderer = video_sink.as_ref().map(|sink| sink.renderer());
     let player = gplayer::Player::new(
-        None,
+        renderer.as_ref(),
         Some(&dispatcher.upcast::<gplayer::PlayerSignalDispatcher>()),
     );
 
+    // Whether the current stream has a video track.
+    let has_video = Arc::new(Mutex::new(false));
+    let has_video_clone = Arc::clone(&has_video);
+    player.connect_media_info_updated(move |_, info| {
+        *has_video_clone.lock().unwrap() = !info.get_video_streams().is_empty();
+    });
+
+    // Without somewhere to show it, playbin would open a window of its own for video.
+    let mut video_enabled = video_sink.is_some() && !current.get().video_as_audio();
+    player.set_video_track_enabled(video_enabled);
+
+    let ended = Arc::new(Mutex::new(false));
+
     // Connect to the player's "end-of-stream" signal, which will tell us when the
     // currently played media stream reached its end.
+    let ended_clone = Arc::clone(&ended);
     player.connect_end_of_stream(move |player| {
+        *ended_clone.lock().unwrap() = true;
         player.stop();
     });
 
@@ -71,6 +112,7 @@ fn audio_thread(
     let desktop_to_player = Arc::new(Mutex::new(Some(send)));
     let player_to_desktop = Arc::new(Mutex::new(None));
 
+    let player_to_desktop_clone = Arc::clone(&player_to_desktop);
     let current_clone = current.clone();
     player.connect_state_changed(move |player, state| {
         let rate = rate_clone.lock().unwrap();
@@ -107,6 +149,14 @@ fn audio_thread(
                             .and_then(|ep| ep.as_ref().ok())
                             .map(|ep| ep.title().to_owned())
                             .unwrap_or_default(),
+                        art_url: current_clone
+                            .get()
+                            .playing_episode()
+                            .as_deref()
+                            .and_then(|ep| ep.as_ref().ok())
+                            .and_then(|ep| {
+                                ep.image_url_at(player.get_position().mseconds().unwrap_or(0))
+                            }),
                     })
                     .unwrap(),
                 GPlayerState::Paused => send.send(DesktopAction::Pause).unwrap(),
@@ -119,13 +169,52 @@ fn audio_thread(
     // Time is 0 while buffering.
     let mut last_known_time = 0;
 
+    // The artwork last sent to the desktop, which changes with chapters.
+    let mut art_url = None;
+
+    // Where to seek to once the stream starts playing, if resuming an episode.
+    let mut resume_time = None;
+    // Whether the stream has played since it was set. Seeking only works once it has.
+    let mut loaded = false;
+
+    let mut queue: Vec<QueueItem> = vec![];
+    // Set when we advance through the queue on our own.
+    let mut next_action = None;
+
     loop {
-        match recv.recv_timeout(Duration::from_millis(100)) {
+        let action = match next_action.take() {
+            Some(action) => Ok(action),
+            None => recv.recv_timeout(Duration::from_millis(100)),
+        };
+
+        let prev_queue_len = queue.len();
+        let mut queue_changed = false;
+
+        match action {
             Ok(PlayerAction::PlayRemote {
                 episode_pk: new_episode_pk,
                 channel_pk: new_channel_pk,
                 uri,
             }) => {
+                resume_time = current
+                    .get()
+                    .episode_progress(&new_episode_pk)
+                    .filter(|progress| progress.resumable())
+                    .map(|progress| progress.time);
+                last_known_time = resume_time.unwrap_or(0);
+                loaded = false;
+                *ended.lock().unwrap() = false;
+                *has_video.lock().unwrap() = false;
+
+                queue.retain(|item| item.episode_pk != new_episode_pk);
+
+                // Prefer the downloaded copy, if there is one.
+                let uri = current
+                    .get()
+                    .download(&new_episode_pk)
+                    .and_then(|download| download.uri())
+                    .unwrap_or(uri);
+
                 player.set_uri(&uri);
                 // Can only set rate in playing state.
                 player.set_rate(1.0);
@@ -137,6 +226,7 @@ fn audio_thread(
                 player.pause();
             }
             Ok(PlayerAction::Unpause) => {
+                *ended.lock().unwrap() = false;
                 player.play();
             }
             Ok(PlayerAction::SeekForward) => {
@@ -149,7 +239,28 @@ fn audio_thread(
             }
             Ok(PlayerAction::SetTime(t)) => {
                 last_known_time = t;
-                player.seek(ClockTime::from_mseconds(t));
+                if loaded {
+                    player.seek(ClockTime::from_mseconds(t));
+                } else {
+                    resume_time = Some(t);
+                }
+            }
+            Ok(PlayerAction::NextChapter) => {
+                if let Some(Ok(episode)) = current.get().playing_episode().as_deref() {
+                    if let Some(start) = next_chapter_start(episode.chapters(), last_known_time) {
+                        last_known_time = start;
+                        player.seek(ClockTime::from_mseconds(start));
+                    }
+                }
+            }
+            Ok(PlayerAction::PreviousChapter) => {
+                if let Some(Ok(episode)) = current.get().playing_episode().as_deref() {
+                    if let Some(start) = previous_chapter_start(episode.chapters(), last_known_time)
+                    {
+                        last_known_time = start;
+                        player.seek(ClockTime::from_mseconds(start));
+                    }
+                }
             }
             Ok(PlayerAction::SetRate(next_rate)) => {
                 *rate.lock().unwrap() = next_rate;
@@ -157,6 +268,45 @@ fn audio_thread(
                     player.set_rate(next_rate);
                 }
             }
+            Ok(PlayerAction::Enqueue(item)) => {
+                queue.retain(|queued| queued.episode_pk != item.episode_pk);
+                queue.push(item);
+                queue_changed = true;
+            }
+            Ok(PlayerAction::PlayNext(item)) => {
+                queue.retain(|queued| queued.episode_pk != item.episode_pk);
+                queue.insert(0, item);
+                queue_changed = true;
+            }
+            Ok(PlayerAction::SkipToNext) => {
+                if !queue.is_empty() {
+                    let item = queue.remove(0);
+                    next_action = Some(PlayerAction::PlayRemote {
+                        episode_pk: item.episode_pk,
+                        channel_pk: item.channel_pk,
+                        uri: item.uri,
+                    });
+                }
+            }
+            Ok(PlayerAction::MoveInQueue { from, to }) => {
+                if from < queue.len() && to < queue.len() {
+                    let item = queue.remove(from);
+                    queue.insert(to, item);
+                    queue_changed = true;
+                }
+            }
+            Ok(PlayerAction::RemoveFromQueue(idx)) => {
+                if idx < queue.len() {
+                    queue.remove(idx);
+                }
+            }
+            Ok(PlayerAction::ClearQueue) => {
+                queue.clear();
+            }
+            Ok(PlayerAction::SetQueue(next_queue)) => {
+                queue = next_queue;
+                queue_changed = true;
+            }
             Err(RecvTimeoutError::Timeout) => {
                 // we'll update the status.
             }
@@ -165,18 +315,67 @@ fn audio_thread(
             }
         }
 
+        let next_video_enabled = video_sink.is_some() && !current.get().video_as_audio();
+        if next_video_enabled != video_enabled {
+            video_enabled = next_video_enabled;
+            player.set_video_track_enabled(video_enabled);
+        }
+
         let playback = match *gplayer_state.lock().unwrap() {
             GPlayerState::Playing => Playback::Playing,
+            GPlayerState::Stopped if *ended.lock().unwrap() => Playback::Ended,
             GPlayerState::Stopped => Playback::Stopped,
             GPlayerState::Paused => Playback::Paused,
             GPlayerState::Buffering => Playback::Buffering,
             _ => Playback::Buffering,
         };
 
-        if playback != Playback::Buffering {
+        if playback == Playback::Playing {
+            loaded = true;
+        }
+
+        // The position is reset once the stream ends, so keep the last known time then.
+        if let (Playback::Playing, Some(time)) = (playback, resume_time) {
+            // Can only seek once the stream is loaded.
+            resume_time = None;
+            last_known_time = time;
+            player.seek(ClockTime::from_mseconds(time));
+        } else if playback != Playback::Buffering
+            && playback != Playback::Ended
+            && resume_time.is_none()
+        {
             last_known_time = player.get_position().mseconds().unwrap_or(0);
         }
 
+        if playback == Playback::Ended && next_action.is_none() && !queue.is_empty() {
+            let item = queue.remove(0);
+            next_action = Some(PlayerAction::PlayRemote {
+                episode_pk: item.episode_pk,
+                channel_pk: item.channel_pk,
+                uri: item.uri,
+            });
+        }
+
+        if playback == Playback::Playing {
+            let next_art_url = current
+                .get()
+                .playing_episode()
+                .as_deref()
+                .and_then(|ep| ep.as_ref().ok())
+                .and_then(|ep| ep.image_url_at(last_known_time));
+            if next_art_url != art_url {
+                if let Some(send) = &*player_to_desktop_clone.lock().unwrap() {
+                    send.send(DesktopAction::SetArt(next_art_url.clone()))
+                        .unwrap();
+                }
+                art_url = next_art_url;
+            }
+        }
+
+        if queue_changed || queue.len() != prev_queue_len {
+            current.update(vec![StateAction::SetQueue(queue.clone())]);
+        }
+
         current.update(vec![StateAction::SetPlayerState(Some(PlayerState {
             episode_pk: episode_pk.clone(),
             channel_pk: channel_pk.clone(),
@@ -184,15 +383,20 @@ fn audio_thread(
             time: last_known_time,
             duration: player.get_duration().mseconds().unwrap_or(0),
             rate: player.get_rate(),
+            video: video_enabled && *has_video.lock().unwrap(),
         }))]);
     }
 }
 
-pub fn new_player(current: Arc<CurrentState>) -> Sender<PlayerAction> {
+/// Starts the player. Video is only shown if there is a sink to draw it in.
+pub fn new_player(
+    current: Arc<CurrentState>,
+    video_sink: Option<VideoSink>,
+) -> Sender<PlayerAction> {
     let (send_cmd, recv_cmd) = channel();
     let send_cmd_clone = send_cmd.clone();
     std::thread::spawn(move || {
-        audio_thread(send_cmd_clone, recv_cmd, current);
+        audio_thread(send_cmd_clone, recv_cmd, current, video_sink);
     });
 
     send_cmd
@@ -0,0 +1,32 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_player as gplayer;
+
+/// Where the player draws video, as a widget the app can embed.
+#[derive(Debug, Clone)]
+pub struct VideoSink {
+    element: gst::Element,
+}
+
+impl VideoSink {
+    /// Uses gtksink, from gst-plugins-good, if it is installed.
+    pub fn new() -> Option<VideoSink> {
+        gst::init().ok()?;
+        let element = gst::ElementFactory::make("gtksink", None).ok()?;
+
+        Some(VideoSink { element })
+    }
+
+    /// The widget the video is drawn in. Only call this on the GTK thread.
+    pub fn widget(&self) -> Option<gtk::Widget> {
+        self.element
+            .get_property("widget")
+            .ok()?
+            .get::<gtk::Widget>()
+            .ok()?
+    }
+
+    pub(crate) fn renderer(&self) -> gplayer::PlayerVideoRenderer {
+        gplayer::PlayerVideoOverlayVideoRenderer::new_with_sink(&self.element).upcast()
+    }
+}
@@ -16,6 +16,10 @@ pub struct ChannelCore {
     image_rss: Option<String>,
 
     rss: Option<String>,
+    /// Whether the feed moved to `rss`, which then wins over what directories say.
+    rss_moved: bool,
+    /// Whether the feed is gone for good, so it won't have new episodes.
+    ended: bool,
 }
 
 impl PartialEq for ChannelCore {
@@ -27,6 +31,8 @@ impl PartialEq for ChannelCore {
             && self.image_600 == other.image_600
             && self.image_rss == other.image_rss
             && self.rss == other.image_rss
+            && self.rss_moved == other.rss_moved
+            && self.ended == other.ended
     }
 }
 
@@ -60,21 +66,34 @@ impl ChannelCore {
         self
     }
 
+    pub fn image_200(&self) -> Option<&str> {
+        self.image_200.as_deref()
+    }
+
     pub fn with_image_200(mut self, image_200: String) -> Self {
         self.image_200 = Some(image_200);
         self
     }
 
+    pub fn image_600(&self) -> Option<&str> {
+        self.image_600.as_deref()
+    }
+
     pub fn with_image_600(mut self, image_600: String) -> Self {
         self.image_600 = Some(image_600);
         self
     }
 
+    pub fn image_rss(&self) -> Option<&str> {
+        self.image_rss.as_deref()
+    }
+
     pub fn with_image_rss(mut self, image_rss: String) -> Self {
         self.image_rss = Some(image_rss);
         self
     }
 
+    /// Artwork for lists. Use `Image::closest` to get the thumbnail for the size it is shown at.
     pub fn small_image(&self) -> Option<Arc<Result<Image, StateError>>> {
         let state = self.state.upgrade()?.get();
 
@@ -115,6 +134,7 @@ impl ChannelCore {
         fallback
     }
 
+    /// Artwork for detail views, preferring the highest resolution source.
     pub fn big_image(&self) -> Option<Arc<Result<Image, StateError>>> {
         let state = self.state.upgrade()?.get();
 
@@ -164,6 +184,24 @@ impl ChannelCore {
         self
     }
 
+    pub fn rss_moved(&self) -> bool {
+        self.rss_moved
+    }
+
+    pub fn with_rss_moved(mut self, rss_moved: bool) -> ChannelCore {
+        self.rss_moved = rss_moved;
+        self
+    }
+
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+
+    pub fn with_ended(mut self, ended: bool) -> ChannelCore {
+        self.ended = ended;
+        self
+    }
+
     pub fn details(&self) -> Option<Arc<Result<ChannelDetail, StateError>>> {
         let state = self.state.upgrade()?.get();
 
@@ -189,9 +227,11 @@ impl ChannelCore {
         if self.image_rss.is_none() {
             self.image_rss = other.image_rss.clone();
         }
-        if self.rss.is_none() {
+        if self.rss.is_none() || (other.rss_moved && !self.rss_moved) {
             self.rss = other.rss.clone();
+            self.rss_moved = other.rss_moved;
         }
+        self.ended |= other.ended;
     }
 
     pub(crate) fn references_image(&self, image: &str) -> bool {
@@ -1,4 +1,7 @@
-use crate::{ChannelCore, CurrentState, EpisodeRef, StateAction, StateError};
+use crate::{
+    ChannelCore, CurrentState, EpisodeRef, Funding, Image, Location, Person, ShowType, StateAction,
+    StateError,
+};
 use std::sync::{Arc, Weak};
 
 #[derive(Debug, Clone, Default)]
@@ -9,6 +12,14 @@ pub struct ChannelDetail {
 
     description: String,
     link: String,
+    show_type: ShowType,
+    /// The feed's podcast:guid, which stays the same wherever the feed is found.
+    guid: Option<String>,
+    /// Whether the feed asks not to be imported by other hosting platforms.
+    locked: bool,
+    persons: Vec<Person>,
+    funding: Vec<Funding>,
+    location: Option<Location>,
     episodes: Vec<EpisodeRef>,
 }
 
@@ -40,6 +51,67 @@ impl ChannelDetail {
         self
     }
 
+    pub fn show_type(&self) -> ShowType {
+        self.show_type
+    }
+
+    pub fn with_show_type(mut self, show_type: ShowType) -> Self {
+        self.show_type = show_type;
+        self
+    }
+
+    pub fn guid(&self) -> Option<&str> {
+        self.guid.as_deref()
+    }
+
+    pub fn with_guid(mut self, guid: Option<String>) -> Self {
+        self.guid = guid;
+        self
+    }
+
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// The show's hosts, guests and crew.
+    pub fn persons(&self) -> &[Person] {
+        &self.persons
+    }
+
+    pub fn with_persons(mut self, persons: Vec<Person>) -> Self {
+        self.persons = persons;
+        self
+    }
+
+    /// A person's image, once it has been loaded.
+    pub fn person_image(&self, person: &Person) -> Option<Arc<Result<Image, StateError>>> {
+        let url = person.image.as_ref()?;
+        self.state.upgrade()?.get().image(url)
+    }
+
+    pub fn funding(&self) -> &[Funding] {
+        &self.funding
+    }
+
+    pub fn with_funding(mut self, funding: Vec<Funding>) -> Self {
+        self.funding = funding;
+        self
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    pub fn with_location(mut self, location: Option<Location>) -> Self {
+        self.location = location;
+        self
+    }
+
     pub fn episodes(&self) -> &[EpisodeRef] {
         &self.episodes
     }
@@ -70,4 +142,10 @@ impl ChannelDetail {
         self.state = current;
         self
     }
+
+    pub(crate) fn references_image(&self, image: &str) -> bool {
+        self.persons
+            .iter()
+            .any(|person| matches!(&person.image, Some(person_image) if person_image == image))
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{ChannelCore, ChannelDetail, CurrentState, StateError};
+use crate::{ChannelCore, ChannelDetail, CurrentState, DownloadPolicy, StateError};
 use std::sync::{Arc, Weak};
 
 #[derive(Debug, Clone)]
@@ -29,4 +29,33 @@ impl ChannelRef {
         let state = self.state.upgrade()?.get();
         state.channel_detail.get(&self.pk).map(Arc::clone)
     }
+
+    /// The feed's podcast:guid, once it has loaded.
+    pub fn guid(&self) -> Option<String> {
+        let details = self.details()?;
+        let details = details.as_ref().as_ref().ok()?;
+        details.guid().map(|guid| guid.to_owned())
+    }
+
+    /// Whether both refer to the same show. Directories key the same feed differently, so this
+    /// also compares podcast:guid.
+    pub fn same_channel(&self, other: &ChannelRef) -> bool {
+        self == other || matches!(self.guid(), Some(guid) if other.guid().as_ref() == Some(&guid))
+    }
+
+    /// How many episodes appeared since the user last looked at this channel.
+    pub fn new_episode_count(&self) -> usize {
+        match self.state.upgrade() {
+            Some(state) => state.get().new_episode_count(&self.pk),
+            None => 0,
+        }
+    }
+
+    /// The rules for automatically downloading this channel's episodes.
+    pub fn download_policy(&self) -> DownloadPolicy {
+        match self.state.upgrade() {
+            Some(state) => state.get().download_policy(&self.pk),
+            None => DownloadPolicy::default(),
+        }
+    }
 }
@@ -0,0 +1,129 @@
+/// A section of an episode, from its feed or its chapters file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// Milliseconds from the start of the episode.
+    pub start: u64,
+    pub title: String,
+    pub image: Option<String>,
+    /// A web page about the chapter.
+    pub url: Option<String>,
+}
+
+/// The index of the chapter playing at `time` (in milliseconds), given chapters sorted by start.
+pub fn chapter_at(chapters: &[Chapter], time: u64) -> Option<usize> {
+    chapters.iter().rposition(|chapter| chapter.start <= time)
+}
+
+/// Where "previous chapter" should go: the start of the current chapter, unless we are at its very
+/// start, in which case the one before it.
+pub fn previous_chapter_start(chapters: &[Chapter], time: u64) -> Option<u64> {
+    // How far into a chapter still counts as its start.
+    const GRACE: u64 = 3000;
+
+    let current = chapter_at(chapters, time)?;
+    if time - chapters[current].start > GRACE || current == 0 {
+        Some(chapters[current].start)
+    } else {
+        Some(chapters[current - 1].start)
+    }
+}
+
+/// Where "next chapter" should go, if there is a next chapter.
+pub fn next_chapter_start(chapters: &[Chapter], time: u64) -> Option<u64> {
+    chapters
+        .iter()
+        .find(|chapter| chapter.start > time)
+        .map(|chapter| chapter.start)
+}
+
+/// Combines chapters from the feed with those embedded in the audio file. The feed's chapters win,
+/// but take images and links from embedded chapters that start at about the same time.
+pub fn merge_chapters(feed: &[Chapter], embedded: &[Chapter]) -> Vec<Chapter> {
+    // How far apart the same chapter can start in the feed and the file.
+    const TOLERANCE: u64 = 1000;
+
+    if feed.is_empty() {
+        return embedded.to_vec();
+    }
+
+    feed.iter()
+        .map(|chapter| {
+            let matching = embedded.iter().find(|embedded| {
+                embedded.start.max(chapter.start) - embedded.start.min(chapter.start) <= TOLERANCE
+            });
+
+            Chapter {
+                image: chapter
+                    .image
+                    .clone()
+                    .or_else(|| matching.and_then(|embedded| embedded.image.clone())),
+                url: chapter
+                    .url
+                    .clone()
+                    .or_else(|| matching.and_then(|embedded| embedded.url.clone())),
+                ..chapter.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> Vec<Chapter> {
+        [0, 60_000, 120_000]
+            .iter()
+            .map(|start| Chapter {
+                start: *start,
+                title: format!("{}", start),
+                image: None,
+                url: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_current_chapter() {
+        assert_eq!(chapter_at(&chapters(), 0), Some(0));
+        assert_eq!(chapter_at(&chapters(), 61_000), Some(1));
+        assert_eq!(chapter_at(&chapters(), 500_000), Some(2));
+        assert_eq!(chapter_at(&[], 500_000), None);
+    }
+
+    #[test]
+    fn skips_between_chapters() {
+        assert_eq!(next_chapter_start(&chapters(), 61_000), Some(120_000));
+        assert_eq!(next_chapter_start(&chapters(), 121_000), None);
+
+        assert_eq!(previous_chapter_start(&chapters(), 90_000), Some(60_000));
+        assert_eq!(previous_chapter_start(&chapters(), 61_000), Some(0));
+        assert_eq!(previous_chapter_start(&chapters(), 1_000), Some(0));
+    }
+
+    #[test]
+    fn merges_embedded_chapters() {
+        let embedded: Vec<Chapter> = chapters()
+            .into_iter()
+            .map(|chapter| Chapter {
+                start: chapter.start + 500,
+                title: "Embedded".to_owned(),
+                image: Some(format!("file:///{}.jpg", chapter.start)),
+                ..chapter
+            })
+            .collect();
+
+        assert_eq!(merge_chapters(&[], &embedded), embedded);
+
+        let mut feed = chapters();
+        feed[0].image = Some("https://example.com/0.jpg".to_owned());
+        let merged = merge_chapters(&feed, &embedded);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[1].title, "60000");
+        assert_eq!(
+            merged[0].image.as_deref(),
+            Some("https://example.com/0.jpg")
+        );
+        assert_eq!(merged[1].image.as_deref(), Some("file:///60000.jpg"));
+    }
+}
@@ -0,0 +1,45 @@
+use crate::StateError;
+use std::path::PathBuf;
+use surf::url::Url;
+
+/// The status of an episode's local copy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Download {
+    Queued,
+    InProgress {
+        /// Bytes downloaded so far, including any resumed part.
+        downloaded: u64,
+        /// Size of the whole file, if the server told us.
+        total: Option<u64>,
+    },
+    Cancelled,
+    Done(PathBuf),
+    Failed(StateError),
+}
+
+impl Download {
+    /// Whether the download is queued or in progress.
+    pub fn active(&self) -> bool {
+        matches!(self, Download::Queued | Download::InProgress { .. })
+    }
+
+    /// How much has been downloaded, from 0.0 to 1.0, if known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self {
+            Download::InProgress {
+                downloaded,
+                total: Some(total),
+            } if *total > 0 => Some(*downloaded as f64 / *total as f64),
+            Download::Done(_) => Some(1.0),
+            _ => None,
+        }
+    }
+
+    /// A file:// URI for the local copy, if the download is done.
+    pub fn uri(&self) -> Option<String> {
+        match self {
+            Download::Done(path) => Url::from_file_path(path).ok().map(|url| url.to_string()),
+            _ => None,
+        }
+    }
+}
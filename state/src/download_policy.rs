@@ -0,0 +1,23 @@
+/// Rules for automatically downloading and cleaning up a subscription's episodes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DownloadPolicy {
+    /// How many of the newest episodes to keep downloaded, or 0 to not download automatically.
+    pub keep_latest: u32,
+    /// Only download automatically when the network connection is not metered.
+    pub unmetered_only: bool,
+    /// Delete downloads of played episodes after this many days.
+    pub delete_played_after: Option<u32>,
+    /// The most space this subscription's downloads may take, in bytes.
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for DownloadPolicy {
+    fn default() -> Self {
+        DownloadPolicy {
+            keep_latest: 0,
+            unmetered_only: true,
+            delete_played_after: None,
+            max_bytes: None,
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+/// One of the files an episode is published as, from an enclosure, media:content or
+/// podcast:alternateEnclosure.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Enclosure {
+    pub url: String,
+    /// The mimetype the feed claims, like "audio/mpeg" or "video/mp4".
+    pub mimetype: Option<String>,
+    /// In bytes.
+    pub length: Option<u64>,
+    /// In bits per second.
+    pub bitrate: Option<u64>,
+    /// What the feed calls this variant, like "High quality".
+    pub title: Option<String>,
+}
+
+impl Enclosure {
+    pub fn is_video(&self) -> bool {
+        self.mimetype
+            .as_deref()
+            .map_or(false, |mimetype| mimetype.starts_with("video/"))
+    }
+
+    /// The bitrate, or an estimate from the length if the feed doesn't say.
+    pub fn estimated_bitrate(&self, duration: Option<Duration>) -> Option<u64> {
+        self.bitrate.or_else(|| {
+            let seconds = duration?.as_secs();
+            Some(self.length? * 8 / seconds).filter(|_| seconds > 0)
+        })
+    }
+}
+
+/// Which variant of an episode to stream and download.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnclosurePreference {
+    /// The feed's main enclosure.
+    FeedDefault,
+    /// The lowest bitrate.
+    Smallest,
+    /// The lowest bitrate on metered connections, the feed's main enclosure otherwise.
+    SmallestWhenMetered,
+    /// The highest bitrate.
+    Largest,
+}
+
+impl Default for EnclosurePreference {
+    fn default() -> Self {
+        EnclosurePreference::FeedDefault
+    }
+}
+
+impl EnclosurePreference {
+    pub fn parse(value: &str) -> EnclosurePreference {
+        match value {
+            "smallest" => EnclosurePreference::Smallest,
+            "smallest-metered" => EnclosurePreference::SmallestWhenMetered,
+            "largest" => EnclosurePreference::Largest,
+            _ => EnclosurePreference::FeedDefault,
+        }
+    }
+
+    /// As saved in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EnclosurePreference::FeedDefault => "default",
+            EnclosurePreference::Smallest => "smallest",
+            EnclosurePreference::SmallestWhenMetered => "smallest-metered",
+            EnclosurePreference::Largest => "largest",
+        }
+    }
+}
+
+/// Picks the variant to play, given enclosures with the feed's main one first. Only variants of
+/// the same kind as the main one are considered, so audio never turns into video or back, unless
+/// `audio_only` asks for an audio variant of a video episode.
+pub fn choose_enclosure(
+    enclosures: &[Enclosure],
+    preference: EnclosurePreference,
+    network_metered: bool,
+    audio_only: bool,
+    duration: Option<Duration>,
+) -> Option<&Enclosure> {
+    let mut main = enclosures.first()?;
+    if audio_only && main.is_video() {
+        if let Some(audio) = enclosures.iter().find(|enclosure| !enclosure.is_video()) {
+            main = audio;
+        }
+    }
+    let candidates = enclosures
+        .iter()
+        .filter(|enclosure| enclosure.is_video() == main.is_video())
+        .filter_map(|enclosure| Some((enclosure.estimated_bitrate(duration)?, enclosure)));
+
+    let chosen = match preference {
+        EnclosurePreference::FeedDefault => None,
+        EnclosurePreference::SmallestWhenMetered if !network_metered => None,
+        EnclosurePreference::Smallest | EnclosurePreference::SmallestWhenMetered => candidates
+            .min_by_key(|(bitrate, _)| *bitrate)
+            .map(|(_, enclosure)| enclosure),
+        EnclosurePreference::Largest => candidates
+            .max_by_key(|(bitrate, _)| *bitrate)
+            .map(|(_, enclosure)| enclosure),
+    };
+
+    chosen.or(Some(main))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enclosure(
+        url: &str,
+        mimetype: &str,
+        bitrate: Option<u64>,
+        length: Option<u64>,
+    ) -> Enclosure {
+        Enclosure {
+            url: url.to_owned(),
+            mimetype: Some(mimetype.to_owned()),
+            length,
+            bitrate,
+            title: None,
+        }
+    }
+
+    #[test]
+    fn chooses_by_preference() {
+        let enclosures = vec![
+            enclosure("main.mp3", "audio/mpeg", Some(128_000), None),
+            enclosure("low.opus", "audio/opus", Some(32_000), None),
+            // 60 MB over an hour is about 133 kbps.
+            enclosure("high.m4a", "audio/mp4", None, Some(60_000_000)),
+            enclosure("video.mp4", "video/mp4", Some(2_000_000), None),
+        ];
+        let hour = Some(Duration::from_secs(3600));
+        let chosen = |preference, metered| {
+            choose_enclosure(&enclosures, preference, metered, false, hour).map(|e| &e.url as &str)
+        };
+
+        assert_eq!(
+            chosen(EnclosurePreference::FeedDefault, true),
+            Some("main.mp3")
+        );
+        assert_eq!(
+            chosen(EnclosurePreference::Smallest, false),
+            Some("low.opus")
+        );
+        assert_eq!(
+            chosen(EnclosurePreference::Largest, false),
+            Some("high.m4a")
+        );
+        assert_eq!(
+            chosen(EnclosurePreference::SmallestWhenMetered, false),
+            Some("main.mp3")
+        );
+        assert_eq!(
+            chosen(EnclosurePreference::SmallestWhenMetered, true),
+            Some("low.opus")
+        );
+        assert_eq!(
+            choose_enclosure(&[], EnclosurePreference::Smallest, true, false, hour),
+            None
+        );
+    }
+
+    #[test]
+    fn plays_video_as_audio() {
+        let enclosures = vec![
+            enclosure("main.mp4", "video/mp4", Some(2_000_000), None),
+            enclosure("small.mp4", "video/mp4", Some(500_000), None),
+            enclosure("audio.mp3", "audio/mpeg", Some(128_000), None),
+        ];
+        let chosen = |preference, audio_only| {
+            choose_enclosure(&enclosures, preference, false, audio_only, None)
+                .map(|e| &e.url as &str)
+        };
+
+        assert_eq!(
+            chosen(EnclosurePreference::FeedDefault, false),
+            Some("main.mp4")
+        );
+        assert_eq!(
+            chosen(EnclosurePreference::Smallest, false),
+            Some("small.mp4")
+        );
+        assert_eq!(
+            chosen(EnclosurePreference::FeedDefault, true),
+            Some("audio.mp3")
+        );
+        assert_eq!(
+            chosen(EnclosurePreference::Largest, true),
+            Some("audio.mp3")
+        );
+    }
+}
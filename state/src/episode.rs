@@ -1,5 +1,12 @@
-use crate::{ChannelRef, CurrentState, Image, StateAction, StateError};
+use crate::parse::{parse_date, parse_duration};
+use crate::{
+    chapter_at, choose_enclosure, merge_chapters, ChannelRef, Chapter, CurrentState, Download,
+    Enclosure, EnclosurePreference, EpisodeType, Image, Location, Person, Progress, Segment,
+    StateAction, StateError, TranscriptLink,
+};
+use chrono::{DateTime, FixedOffset};
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Default)]
 pub struct Episode {
@@ -10,12 +17,47 @@ pub struct Episode {
 
     title: String,
     url: String,
+    /// As written in the feed.
     date: String,
     description: String,
+    /// Show notes, in the subset of Pango markup the loader produces.
+    notes: String,
+    /// As written in the feed.
     duration: String,
 
+    parsed_date: Option<DateTime<FixedOffset>>,
+    parsed_duration: Option<Duration>,
+
     image: Option<String>,
+    /// The URL of the feed's main enclosure.
     audio: String,
+    /// Every variant the episode is published as, with the main enclosure first.
+    enclosures: Vec<Enclosure>,
+
+    season: Option<u32>,
+    /// What the feed calls the season, like "Rebuilding".
+    season_name: Option<String>,
+    /// The episode's number, within its season if there is one.
+    number: Option<u32>,
+    episode_type: EpisodeType,
+
+    /// Who is on the episode, on top of the show's own hosts.
+    persons: Vec<Person>,
+    location: Option<Location>,
+
+    /// From the feed or its chapters file, sorted by start time.
+    feed_chapters: Vec<Chapter>,
+    /// A podcast:chapters JSON file, loaded when the episode is played.
+    chapters_url: Option<String>,
+    /// From the tags of the downloaded file, sorted by start time. Not saved, so they are read
+    /// again when the episode is played.
+    embedded_chapters: Vec<Chapter>,
+    /// `feed_chapters` and `embedded_chapters` combined.
+    chapters: Vec<Chapter>,
+
+    /// Sorted by start time. Not saved, so it is loaded again when the episode is played.
+    transcript: Vec<Segment>,
+    transcript_link: Option<TranscriptLink>,
 }
 
 impl PartialEq for Episode {
@@ -26,9 +68,22 @@ impl PartialEq for Episode {
             && self.url == other.url
             && self.date == other.date
             && self.description == other.description
+            && self.notes == other.notes
             && self.duration == other.duration
             && self.image == other.image
             && self.audio == other.audio
+            && self.enclosures == other.enclosures
+            && self.season == other.season
+            && self.season_name == other.season_name
+            && self.number == other.number
+            && self.episode_type == other.episode_type
+            && self.persons == other.persons
+            && self.location == other.location
+            && self.feed_chapters == other.feed_chapters
+            && self.chapters_url == other.chapters_url
+            && self.embedded_chapters == other.embedded_chapters
+            && self.transcript == other.transcript
+            && self.transcript_link == other.transcript_link
     }
 }
 
@@ -74,11 +129,18 @@ impl Episode {
         self
     }
 
-    pub fn date(&self) -> &str {
+    /// When the episode was published, if the feed says in a way we understand.
+    pub fn date(&self) -> Option<DateTime<FixedOffset>> {
+        self.parsed_date
+    }
+
+    /// The publication date as written in the feed.
+    pub fn raw_date(&self) -> &str {
         &self.date
     }
 
     pub fn with_date(mut self, date: String) -> Self {
+        self.parsed_date = parse_date(&date);
         self.date = date;
         self
     }
@@ -92,11 +154,28 @@ impl Episode {
         self
     }
 
-    pub fn duration(&self) -> &str {
+    /// The full show notes as Pango markup, with formatting and links.
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    pub fn with_notes(mut self, notes: String) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    /// How long the episode is, if the feed says in a way we understand.
+    pub fn duration(&self) -> Option<Duration> {
+        self.parsed_duration
+    }
+
+    /// The duration as written in the feed.
+    pub fn raw_duration(&self) -> &str {
         &self.duration
     }
 
     pub fn with_duration(mut self, duration: String) -> Self {
+        self.parsed_duration = parse_duration(&duration);
         self.duration = duration;
         self
     }
@@ -110,6 +189,139 @@ impl Episode {
         self
     }
 
+    pub fn enclosures(&self) -> &[Enclosure] {
+        &self.enclosures
+    }
+
+    pub fn with_enclosures(mut self, enclosures: Vec<Enclosure>) -> Self {
+        self.enclosures = enclosures;
+        self
+    }
+
+    /// The variant to stream or download, following the user's preferences and the network.
+    pub fn preferred_enclosure(&self) -> Option<&Enclosure> {
+        let (preference, network_metered, audio_only) = match self.state.upgrade() {
+            Some(state) => {
+                let state = state.get();
+                (
+                    state.enclosure_preference(),
+                    state.network_metered(),
+                    state.video_as_audio(),
+                )
+            }
+            None => (EnclosurePreference::default(), false, false),
+        };
+
+        choose_enclosure(
+            &self.enclosures,
+            preference,
+            network_metered,
+            audio_only,
+            self.duration(),
+        )
+    }
+
+    /// The URL to stream or download.
+    pub fn media_url(&self) -> &str {
+        self.preferred_enclosure()
+            .map(|enclosure| &enclosure.url as &str)
+            .unwrap_or(&self.audio)
+    }
+
+    pub fn season(&self) -> Option<u32> {
+        self.season
+    }
+
+    pub fn with_season(mut self, season: Option<u32>) -> Self {
+        self.season = season;
+        self
+    }
+
+    pub fn season_name(&self) -> Option<&str> {
+        self.season_name.as_deref()
+    }
+
+    pub fn with_season_name(mut self, season_name: Option<String>) -> Self {
+        self.season_name = season_name;
+        self
+    }
+
+    pub fn number(&self) -> Option<u32> {
+        self.number
+    }
+
+    pub fn with_number(mut self, number: Option<u32>) -> Self {
+        self.number = number;
+        self
+    }
+
+    pub fn episode_type(&self) -> EpisodeType {
+        self.episode_type
+    }
+
+    pub fn with_episode_type(mut self, episode_type: EpisodeType) -> Self {
+        self.episode_type = episode_type;
+        self
+    }
+
+    /// Who is on the episode. The show's hosts are on the channel instead.
+    pub fn persons(&self) -> &[Person] {
+        &self.persons
+    }
+
+    pub fn with_persons(mut self, persons: Vec<Person>) -> Self {
+        self.persons = persons;
+        self
+    }
+
+    /// A person's image, once it has been loaded.
+    pub fn person_image(&self, person: &Person) -> Option<Arc<Result<Image, StateError>>> {
+        let url = person.image.as_ref()?;
+        self.state.upgrade()?.get().image(url)
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    pub fn with_location(mut self, location: Option<Location>) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// The last known playback position, if this episode has been played.
+    pub fn progress(&self) -> Option<Progress> {
+        let state = self.state.upgrade()?.get();
+        state.episode_progress(&self.pk)
+    }
+
+    /// The status of the local copy of this episode, if it has been downloaded.
+    pub fn download(&self) -> Option<Download> {
+        let state = self.state.upgrade()?.get();
+        state.download(&self.pk).cloned()
+    }
+
+    /// Whether this episode has been listened to the end or marked as played.
+    pub fn played(&self) -> bool {
+        match self.state.upgrade() {
+            Some(state) => state.get().episode_played(&self.pk),
+            None => false,
+        }
+    }
+
+    /// Whether this episode appeared in a subscription since the user last looked at it.
+    pub fn is_new(&self) -> bool {
+        match self.state.upgrade() {
+            Some(state) => state
+                .get()
+                .new_episodes()
+                .get(&self.channel)
+                .map(|episodes| episodes.contains(&self.pk))
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+
     pub fn image(&self) -> Option<Arc<Result<Image, StateError>>> {
         let state = self.state.upgrade()?.get();
 
@@ -134,11 +346,115 @@ impl Episode {
         fallback
     }
 
+    /// The URL of the episode's own artwork, if it has any.
+    pub fn image_url(&self) -> Option<&str> {
+        self.image.as_deref()
+    }
+
     pub fn with_image(mut self, image: Option<String>) -> Self {
         self.image = image;
         self
     }
 
+    /// The chapters from the feed and the downloaded file, combined.
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    /// The chapters from the feed or its chapters file only.
+    pub fn feed_chapters(&self) -> &[Chapter] {
+        &self.feed_chapters
+    }
+
+    pub fn with_chapters(mut self, mut chapters: Vec<Chapter>) -> Self {
+        chapters.sort_by_key(|chapter| chapter.start);
+        self.feed_chapters = chapters;
+        self.merge_chapters();
+        self
+    }
+
+    pub fn embedded_chapters(&self) -> &[Chapter] {
+        &self.embedded_chapters
+    }
+
+    pub fn with_embedded_chapters(mut self, mut chapters: Vec<Chapter>) -> Self {
+        chapters.sort_by_key(|chapter| chapter.start);
+        self.embedded_chapters = chapters;
+        self.merge_chapters();
+        self
+    }
+
+    fn merge_chapters(&mut self) {
+        self.chapters = merge_chapters(&self.feed_chapters, &self.embedded_chapters);
+    }
+
+    /// The artwork to show at `time` (in milliseconds): the current chapter's image if it is
+    /// loaded, otherwise the episode's.
+    pub fn image_at(&self, time: u64) -> Option<Arc<Result<Image, StateError>>> {
+        let chapter_image = chapter_at(self.chapters(), time)
+            .and_then(|idx| self.chapters()[idx].image.as_ref())
+            .and_then(|url| self.state.upgrade()?.get().image(url))
+            .filter(|image| matches!(image.as_ref(), Ok(image) if image.loaded()));
+
+        chapter_image.or_else(|| self.image())
+    }
+
+    /// The URL of the artwork to show at `time` (in milliseconds), for other programs.
+    pub fn image_url_at(&self, time: u64) -> Option<String> {
+        if let Some(idx) = chapter_at(self.chapters(), time) {
+            if let Some(image) = &self.chapters()[idx].image {
+                return Some(image.clone());
+            }
+        }
+        if let Some(image) = &self.image {
+            return Some(image.clone());
+        }
+
+        let core = self.channel().core()?;
+        let core = core.as_ref().as_ref().ok()?;
+        core.image_600()
+            .or_else(|| core.image_rss())
+            .map(|image| image.to_owned())
+    }
+
+    pub fn chapters_url(&self) -> Option<&str> {
+        self.chapters_url.as_deref()
+    }
+
+    pub fn with_chapters_url(mut self, chapters_url: Option<String>) -> Self {
+        self.chapters_url = chapters_url;
+        self
+    }
+
+    /// Whether the chapters file still needs to be loaded.
+    pub fn needs_chapters(&self) -> bool {
+        self.feed_chapters.is_empty() && self.chapters_url.is_some()
+    }
+
+    pub fn transcript(&self) -> &[Segment] {
+        &self.transcript
+    }
+
+    pub fn with_transcript(mut self, mut transcript: Vec<Segment>) -> Self {
+        transcript.sort_by_key(|segment| segment.start);
+        self.transcript = transcript;
+        self
+    }
+
+    pub fn transcript_link(&self) -> Option<&TranscriptLink> {
+        self.transcript_link.as_ref()
+    }
+
+    pub fn with_transcript_link(mut self, transcript_link: Option<TranscriptLink>) -> Self {
+        self.transcript_link = transcript_link;
+        self
+    }
+
+    /// Whether the transcript still needs to be loaded.
+    pub fn needs_transcript(&self) -> bool {
+        self.transcript.is_empty() && self.transcript_link.is_some()
+    }
+
     pub fn build(self) -> StateAction {
         StateAction::SetEpisode(String::from(&self.pk), Ok(self))
     }
@@ -148,7 +464,28 @@ impl Episode {
         self
     }
 
+    /// Keeps chapters and transcripts loaded from other files when the feed is refreshed.
+    pub(crate) fn update(&mut self, other: &Episode) {
+        if self.needs_chapters() && self.chapters_url == other.chapters_url {
+            self.feed_chapters = other.feed_chapters.clone();
+        }
+        if self.embedded_chapters.is_empty() {
+            self.embedded_chapters = other.embedded_chapters.clone();
+        }
+        self.merge_chapters();
+        if self.needs_transcript() && self.transcript_link == other.transcript_link {
+            self.transcript = other.transcript.clone();
+        }
+    }
+
     pub(crate) fn references_image(&self, image: &str) -> bool {
         matches!(&self.image, Some(ep_image) if ep_image == image)
+            || self.chapters.iter().any(
+                |chapter| matches!(&chapter.image, Some(chapter_image) if chapter_image == image),
+            )
+            || self
+                .persons
+                .iter()
+                .any(|person| matches!(&person.image, Some(person_image) if person_image == image))
     }
 }
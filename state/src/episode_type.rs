@@ -0,0 +1,66 @@
+/// What an episode is, from itunes:episodeType.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EpisodeType {
+    Full,
+    Trailer,
+    Bonus,
+}
+
+impl Default for EpisodeType {
+    fn default() -> Self {
+        EpisodeType::Full
+    }
+}
+
+impl EpisodeType {
+    /// Anything we don't know is taken to be a full episode.
+    pub fn parse(value: &str) -> EpisodeType {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "trailer" => EpisodeType::Trailer,
+            "bonus" => EpisodeType::Bonus,
+            _ => EpisodeType::Full,
+        }
+    }
+
+    /// As written in feeds.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EpisodeType::Full => "full",
+            EpisodeType::Trailer => "trailer",
+            EpisodeType::Bonus => "bonus",
+        }
+    }
+}
+
+/// How a show is meant to be listened to, from itunes:type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShowType {
+    /// Newest episodes first, in any order.
+    Episodic,
+    /// In order, from the first episode.
+    Serial,
+}
+
+impl Default for ShowType {
+    fn default() -> Self {
+        ShowType::Episodic
+    }
+}
+
+impl ShowType {
+    pub fn parse(value: &str) -> ShowType {
+        if value.trim().eq_ignore_ascii_case("serial") {
+            ShowType::Serial
+        } else {
+            ShowType::Episodic
+        }
+    }
+
+    /// As written in feeds.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShowType::Episodic => "episodic",
+            ShowType::Serial => "serial",
+        }
+    }
+}
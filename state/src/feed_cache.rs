@@ -0,0 +1,8 @@
+/// What we know about the last response for a feed, so we only download it when it changed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FeedCache {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the feed was last fetched or confirmed unchanged (seconds since the epoch).
+    pub checked: i64,
+}
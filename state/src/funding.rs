@@ -0,0 +1,7 @@
+/// A way to support a show, from podcast:funding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Funding {
+    pub url: String,
+    /// What the feed calls the link, like "Support us on Patreon". May be empty.
+    pub title: String,
+}
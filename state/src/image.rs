@@ -1,9 +1,20 @@
+/// A smaller copy of an image, made when it is loaded so the UI doesn't have to scale it.
+#[derive(Clone)]
+pub struct Thumbnail {
+    /// The longest side, in pixels.
+    pub size: u32,
+    pub mimetype: String,
+    pub data: Vec<u8>,
+}
+
 pub struct Image {
     pub pk: String,
     pub mimetype: Option<String>,
     pub etag: Option<String>,
     pub last_modified: Option<String>,
     pub data: Option<Vec<u8>>,
+    /// Smallest first.
+    pub thumbnails: Vec<Thumbnail>,
 }
 
 impl Image {
@@ -14,12 +25,26 @@ impl Image {
             etag: None,
             last_modified: None,
             data: None,
+            thumbnails: vec![],
         }
     }
 
     pub fn loaded(&self) -> bool {
         self.data.is_some()
     }
+
+    /// The mimetype and data of the smallest copy that is at least `size` pixels, falling back to
+    /// the original.
+    pub fn closest(&self, size: u32) -> Option<(&str, &[u8])> {
+        match self
+            .thumbnails
+            .iter()
+            .find(|thumbnail| thumbnail.size >= size)
+        {
+            Some(thumbnail) => Some((&thumbnail.mimetype, &thumbnail.data)),
+            None => Some((self.mimetype.as_deref()?, self.data.as_deref()?)),
+        }
+    }
 }
 
 impl std::fmt::Debug for Image {
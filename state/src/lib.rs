@@ -1,26 +1,54 @@
 use crossbeam_channel::{unbounded, Sender, TryRecvError};
 use futures::channel::mpsc::{channel as fchannel, Receiver as FReceiver};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock, Weak};
 use std::thread;
 
 mod channel_core;
 mod channel_detail;
 mod channel_ref;
+mod chapter;
+mod download;
+mod download_policy;
+mod enclosure;
 mod episode;
 mod episode_ref;
+mod episode_type;
+mod feed_cache;
+mod funding;
 mod image;
+mod location;
+mod parse;
+mod person;
 mod player_state;
+mod progress;
+mod queue_item;
 mod state_error;
+mod transcript;
 
 pub use channel_core::ChannelCore;
 pub use channel_detail::ChannelDetail;
 pub use channel_ref::ChannelRef;
+pub use chapter::{
+    chapter_at, merge_chapters, next_chapter_start, previous_chapter_start, Chapter,
+};
+pub use download::Download;
+pub use download_policy::DownloadPolicy;
+pub use enclosure::{choose_enclosure, Enclosure, EnclosurePreference};
 pub use episode::Episode;
 pub use episode_ref::EpisodeRef;
-pub use image::Image;
+pub use episode_type::{EpisodeType, ShowType};
+pub use feed_cache::FeedCache;
+pub use funding::Funding;
+pub use image::{Image, Thumbnail};
+pub use location::Location;
+pub use parse::{timestamp_from_href, timestamp_href};
+pub use person::Person;
 pub use player_state::{Playback, PlayerState};
+pub use progress::Progress;
+pub use queue_item::QueueItem;
 pub use state_error::StateError;
+pub use transcript::{segment_at, Segment, TranscriptLink};
 
 #[derive(Debug)]
 pub enum StateAction {
@@ -29,19 +57,36 @@ pub enum StateAction {
 
     SetSearchQuery(String),
     SetSearchFocus(Option<ChannelRef>),
+    SetSearchEpisodeFocus(Option<EpisodeRef>),
     SetSearchFeed {
         query: String,
         results: Result<Vec<String>, StateError>,
     },
 
     SetHomeFocus(Option<ChannelRef>),
+    SetHomeEpisodeFocus(Option<EpisodeRef>),
 
     SetChannelCore(String, Result<ChannelCore, StateError>),
+    /// The channel's feed moved to a new URL for good.
+    SetChannelRss(String, String),
+    /// Whether the channel's feed is gone for good.
+    SetChannelEnded(String, bool),
     SetChannelDetail(String, Result<ChannelDetail, StateError>),
     SetEpisode(String, Result<Episode, StateError>),
     SetImage(String, Result<Image, StateError>),
     SetLoading(bool),
     SetPlayerState(Option<PlayerState>),
+    SetProgress(Vec<(String, Progress)>),
+    SetPlayed(String, bool),
+    SetPlayedThreshold(u64),
+    SetQueue(Vec<QueueItem>),
+    SetDownload(String, Option<Download>),
+    SetDownloadPolicy(String, DownloadPolicy),
+    SetNetworkMetered(bool),
+    SetEnclosurePreference(EnclosurePreference),
+    SetVideoAsAudio(bool),
+    SetNewEpisodes(String, HashSet<String>),
+    SetFeedCache(String, FeedCache),
     SetSubscriptions(Result<Vec<ChannelRef>, StateError>),
 }
 
@@ -57,8 +102,12 @@ pub struct State {
 
     pub(crate) search_query: String,
     pub(crate) search_focus: Option<ChannelRef>,
+    /// An episode of `search_focus` whose details are shown.
+    pub(crate) search_episode_focus: Option<EpisodeRef>,
 
     pub(crate) home_focus: Option<ChannelRef>,
+    /// An episode of `home_focus` whose details are shown.
+    pub(crate) home_episode_focus: Option<EpisodeRef>,
 
     pub(crate) search_results: Arc<Result<Vec<ChannelRef>, StateError>>,
 
@@ -67,6 +116,21 @@ pub struct State {
     pub(crate) episodes: AMap<Episode>,
     pub(crate) images: AMap<Image>,
     pub(crate) player_state: Arc<Option<PlayerState>>,
+    pub(crate) progress: Arc<HashMap<String, Progress>>,
+    pub(crate) played: Arc<HashSet<String>>,
+    /// Episodes are marked as played when there is less than this much time left (ms).
+    pub(crate) played_threshold: u64,
+    pub(crate) queue: Arc<Vec<QueueItem>>,
+    pub(crate) downloads: Arc<HashMap<String, Download>>,
+    pub(crate) download_policies: Arc<HashMap<String, DownloadPolicy>>,
+    pub(crate) network_metered: bool,
+    pub(crate) enclosure_preference: EnclosurePreference,
+    /// Play only the sound of video episodes, to save data.
+    pub(crate) video_as_audio: bool,
+    /// Episodes that appeared in a subscription since the user last looked at it, by channel.
+    pub(crate) new_episodes: Arc<HashMap<String, HashSet<String>>>,
+    /// Validators for conditional requests, by feed URL.
+    pub(crate) feed_caches: Arc<HashMap<String, FeedCache>>,
 
     pub(crate) subscriptions: Arc<Result<Vec<ChannelRef>, StateError>>,
 
@@ -90,6 +154,10 @@ impl State {
         self.search_focus.as_ref()
     }
 
+    pub fn search_episode_focus(&self) -> Option<&EpisodeRef> {
+        self.search_episode_focus.as_ref()
+    }
+
     pub fn search_results(&self) -> Arc<Result<Vec<ChannelRef>, StateError>> {
         Arc::clone(&self.search_results)
     }
@@ -98,6 +166,10 @@ impl State {
         self.home_focus.as_ref()
     }
 
+    pub fn home_episode_focus(&self) -> Option<&EpisodeRef> {
+        self.home_episode_focus.as_ref()
+    }
+
     pub fn loading(&self) -> bool {
         self.loading
     }
@@ -118,6 +190,81 @@ impl State {
         self.player_state.clone()
     }
 
+    pub fn episode_progress(&self, episode_pk: &str) -> Option<Progress> {
+        self.progress.get(episode_pk).copied()
+    }
+
+    pub fn episode_played(&self, episode_pk: &str) -> bool {
+        self.played.contains(episode_pk)
+    }
+
+    pub fn played_threshold(&self) -> u64 {
+        self.played_threshold
+    }
+
+    pub fn queue(&self) -> Arc<Vec<QueueItem>> {
+        Arc::clone(&self.queue)
+    }
+
+    /// An image that has been requested, by URL.
+    pub fn image(&self, url: &str) -> Option<Arc<Result<Image, StateError>>> {
+        self.images.get(url).cloned()
+    }
+
+    pub fn download(&self, episode_pk: &str) -> Option<&Download> {
+        self.downloads.get(episode_pk)
+    }
+
+    pub fn downloads(&self) -> Arc<HashMap<String, Download>> {
+        Arc::clone(&self.downloads)
+    }
+
+    pub fn download_policy(&self, channel_pk: &str) -> DownloadPolicy {
+        self.download_policies
+            .get(channel_pk)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn network_metered(&self) -> bool {
+        self.network_metered
+    }
+
+    /// Which variant of an episode to stream and download.
+    pub fn enclosure_preference(&self) -> EnclosurePreference {
+        self.enclosure_preference
+    }
+
+    pub fn video_as_audio(&self) -> bool {
+        self.video_as_audio
+    }
+
+    pub fn new_episodes(&self) -> Arc<HashMap<String, HashSet<String>>> {
+        Arc::clone(&self.new_episodes)
+    }
+
+    pub fn new_episode_count(&self, channel_pk: &str) -> usize {
+        self.new_episodes
+            .get(channel_pk)
+            .map(|episodes| episodes.len())
+            .unwrap_or(0)
+    }
+
+    pub fn feed_cache(&self, url: &str) -> Option<&FeedCache> {
+        self.feed_caches.get(url)
+    }
+
+    pub fn feed_caches(&self) -> Arc<HashMap<String, FeedCache>> {
+        Arc::clone(&self.feed_caches)
+    }
+
+    pub fn episode_ref(&self, pk: String) -> EpisodeRef {
+        EpisodeRef {
+            pk,
+            state: Weak::clone(&self.current),
+        }
+    }
+
     pub fn channel_ref(&self, pk: String) -> ChannelRef {
         ChannelRef {
             pk,
@@ -145,6 +292,7 @@ impl State {
                 .iter()
                 .any(|ok| ok.iter().any(|search_item| search_item.pk == channel))
             || self.player_state.iter().any(|ok| ok.channel_pk == channel)
+            || self.queue.iter().any(|item| item.channel_pk == channel)
             || self
                 .subscriptions
                 .iter()
@@ -157,6 +305,11 @@ impl State {
                 .1
                 .iter()
                 .any(|channel| channel.references_image(image))
+        }) || self.channel_detail.iter().any(|channel| {
+            channel
+                .1
+                .iter()
+                .any(|channel| channel.references_image(image))
         }) || self.episodes.iter().any(|episode| {
             episode
                 .1
@@ -172,14 +325,27 @@ impl State {
             allow_explicit: false,
             search_query: String::new(),
             search_focus: None,
+            search_episode_focus: None,
             search_results: Arc::new(Result::Err(StateError::Loading)),
             home_focus: None,
+            home_episode_focus: None,
             channel_core: Default::default(),
             channel_detail: Default::default(),
             episodes: Default::default(),
             images: Default::default(),
             loading: true,
             player_state: Arc::new(Option::None),
+            progress: Default::default(),
+            played: Default::default(),
+            played_threshold: 30000,
+            queue: Default::default(),
+            downloads: Default::default(),
+            download_policies: Default::default(),
+            network_metered: false,
+            enclosure_preference: EnclosurePreference::default(),
+            video_as_audio: false,
+            new_episodes: Default::default(),
+            feed_caches: Default::default(),
             subscriptions: Arc::new(Result::Err(StateError::Loading)),
         }
     }
@@ -197,6 +363,12 @@ impl State {
         let mut next_channel_detail = None;
         let mut next_episodes = None;
         let mut next_images = None;
+        let mut next_progress = None;
+        let mut next_played = None;
+        let mut next_downloads = None;
+        let mut next_download_policies = None;
+        let mut next_new_episodes = None;
+        let mut next_feed_caches = None;
 
         for action in actions {
             match action {
@@ -213,8 +385,14 @@ impl State {
                     }
                 }
                 StateAction::SetSearchFocus(focus) => {
+                    if next.search_focus != focus {
+                        next.search_episode_focus = None;
+                    }
                     next.search_focus = focus;
                 }
+                StateAction::SetSearchEpisodeFocus(focus) => {
+                    next.search_episode_focus = focus;
+                }
                 StateAction::SetSearchFeed { query, results } => {
                     if next.search_query == query {
                         next.search_results = Arc::new(results.map(|results| {
@@ -229,10 +407,21 @@ impl State {
                     }
                 }
                 StateAction::SetHomeFocus(focus) => {
+                    if next.home_focus != focus {
+                        next.home_episode_focus = None;
+                    }
                     next.home_focus = focus;
                 }
+                StateAction::SetHomeEpisodeFocus(focus) => {
+                    next.home_episode_focus = focus;
+                }
 
                 StateAction::SetChannelCore(pk, mut core) => {
+                    let cores = next_channel_core.as_ref().unwrap_or(&*self.channel_core);
+                    // Keep showing what we loaded (e.g., from the database) when a refresh fails.
+                    if core.is_err() && cores.get(&pk).map_or(false, |core| core.is_ok()) {
+                        continue;
+                    }
                     if next.references_channel(&pk) {
                         if let Ok(core) = &mut core {
                             if let Some(curr_core) = self.channel_core.get(&pk) {
@@ -247,14 +436,43 @@ impl State {
                             .insert(pk, Arc::new(core));
                     }
                 }
+                StateAction::SetChannelRss(pk, rss) => {
+                    let cores =
+                        next_channel_core.get_or_insert_with(|| (*self.channel_core).clone());
+                    if let Some(Ok(core)) = cores.get(&pk).map(|core| (**core).clone()) {
+                        let core = core.with_rss(rss).with_rss_moved(true);
+                        cores.insert(pk, Arc::new(Ok(core)));
+                    }
+                }
+                StateAction::SetChannelEnded(pk, ended) => {
+                    let cores =
+                        next_channel_core.get_or_insert_with(|| (*self.channel_core).clone());
+                    if let Some(Ok(core)) = cores.get(&pk).map(|core| (**core).clone()) {
+                        cores.insert(pk, Arc::new(Ok(core.with_ended(ended))));
+                    }
+                }
                 StateAction::SetChannelDetail(pk, detail) => {
+                    let details = next_channel_detail
+                        .as_ref()
+                        .unwrap_or(&*self.channel_detail);
+                    if detail.is_err() && details.get(&pk).map_or(false, |detail| detail.is_ok()) {
+                        continue;
+                    }
                     if next.references_channel(&pk) {
                         next_channel_detail
                             .get_or_insert_with(|| (*self.channel_detail).clone())
                             .insert(pk, Arc::new(detail));
                     }
                 }
-                StateAction::SetEpisode(pk, episode) => {
+                StateAction::SetEpisode(pk, mut episode) => {
+                    if let Ok(episode) = &mut episode {
+                        if let Some(curr_episode) = self.episodes.get(&pk) {
+                            if let Ok(curr_episode) = curr_episode.as_ref() {
+                                episode.update(curr_episode);
+                            }
+                        }
+                    }
+
                     next_episodes
                         .get_or_insert_with(|| (*self.episodes).clone())
                         .insert(pk, Arc::new(episode));
@@ -270,8 +488,83 @@ impl State {
                     next.loading = loading;
                 }
                 StateAction::SetPlayerState(player_state) => {
+                    if let Some(player_state) = &player_state {
+                        // Time is 0 before the player has loaded anything.
+                        if player_state.duration > 0 {
+                            next_progress
+                                .get_or_insert_with(|| (*self.progress).clone())
+                                .insert(
+                                    player_state.episode_pk.clone(),
+                                    Progress {
+                                        time: player_state.time,
+                                        duration: player_state.duration,
+                                    },
+                                );
+                        }
+                    }
                     next.player_state = Arc::new(player_state);
                 }
+                StateAction::SetProgress(progress) => {
+                    let next_progress =
+                        next_progress.get_or_insert_with(|| (*self.progress).clone());
+                    for (episode_pk, progress) in progress {
+                        next_progress.insert(episode_pk, progress);
+                    }
+                }
+                StateAction::SetPlayed(episode_pk, played) => {
+                    let next_played = next_played.get_or_insert_with(|| (*self.played).clone());
+                    if played {
+                        next_played.insert(episode_pk);
+                    } else {
+                        next_played.remove(&episode_pk);
+                    }
+                }
+                StateAction::SetPlayedThreshold(played_threshold) => {
+                    next.played_threshold = played_threshold;
+                }
+                StateAction::SetQueue(queue) => {
+                    next.queue = Arc::new(queue);
+                }
+                StateAction::SetDownload(episode_pk, download) => {
+                    let next_downloads =
+                        next_downloads.get_or_insert_with(|| (*self.downloads).clone());
+                    match download {
+                        Some(download) => {
+                            next_downloads.insert(episode_pk, download);
+                        }
+                        None => {
+                            next_downloads.remove(&episode_pk);
+                        }
+                    }
+                }
+                StateAction::SetDownloadPolicy(channel_pk, policy) => {
+                    next_download_policies
+                        .get_or_insert_with(|| (*self.download_policies).clone())
+                        .insert(channel_pk, policy);
+                }
+                StateAction::SetNetworkMetered(network_metered) => {
+                    next.network_metered = network_metered;
+                }
+                StateAction::SetEnclosurePreference(preference) => {
+                    next.enclosure_preference = preference;
+                }
+                StateAction::SetVideoAsAudio(video_as_audio) => {
+                    next.video_as_audio = video_as_audio;
+                }
+                StateAction::SetNewEpisodes(channel_pk, episodes) => {
+                    let next_new_episodes =
+                        next_new_episodes.get_or_insert_with(|| (*self.new_episodes).clone());
+                    if episodes.is_empty() {
+                        next_new_episodes.remove(&channel_pk);
+                    } else {
+                        next_new_episodes.insert(channel_pk, episodes);
+                    }
+                }
+                StateAction::SetFeedCache(url, feed_cache) => {
+                    next_feed_caches
+                        .get_or_insert_with(|| (*self.feed_caches).clone())
+                        .insert(url, feed_cache);
+                }
                 StateAction::SetSubscriptions(subscriptions) => {
                     next.subscriptions = Arc::new(subscriptions);
                 }
@@ -290,6 +583,24 @@ impl State {
         if let Some(images) = next_images {
             next.images = Arc::new(images);
         }
+        if let Some(progress) = next_progress {
+            next.progress = Arc::new(progress);
+        }
+        if let Some(played) = next_played {
+            next.played = Arc::new(played);
+        }
+        if let Some(downloads) = next_downloads {
+            next.downloads = Arc::new(downloads);
+        }
+        if let Some(download_policies) = next_download_policies {
+            next.download_policies = Arc::new(download_policies);
+        }
+        if let Some(new_episodes) = next_new_episodes {
+            next.new_episodes = Arc::new(new_episodes);
+        }
+        if let Some(feed_caches) = next_feed_caches {
+            next.feed_caches = Arc::new(feed_caches);
+        }
 
         next
     }
@@ -421,4 +732,148 @@ mod tests {
         );
         assert!(current_state.get().channel_core.get("invalid").is_none());
     }
+
+    #[test]
+    fn progress() {
+        let (current_state, mut wait_for_update) = CurrentState::new();
+        // Wait for the initial notification.
+        while wait_for_update.try_next().is_err() {}
+
+        current_state.update(vec![StateAction::SetProgress(vec![(
+            String::from("ep1"),
+            Progress {
+                time: 60000,
+                duration: 3600000,
+            },
+        )])]);
+        while wait_for_update.try_next().is_err() {}
+        assert_eq!(
+            current_state.get().episode_progress("ep1"),
+            Some(Progress {
+                time: 60000,
+                duration: 3600000
+            })
+        );
+
+        current_state.update(vec![StateAction::SetPlayerState(Some(PlayerState {
+            episode_pk: String::from("ep1"),
+            channel_pk: String::from("tal"),
+            playback: Playback::Playing,
+            time: 120000,
+            duration: 3600000,
+            rate: 1.0,
+            video: false,
+        }))]);
+        while wait_for_update.try_next().is_err() {}
+        let progress = current_state.get().episode_progress("ep1").unwrap();
+        assert_eq!(progress.time, 120000);
+        assert_eq!(progress.remaining(), Some(3480000));
+        assert!(progress.resumable());
+        assert_eq!(current_state.get().episode_progress("ep2"), None);
+    }
+
+    #[test]
+    fn played() {
+        let (current_state, mut wait_for_update) = CurrentState::new();
+        // Wait for the initial notification.
+        while wait_for_update.try_next().is_err() {}
+
+        current_state.update(vec![
+            StateAction::SetPlayed(String::from("ep1"), true),
+            StateAction::SetPlayed(String::from("ep2"), true),
+            StateAction::SetPlayed(String::from("ep1"), false),
+        ]);
+        while wait_for_update.try_next().is_err() {}
+        assert!(!current_state.get().episode_played("ep1"));
+        assert!(current_state.get().episode_played("ep2"));
+    }
+
+    #[test]
+    fn moved_feed() {
+        let (current_state, mut wait_for_update) = CurrentState::new();
+        // Wait for the initial notification.
+        while wait_for_update.try_next().is_err() {}
+
+        let itunes_core = || {
+            current_state
+                .get()
+                .new_channel_core()
+                .with_pk(String::from("tal"))
+                .with_rss(String::from("https://old.example.com/feed"))
+                .build()
+        };
+        current_state.update(vec![
+            StateAction::SetSubscriptions(Ok(vec![current_state
+                .get()
+                .channel_ref(String::from("tal"))])),
+            itunes_core(),
+            StateAction::SetChannelRss(
+                String::from("tal"),
+                String::from("https://new.example.com/feed"),
+            ),
+        ]);
+        while wait_for_update.try_next().is_err() {}
+
+        // Directories still have the old URL.
+        current_state.update(vec![
+            itunes_core(),
+            StateAction::SetChannelEnded(String::from("tal"), true),
+        ]);
+        while wait_for_update.try_next().is_err() {}
+
+        let core = current_state.get().channel_ref(String::from("tal")).core();
+        let core = core.as_deref().unwrap().as_ref().unwrap();
+        assert_eq!(core.rss(), Some("https://new.example.com/feed"));
+        assert!(core.rss_moved());
+        assert!(core.ended());
+    }
+
+    #[test]
+    fn offline_refresh() {
+        let (current_state, mut wait_for_update) = CurrentState::new();
+        // Wait for the initial notification.
+        while wait_for_update.try_next().is_err() {}
+
+        // Loaded from the database at startup.
+        current_state.update(vec![
+            StateAction::SetSubscriptions(Ok(vec![current_state
+                .get()
+                .channel_ref(String::from("tal"))])),
+            current_state
+                .get()
+                .new_channel_core()
+                .with_pk(String::from("tal"))
+                .with_title(String::from("This American Life"))
+                .build(),
+            current_state
+                .get()
+                .new_channel_detail()
+                .with_pk(String::from("tal"))
+                .build(),
+        ]);
+        while wait_for_update.try_next().is_err() {}
+
+        // The refresh fails.
+        let err = StateError::from(std::io::Error::new(std::io::ErrorKind::Other, "offline"));
+        current_state.update(vec![
+            StateAction::SetChannelCore(String::from("tal"), Err(err.clone())),
+            StateAction::SetChannelDetail(String::from("tal"), Err(err.clone())),
+            StateAction::SetSubscriptions(Ok(vec![
+                current_state.get().channel_ref(String::from("tal")),
+                current_state.get().channel_ref(String::from("new")),
+            ])),
+            StateAction::SetChannelCore(String::from("new"), Err(err)),
+        ]);
+        while wait_for_update.try_next().is_err() {}
+
+        let channel = current_state.get().channel_ref(String::from("tal"));
+        let core = channel.core();
+        let core = core.as_deref().unwrap().as_ref().unwrap();
+        assert_eq!(core.title(), "This American Life");
+        assert!(matches!(channel.details().as_deref(), Some(Ok(_))));
+
+        // Channels we have nothing for still show the error.
+        let channel = current_state.get().channel_ref(String::from("new"));
+        assert!(matches!(channel.core().as_deref(), Some(Err(_))));
+    }
 }
@@ -0,0 +1,10 @@
+/// Where a show or episode is set or recorded, from podcast:location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// Human readable, like "Austin, TX".
+    pub name: String,
+    /// A geo URI, like "geo:30.2672,97.7431".
+    pub geo: Option<String>,
+    /// An OpenStreetMap object, like "R113314".
+    pub osm: Option<String>,
+}
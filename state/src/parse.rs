@@ -0,0 +1,226 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+use std::time::Duration;
+
+/// Parses an itunes:duration, which feeds write as "HH:MM:SS", "MM:SS", or seconds, sometimes
+/// with a fraction.
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim();
+    if duration.is_empty() {
+        return None;
+    }
+
+    let parts: Vec<&str> = duration.split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    for part in parts {
+        let part: f64 = part.trim().parse().ok()?;
+        if !part.is_finite() || part < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + part;
+    }
+
+    Some(Duration::from_millis((seconds * 1000.0) as u64))
+}
+
+fn parse_month(month: &str) -> Option<u32> {
+    let month = month.get(..3)?.to_ascii_lowercase();
+    let months = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    months
+        .iter()
+        .position(|name| *name == month)
+        .map(|idx| idx as u32 + 1)
+}
+
+/// Seconds east of UTC, for offsets like "+0200" or "-05:00" and the zone names feeds use.
+fn parse_offset(zone: &str) -> Option<i32> {
+    let hours = match zone.to_ascii_uppercase().as_str() {
+        "" | "Z" | "GMT" | "UT" | "UTC" => Some(0),
+        "EDT" => Some(-4),
+        "EST" | "CDT" => Some(-5),
+        "CST" | "MDT" => Some(-6),
+        "MST" | "PDT" => Some(-7),
+        "PST" => Some(-8),
+        "BST" | "CET" => Some(1),
+        "CEST" => Some(2),
+        _ => None,
+    };
+    if let Some(hours) = hours {
+        return Some(hours * 3600);
+    }
+
+    let sign = match zone.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let digits: String = zone[1..].chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parses dates shaped like RFC 2822 ("Tue, 10 Jun 2003 04:00:00 GMT"), forgiving the mistakes
+/// feeds make: wrong or missing weekdays, full month names, two digit years, missing seconds and
+/// zone names that RFC 2822 doesn't allow.
+fn parse_rfc2822ish(date: &str) -> Option<DateTime<FixedOffset>> {
+    // Skip the weekday, which is often wrong.
+    let date = match date.find(',') {
+        Some(comma) => &date[comma + 1..],
+        None => date,
+    };
+    let mut parts = date.split_whitespace();
+
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = parse_month(parts.next()?)?;
+    let year: i32 = match parts.next()? {
+        year if year.len() == 2 => {
+            let year: i32 = year.parse().ok()?;
+            if year < 70 {
+                2000 + year
+            } else {
+                1900 + year
+            }
+        }
+        year => year.parse().ok()?,
+    };
+
+    let (hour, minute, second) = match parts.next() {
+        Some(time) => {
+            let mut time = time.split(':');
+            let hour: u32 = time.next()?.parse().ok()?;
+            let minute: u32 = time.next()?.parse().ok()?;
+            let second: u32 = match time.next() {
+                Some(second) => second.split('.').next()?.parse().ok()?,
+                None => 0,
+            };
+            (hour, minute, second)
+        }
+        None => (0, 0, 0),
+    };
+    let offset = parse_offset(parts.next().unwrap_or_default())?;
+
+    let naive = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, second)?;
+    FixedOffset::east_opt(offset)?
+        .from_local_datetime(&naive)
+        .single()
+}
+
+/// Parses a pubDate (RFC 2822) or an Atom date (RFC 3339), and the variations of both that show
+/// up in real feeds. Dates without a time zone are taken to be UTC.
+pub fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+    if date.is_empty() {
+        return None;
+    }
+
+    if let Ok(date) = DateTime::parse_from_rfc2822(date) {
+        return Some(date);
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date);
+    }
+    if let Some(date) = parse_rfc2822ish(date) {
+        return Some(date);
+    }
+
+    let utc = FixedOffset::east(0);
+    for format in &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(date, format) {
+            return utc.from_local_datetime(&date).single();
+        }
+    }
+    for format in &["%Y-%m-%dT%H:%M:%S%z", "%Y-%m-%d %H:%M:%S %z"] {
+        if let Ok(date) = DateTime::parse_from_str(date, format) {
+            return Some(date);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        return utc.from_local_datetime(&date.and_hms(0, 0, 0)).single();
+    }
+
+    None
+}
+
+/// Show notes link timestamps with this scheme, so the app can seek instead of opening them.
+const TIMESTAMP_SCHEME: &str = "pyrocast-time:";
+
+/// The href of a link to a time in the episode, in ms.
+pub fn timestamp_href(time: u64) -> String {
+    format!("{}{}", TIMESTAMP_SCHEME, time)
+}
+
+/// The time a timestamp link points to, or None for other links.
+pub fn timestamp_from_href(href: &str) -> Option<u64> {
+    href.strip_prefix(TIMESTAMP_SCHEME)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        let seconds = |duration: &str| parse_duration(duration).map(|d| d.as_millis());
+
+        assert_eq!(seconds("01:02:03"), Some(3_723_000));
+        assert_eq!(seconds("62:03"), Some(3_723_000));
+        assert_eq!(seconds("3723"), Some(3_723_000));
+        assert_eq!(seconds(" 45:00 "), Some(2_700_000));
+        assert_eq!(seconds("12.5"), Some(12_500));
+        assert_eq!(seconds("1:02:03.250"), Some(3_723_250));
+        assert_eq!(seconds(""), None);
+        assert_eq!(seconds("about an hour"), None);
+        assert_eq!(seconds("-5"), None);
+        assert_eq!(seconds("1:2:3:4"), None);
+    }
+
+    #[test]
+    fn parses_dates() {
+        let date = |date: &str| parse_date(date).map(|date| date.to_rfc3339());
+
+        assert_eq!(
+            date("Tue, 10 Jun 2003 04:00:00 GMT").as_deref(),
+            Some("2003-06-10T04:00:00+00:00")
+        );
+        assert_eq!(
+            date("2020-10-01T12:30:00-04:00").as_deref(),
+            Some("2020-10-01T12:30:00-04:00")
+        );
+        // Wrong weekday, full names, no seconds, and a zone name RFC 2822 doesn't allow.
+        assert_eq!(
+            date("Monday, 10 June 2003 04:00 UTC").as_deref(),
+            Some("2003-06-10T04:00:00+00:00")
+        );
+        assert_eq!(
+            date("Wed, 2 Sep 20 18:05:00 PDT").as_deref(),
+            Some("2020-09-02T18:05:00-07:00")
+        );
+        assert_eq!(
+            date("Thu, 03 Sep 2020 09:00:00 +02:00").as_deref(),
+            Some("2020-09-03T09:00:00+02:00")
+        );
+        assert_eq!(
+            date("03 Sep 2020").as_deref(),
+            Some("2020-09-03T00:00:00+00:00")
+        );
+        assert_eq!(
+            date("2020-09-03 09:00:00").as_deref(),
+            Some("2020-09-03T09:00:00+00:00")
+        );
+        assert_eq!(
+            date("2020-09-03").as_deref(),
+            Some("2020-09-03T00:00:00+00:00")
+        );
+        assert_eq!(date("").as_deref(), None);
+        assert_eq!(date("last Tuesday").as_deref(), None);
+    }
+}
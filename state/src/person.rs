@@ -0,0 +1,22 @@
+/// Someone who works on a show or an episode, from podcast:person.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Person {
+    pub name: String,
+    /// Lowercase, like "host" or "guest".
+    pub role: String,
+    /// Lowercase, like "cast" or "writing".
+    pub group: String,
+    pub image: Option<String>,
+    /// A page about the person.
+    pub href: Option<String>,
+}
+
+impl Person {
+    pub fn is_host(&self) -> bool {
+        self.role == "host"
+    }
+
+    pub fn is_guest(&self) -> bool {
+        self.role == "guest"
+    }
+}
@@ -4,6 +4,8 @@ pub enum Playback {
     Buffering,
     Paused,
     Playing,
+    /// Stopped because the stream reached its end.
+    Ended,
 }
 
 impl Playback {
@@ -23,4 +25,6 @@ pub struct PlayerState {
     pub time: u64,
     pub duration: u64,
     pub rate: f64,
+    /// Whether the stream has video, and it is being shown.
+    pub video: bool,
 }
@@ -0,0 +1,24 @@
+/// How far into an episode the user has listened.
+///
+/// Times are in milliseconds, like `PlayerState`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Progress {
+    pub time: u64,
+    pub duration: u64,
+}
+
+impl Progress {
+    /// Remaining time, or None if the duration is not yet known.
+    pub fn remaining(&self) -> Option<u64> {
+        if self.duration == 0 {
+            None
+        } else {
+            Some(self.duration.saturating_sub(self.time))
+        }
+    }
+
+    /// Whether there is a position worth resuming from.
+    pub fn resumable(&self) -> bool {
+        self.time > 0 && matches!(self.remaining(), Some(remaining) if remaining > 30000)
+    }
+}
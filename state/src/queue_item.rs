@@ -0,0 +1,10 @@
+/// An episode waiting to be played.
+///
+/// This has everything the player needs, so queued episodes can be played even if their
+/// feed has not been loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueItem {
+    pub episode_pk: String,
+    pub channel_pk: String,
+    pub uri: String,
+}
@@ -0,0 +1,47 @@
+/// A podcast:transcript link from a feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptLink {
+    pub url: String,
+    /// The mimetype the feed claims, like "text/vtt" or "application/srt".
+    pub mimetype: Option<String>,
+}
+
+/// A timed piece of an episode's transcript, parsed from SRT, WebVTT, JSON or HTML.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Milliseconds from the start of the episode.
+    pub start: u64,
+    /// Milliseconds from the start of the episode, if the format says when the segment ends.
+    pub end: Option<u64>,
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+/// The index of the segment being spoken at `time` (in milliseconds), given segments sorted by
+/// start. Between segments, this is the one that was spoken last.
+pub fn segment_at(segments: &[Segment], time: u64) -> Option<usize> {
+    segments.iter().rposition(|segment| segment.start <= time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_current_segment() {
+        let segments: Vec<Segment> = [1_000, 4_000, 9_000]
+            .iter()
+            .map(|start| Segment {
+                start: *start,
+                end: Some(start + 2_000),
+                speaker: None,
+                text: format!("{}", start),
+            })
+            .collect();
+
+        assert_eq!(segment_at(&segments, 0), None);
+        assert_eq!(segment_at(&segments, 1_500), Some(0));
+        assert_eq!(segment_at(&segments, 7_000), Some(1));
+        assert_eq!(segment_at(&segments, 9_000), Some(2));
+    }
+}